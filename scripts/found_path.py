@@ -3,20 +3,15 @@ from datetime import datetime
 import contextily as ctx
 import geopandas as gpd
 import matplotlib.pyplot as plt
-import pandas as pd
-from pyproj import Transformer
-from shapely.geometry import LineString
+from shapely.geometry import Point
 
-# Load the CSV file into a DataFrame
-csv_file = "results/analysis/worst_case_map.csv"
-df = pd.read_csv(csv_file)
+# Load the worst case path, a single LineString, and transform it from EPSG:4326 to EPSG:3857
+geojson_file = "results/analysis/worst_case_map.geojson"
+line_gdf = gpd.read_file(geojson_file).to_crs("EPSG:3857")
+line = line_gdf.geometry.iloc[0]
 
-# Transform the coordinates from EPSG:4326 to EPSG:3857
-transformer = Transformer.from_crs("EPSG:4326", "EPSG:3857", always_xy=True)
-df['x'], df['y'] = transformer.transform(df['lon'].values, df['lat'].values)
-
-# Create a GeoDataFrame
-gdf = gpd.GeoDataFrame(df, geometry=gpd.points_from_xy(df['x'], df['y']), crs="EPSG:3857")
+# Create a GeoDataFrame with a point per node of the path
+gdf = gpd.GeoDataFrame(geometry=[Point(coordinate) for coordinate in line.coords], crs="EPSG:3857")
 
 # Get the bounding box of the GeoDataFrame
 minx, miny, maxx, maxy = gdf.total_bounds
@@ -36,9 +31,6 @@ ctx.add_basemap(ax, source="https://tile.openstreetmap.org/{z}/{x}/{y}.png", zoo
 # Plot the GeoDataFrame points
 gdf.plot(ax=ax, marker='o', color='red', markersize=5)
 
-# Create a LineString from the points
-line = LineString(gdf.geometry.tolist())
-
 # Plot the line with green color and alpha of 0.5
 gpd.GeoSeries([line], crs="EPSG:3857").plot(ax=ax, color='green', linewidth=2, alpha=0.5)
 
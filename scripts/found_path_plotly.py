@@ -1,17 +1,18 @@
+import json
 import webbrowser
 from pathlib import Path
 
-import pandas as pd
 import plotly.graph_objects as go
 
-# Load the CSV file into a DataFrame
-csv_file = "results/analysis/worst_case_map.csv"
-df = pd.read_csv(csv_file)
+# Load the worst case path, a single GeoJSON LineString feature
+geojson_file = "results/analysis/worst_case_map.geojson"
+with open(geojson_file) as file:
+    feature = json.load(file)["features"][0]
 
-# Ensure lat and lon are provided as lists
-latitudes = df['lat'].tolist()  # Extract latitudes as a list
-longitudes = df['lon'].tolist()  # Extract longitudes as a list
-node_ids = df['node_id'].tolist()  # Extract node_id as a list
+# GeoJSON coordinates are [lon, lat] pairs
+longitudes = [coordinate[0] for coordinate in feature["geometry"]["coordinates"]]
+latitudes = [coordinate[1] for coordinate in feature["geometry"]["coordinates"]]
+node_ids = feature["properties"]["node_ids"].split(",")
 
 # Initialize the figure
 fig = go.Figure()
@@ -1,36 +1,24 @@
 use core::f64;
 use std::fs::File;
-use std::io::Write;
 use std::path::Path;
 
-use crate::data_handling::OSMData;
+use crate::data_handling::feature_export::write_geojson;
+use crate::data_handling::{ClipArea, MapFeature, OSMData};
 use crate::path_finding::nearest_road::find_closest_road_coordinate;
 use crate::path_finding::path_finding::path_finding;
 use crate::path_finding::TransportMode;
-use crate::utils::geographic_areas::GeographicArea;
-use geo::GeodesicBearing;
+use geo::{GeodesicBearing, Geometry};
 #[allow(unused)]
 use log::{info, warn};
 use polars::prelude::*;
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
 
-pub fn deviation_great_circle(osm_data: &OSMData) {
+/// Compares path lengths with great-circle distances between random points in the analysis area,
+/// e.g. a bounding box or `osm_data.administrative_area("Weert", Some(8))`.
+pub fn deviation_great_circle(osm_data: &OSMData, analysis_area: &ClipArea) {
     let maximum_samples = 10000;
 
-    let minimum_latitude = 49.02110;
-    let maximum_latitude = 49.03956;
-
-    let minimum_longitude = 104.03546;
-    let maximum_longitude = 104.06881;
-
-    let geographic_area = GeographicArea::new(
-        minimum_latitude,
-        maximum_latitude,
-        minimum_longitude,
-        maximum_longitude,
-    );
-
     let mut coordinate_1_x = Vec::new();
     let mut coordinate_1_y = Vec::new();
     let mut coordinate_2_x = Vec::new();
@@ -46,8 +34,8 @@ pub fn deviation_great_circle(osm_data: &OSMData) {
     let mut worst_performer_path: Vec<u64> = Vec::new();
 
     for _ in 0..maximum_samples {
-        let coordinate_1 = geographic_area.random_coordinate(&mut rng);
-        let coordinate_2 = geographic_area.random_coordinate(&mut rng);
+        let coordinate_1 = analysis_area.random_coordinate(&mut rng);
+        let coordinate_2 = analysis_area.random_coordinate(&mut rng);
 
         let (road_node_id_1, _) = find_closest_road_coordinate(osm_data, coordinate_1);
         let (road_node_id_2, _) = find_closest_road_coordinate(osm_data, coordinate_2);
@@ -107,20 +95,35 @@ pub fn deviation_great_circle(osm_data: &OSMData) {
         }
     }
 
-    let mut worst_path_file = File::create(Path::new("results/analysis/worst_case_map.csv"))
-        .expect("Failed to create file");
-
-    worst_path_file
-        .write("lat,lon,node_id\n".as_bytes())
-        .expect("Failed to write headers");
-
-    for node_id in worst_performer_path.iter() {
-        let coordinate = osm_data.node_map.get(node_id).unwrap().coordinate;
-
-        worst_path_file
-            .write(format!("{},{},{}\n", coordinate.y(), coordinate.x(), node_id).as_bytes())
-            .expect("Failed to write line");
-    }
+    let worst_path = MapFeature {
+        id: worst_performer_path.first().copied().unwrap_or_default(),
+        geometry: Geometry::LineString(
+            worst_performer_path
+                .iter()
+                .map(|node_id| osm_data.node_map[node_id].coordinate)
+                .collect(),
+        ),
+        // The node IDs are read by scripts/found_path_plotly.py.
+        tags: [
+            ("path_ratio", worst_performer_ratio.to_string()),
+            (
+                "node_ids",
+                worst_performer_path
+                    .iter()
+                    .map(|node_id| node_id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+            ),
+        ]
+        .into_iter()
+        .collect(),
+    };
+
+    write_geojson(
+        &[worst_path],
+        Path::new("results/analysis/worst_case_map.geojson"),
+    )
+    .expect("Failed to write worst case map");
 
     let df = DataFrame::new(vec![
         Series::new("coordinate_1_x".into(), coordinate_1_x),
@@ -1,18 +1,44 @@
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::OnceLock;
 
+use chrono::{DateTime, Utc};
+use geo::{Geometry, MultiPolygon, Point};
+use memmap2::Mmap;
 use serde::{Deserialize, Serialize};
 
-use crate::osm_parsing::{Node, Way};
+use crate::osm_parsing::{MetadataStore, Node, Relation, Tags, Way};
+use crate::path_finding::{ConnectedComponents, SpeedProfiles};
+use crate::utils::geographic_areas::GeographicArea;
 
+pub mod areas;
+pub mod boundaries;
+pub mod changes;
+pub mod clipping;
 pub mod data_handling;
+pub mod feature_export;
 pub mod filtering;
+pub mod mapped_map;
+pub mod metadata;
 pub mod searching;
+pub mod validation;
 
 #[derive(Default, Debug)]
 pub struct OSMData {
     pub node_map: HashMap<u64, Node>,
     pub way_map: HashMap<u64, Way>,
+    pub relation_map: HashMap<u64, Relation>,
+    /// Areas kept as landmarks, keyed by the ID of the node that represents them in `node_map`.
+    pub area_map: HashMap<u64, Area>,
     pub node_subsets: Vec<NodeSubset>,
+    pub build_settings: BuildSettings,
+    /// The last replication diff applied with `apply_replication_diff`, if any.
+    pub replication_state: Option<ReplicationState>,
+    /// Version, timestamp and author of the elements, if kept with `ParseOptions::keep_metadata`.
+    pub metadata: Option<MetadataStore>,
+    pub speed_profiles: Option<SpeedProfiles>,
+    /// Components of the unrestricted road graph, computed on first use and reset by `update_road_nodes`.
+    pub road_components: OnceLock<ConnectedComponents>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,7 +47,7 @@ pub struct NodeSubset {
     pub node_subset: HashSet<u64>,
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FilterSubset {
     Roads,
     Landmark(String),
@@ -35,3 +61,141 @@ pub struct FilterSet {
     pub filter_values: HashSet<String>,
     pub filter_subset: FilterSubset,
 }
+
+/// How a map was built from its source file. Stored in the map cache, so a cache can be checked against
+/// the settings it is expected to have been built with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildSettings {
+    pub filters: Vec<AppliedFilter>,
+    /// Bounding box (minimum longitude, minimum latitude, maximum longitude, maximum latitude) of the
+    /// area the map was clipped to.
+    pub clip_bounds: Option<[f64; 4]>,
+    pub minimum_component_size: Option<usize>,
+}
+
+/// Position of a map in a replication stream, as read from the `state.txt` next to each diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicationState {
+    pub sequence_number: u64,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// What applying a change file did. Changes to elements that the map's filters do not keep are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSummary {
+    pub created: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub ignored: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedFilter {
+    pub filter_key: String,
+    /// Sorted, so that the settings do not depend on hash order.
+    pub filter_values: Vec<String>,
+    pub filter_subset: FilterSubset,
+}
+
+/// A closed way or multipolygon relation. It is represented in `node_map` by a landmark node at its
+/// representative point, carrying its tags, so it can be searched and routed to like any other landmark.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Area {
+    pub node_id: u64,
+    pub source: AreaSource,
+    pub geometry: MultiPolygon,
+    /// A point guaranteed to lie inside the area, unlike the centroid of e.g. a U-shaped building.
+    pub representative_point: Point,
+    pub centroid: Point,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AreaSource {
+    Way(u64),
+    Relation(u64),
+}
+
+/// Part of a map to export with `OSMData::export_features`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeatureSelection {
+    /// The nodes of the subset built with this filter subset, as points.
+    Subset(FilterSubset),
+    /// All ways, as line strings.
+    Ways,
+    /// Assembled areas, as multipolygons.
+    Areas,
+}
+
+/// A node, way or area with its geometry and tags, as written to GeoJSON and FlatGeobuf files.
+#[derive(Debug, Clone)]
+pub struct MapFeature {
+    /// The ID of the node or way, or of the way or relation an area was assembled from.
+    pub id: u64,
+    pub geometry: Geometry,
+    pub tags: Tags,
+}
+
+/// Region to clip a map to.
+#[derive(Debug, Clone)]
+pub enum ClipArea {
+    BoundingBox(GeographicArea),
+    /// E.g. loaded from an Osmosis `.poly` file.
+    Polygon(MultiPolygon),
+}
+
+/// A problem found in the loaded map by `OSMData::validate`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationIssue {
+    /// The way references a node that is not in the extract, e.g. because it was clipped.
+    DanglingNodeReference {
+        way_id: u64,
+        node_id: u64,
+    },
+    /// Two different nodes at the same location follow each other in the way.
+    ZeroLengthSegment {
+        way_id: u64,
+        from_node_id: u64,
+        to_node_id: u64,
+    },
+    /// The same node is listed twice in a row.
+    DuplicateConsecutiveNode {
+        way_id: u64,
+        node_id: u64,
+    },
+    /// The way turns around at the node and goes back the way it came, as in A-B-A.
+    DoublesBack {
+        way_id: u64,
+        node_id: u64,
+    },
+    UnparseableMaxSpeed {
+        way_id: u64,
+        max_speed: String,
+    },
+    UntaggedWay {
+        way_id: u64,
+    },
+}
+
+/// All issues found in a map, ordered by way ID.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// A map in the flat layout written by `MappedMap::write`, memory-mapped so that routing and tag lookups
+/// read straight from the file instead of deserializing it first.
+#[derive(Debug)]
+pub struct MappedMap {
+    mmap: Mmap,
+    /// Byte ranges of the arrays in the file, indexed by `MappedSection`.
+    sections: Vec<Range<usize>>,
+    pub speed_profiles: Option<SpeedProfiles>,
+}
+
+/// A way read from a `MappedMap`.
+#[derive(Debug, Clone, Copy)]
+pub struct MappedWay<'a> {
+    map: &'a MappedMap,
+    way_index: usize,
+}
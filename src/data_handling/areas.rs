@@ -0,0 +1,165 @@
+use geo::{Centroid, Coord, InteriorPoint, LineString, MultiPolygon, Polygon};
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::{Area, AreaSource, FilterSet, FilterSubset, OSMData};
+use crate::osm_parsing::{Node, Tags, Way};
+
+/// Area nodes get IDs far above any OSM node ID, offset by the ID of the way or relation they come from.
+pub const WAY_AREA_NODE_OFFSET: u64 = 1 << 62;
+pub const RELATION_AREA_NODE_OFFSET: u64 = 3 << 61;
+
+/// Closed ways with one of these keys are areas, unless tagged `area=no`.
+const AREA_KEYS: [&str; 16] = [
+    "aeroway",
+    "amenity",
+    "building",
+    "historic",
+    "landuse",
+    "leisure",
+    "man_made",
+    "military",
+    "natural",
+    "office",
+    "place",
+    "public_transport",
+    "railway",
+    "shop",
+    "tourism",
+    "water",
+];
+
+impl Area {
+    pub fn way_id(&self) -> Option<u64> {
+        match self.source {
+            AreaSource::Way(way_id) => Some(way_id),
+            AreaSource::Relation(_) => None,
+        }
+    }
+
+    pub fn relation_id(&self) -> Option<u64> {
+        match self.source {
+            AreaSource::Way(_) => None,
+            AreaSource::Relation(relation_id) => Some(relation_id),
+        }
+    }
+}
+
+impl OSMData {
+    /// Assembles the closed ways and multipolygon relations that one of the landmark filters would keep,
+    /// and adds a landmark node for each. Runs as part of `filter`, while the ways still exist.
+    pub fn assemble_areas(&mut self, filters: &[FilterSet]) {
+        let mut areas: Vec<(Area, Tags)> = Vec::new();
+
+        for way in self.way_map.values() {
+            if !is_area_way(way) || !matches_landmark_filter(&way.tags, filters) {
+                continue;
+            }
+
+            let Some(polygon) = self.way_polygon(way) else {
+                warn!("Area way {} references missing nodes", way.id);
+                continue;
+            };
+
+            if let Some(area) = area(
+                WAY_AREA_NODE_OFFSET + way.id,
+                AreaSource::Way(way.id),
+                MultiPolygon::new(vec![polygon]),
+            ) {
+                areas.push((area, way.tags.clone()));
+            }
+        }
+
+        for relation in self.relation_map.values() {
+            if relation.tags.get("type") != Some("multipolygon")
+                || !matches_landmark_filter(&relation.tags, filters)
+            {
+                continue;
+            }
+
+            let multi_polygon = match self.relation_multipolygon(relation) {
+                Ok(multi_polygon) => multi_polygon,
+                Err(error) => {
+                    warn!("Failed to assemble relation {}: {}", relation.id, error);
+                    continue;
+                }
+            };
+
+            if let Some(area) = area(
+                RELATION_AREA_NODE_OFFSET + relation.id,
+                AreaSource::Relation(relation.id),
+                multi_polygon,
+            ) {
+                let mut tags = relation.tags.clone();
+                tags.remove("type");
+                areas.push((area, tags));
+            }
+        }
+
+        info!("Assembled {} areas", areas.len());
+
+        for (area, tags) in areas {
+            let node = Node {
+                id: area.node_id,
+                coordinate: area.representative_point,
+                tags,
+                ways: Vec::new(),
+                nodes: Vec::new(),
+                elevation: None,
+            };
+
+            self.node_map.insert(node.id, node);
+            self.area_map.insert(area.node_id, area);
+        }
+    }
+
+    /// The area a landmark node stands for, if it is an area node.
+    pub fn area(&self, node_id: u64) -> Option<&Area> {
+        self.area_map.get(&node_id)
+    }
+
+    fn way_polygon(&self, way: &Way) -> Option<Polygon> {
+        let coordinates = way
+            .node_ids
+            .iter()
+            .map(|node_id| Some(Coord::from(self.node_map.get(node_id)?.coordinate)))
+            .collect::<Option<Vec<Coord>>>()?;
+
+        Some(Polygon::new(LineString::from(coordinates), Vec::new()))
+    }
+}
+
+fn area(node_id: u64, source: AreaSource, geometry: MultiPolygon) -> Option<Area> {
+    let representative_point = geometry.interior_point()?;
+    let centroid = geometry.centroid()?;
+
+    Some(Area {
+        node_id,
+        source,
+        geometry,
+        representative_point,
+        centroid,
+    })
+}
+
+fn is_area_way(way: &Way) -> bool {
+    let is_closed = way.node_ids.len() >= 4 && way.node_ids.first() == way.node_ids.last();
+
+    match way.tags.get("area") {
+        _ if !is_closed => false,
+        Some("yes") => true,
+        Some("no") => false,
+        _ => AREA_KEYS.iter().any(|key| way.tags.contains_key(key)),
+    }
+}
+
+/// Whether a landmark filter keeps an element with these tags, the same way it would keep a node.
+fn matches_landmark_filter(tags: &Tags, filters: &[FilterSet]) -> bool {
+    filters.iter().any(|filter| match filter.filter_subset {
+        FilterSubset::AllLandmarks => !tags.is_empty(),
+        FilterSubset::Landmark(_) => tags
+            .get(&filter.filter_key)
+            .is_some_and(|value| filter.filter_values.contains(value)),
+        FilterSubset::Roads => false,
+    })
+}
@@ -0,0 +1,154 @@
+use std::error::Error;
+
+use geo::{Contains, Coord, LineString, MultiPolygon, Polygon};
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::{ClipArea, OSMData};
+use crate::osm_parsing::Relation;
+
+impl OSMData {
+    /// Relations tagged `boundary=administrative`, optionally matching a name and `admin_level`
+    /// (e.g. 8 for Dutch municipalities), sorted by ID.
+    pub fn administrative_boundaries(
+        &self,
+        name: Option<&str>,
+        admin_level: Option<u8>,
+    ) -> Vec<&Relation> {
+        let admin_level = admin_level.map(|admin_level| admin_level.to_string());
+
+        let mut boundaries: Vec<&Relation> = self
+            .relation_map
+            .values()
+            .filter(|relation| relation.tags.get("boundary") == Some("administrative"))
+            .filter(|relation| name.is_none_or(|name| relation.tags.get("name") == Some(name)))
+            .filter(|relation| {
+                admin_level.as_ref().is_none_or(|admin_level| {
+                    relation.tags.get("admin_level") == Some(admin_level.as_str())
+                })
+            })
+            .collect();
+
+        boundaries.sort_by_key(|relation| relation.id);
+        boundaries
+    }
+
+    /// The area of the administrative boundary with this name, to clip the map to or sample from.
+    /// Boundaries must be assembled before filtering, which drops the ways they consist of.
+    pub fn administrative_area(
+        &self,
+        name: &str,
+        admin_level: Option<u8>,
+    ) -> Result<ClipArea, Box<dyn Error>> {
+        let boundaries = self.administrative_boundaries(Some(name), admin_level);
+
+        let boundary = match boundaries.as_slice() {
+            [] => return Err(format!("No administrative boundary named {}", name).into()),
+            [boundary] => boundary,
+            [boundary, ..] => {
+                warn!(
+                    "Found {} administrative boundaries named {}: using relation {}",
+                    boundaries.len(),
+                    name,
+                    boundary.id
+                );
+                boundary
+            }
+        };
+
+        Ok(ClipArea::Polygon(self.relation_multipolygon(boundary)?))
+    }
+
+    /// Joins the `outer` and `inner` way members of a relation into closed rings and assigns every inner
+    /// ring to the outer ring that contains it. Members without a role count as outer.
+    pub fn relation_multipolygon(
+        &self,
+        relation: &Relation,
+    ) -> Result<MultiPolygon, Box<dyn Error>> {
+        let outer_rings = self.assemble_rings(
+            relation
+                .way_members("outer")
+                .chain(relation.way_members("")),
+        )?;
+        let inner_rings = self.assemble_rings(relation.way_members("inner"))?;
+
+        if outer_rings.is_empty() {
+            return Err(format!("Relation {} has no outer ways", relation.id).into());
+        }
+
+        let mut polygons: Vec<Polygon> = outer_rings
+            .into_iter()
+            .map(|ring| Polygon::new(ring, Vec::new()))
+            .collect();
+
+        for inner_ring in inner_rings {
+            let Some(first_coordinate) = inner_ring.0.first().copied() else {
+                continue;
+            };
+
+            match polygons
+                .iter_mut()
+                .find(|polygon| polygon.contains(&first_coordinate))
+            {
+                Some(polygon) => polygon.interiors_push(inner_ring),
+                None => warn!(
+                    "Inner ring of relation {} lies outside all outer rings",
+                    relation.id
+                ),
+            }
+        }
+
+        Ok(MultiPolygon::new(polygons))
+    }
+
+    /// Chains ways that share end nodes into closed rings.
+    fn assemble_rings(
+        &self,
+        way_ids: impl Iterator<Item = u64>,
+    ) -> Result<Vec<LineString>, Box<dyn Error>> {
+        let mut fragments: Vec<Vec<u64>> = Vec::new();
+        for way_id in way_ids {
+            let way = self
+                .way_map
+                .get(&way_id)
+                .ok_or_else(|| format!("Way {} is missing from the data", way_id))?;
+            if way.node_ids.len() >= 2 {
+                fragments.push(way.node_ids.clone());
+            }
+        }
+
+        let mut rings = Vec::new();
+
+        while let Some(mut ring) = fragments.pop() {
+            while ring.first() != ring.last() {
+                let ring_end = *ring.last().unwrap();
+
+                let Some(index) = fragments.iter().position(|fragment| {
+                    fragment.first() == Some(&ring_end) || fragment.last() == Some(&ring_end)
+                }) else {
+                    return Err(format!("Ring ending at node {} is not closed", ring_end).into());
+                };
+
+                let mut fragment = fragments.swap_remove(index);
+                if fragment.first() != Some(&ring_end) {
+                    fragment.reverse();
+                }
+                ring.extend(fragment.into_iter().skip(1));
+            }
+
+            let coordinates = ring
+                .iter()
+                .map(|node_id| {
+                    self.node_map
+                        .get(node_id)
+                        .map(|node| Coord::from(node.coordinate))
+                        .ok_or_else(|| format!("Node {} is missing from the data", node_id))
+                })
+                .collect::<Result<Vec<Coord>, String>>()?;
+
+            rings.push(LineString::from(coordinates));
+        }
+
+        Ok(rings)
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::validation::valid_pieces;
+use crate::data_handling::{AppliedFilter, ChangeSummary, FilterSubset, OSMData, ReplicationState};
+use crate::osm_parsing::change_parsing::parse_change_file;
+use crate::osm_parsing::{ChangeAction, ChangedElement, Node, OsmChange, Way};
+
+impl ReplicationState {
+    /// Reads a replication `state.txt`, e.g. from Geofabrik:
+    ///
+    /// ```text
+    /// #Sat Oct 19 20:21:02 UTC 2024
+    /// sequenceNumber=4212
+    /// timestamp=2024-10-19T20\:21\:02Z
+    /// ```
+    pub fn from_state_file(file_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut sequence_number = None;
+        let mut timestamp = None;
+
+        for line in fs::read_to_string(file_path)?.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            match key.trim() {
+                "sequenceNumber" => sequence_number = Some(value.trim().parse::<u64>()?),
+                "timestamp" => {
+                    let value = value.trim().replace('\\', "");
+                    timestamp = Some(DateTime::parse_from_rfc3339(&value)?.with_timezone(&Utc));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(ReplicationState {
+            sequence_number: sequence_number.ok_or("State file has no sequenceNumber")?,
+            timestamp,
+        })
+    }
+}
+
+impl ChangeSummary {
+    fn record(&mut self, action: ChangeAction, is_applied: bool) {
+        match (is_applied, action) {
+            (false, _) => self.ignored += 1,
+            (true, ChangeAction::Create) => self.created += 1,
+            (true, ChangeAction::Modify) => self.modified += 1,
+            (true, ChangeAction::Delete) => self.deleted += 1,
+        }
+    }
+}
+
+/// A changed node the filters do not keep on their own, with the changes made to it, in case a changed
+/// way needs it.
+struct PendingNode {
+    node: Node,
+    actions: Vec<ChangeAction>,
+}
+
+impl OSMData {
+    /// Applies a replication diff and records its state. A diff at or before the map's current sequence
+    /// number is skipped; a gap in the sequence is applied with a warning.
+    pub fn apply_replication_diff(
+        &mut self,
+        change_file_path: &Path,
+        state_file_path: &Path,
+    ) -> Result<ChangeSummary, Box<dyn Error>> {
+        let replication_state = ReplicationState::from_state_file(state_file_path)?;
+
+        if let Some(current_state) = &self.replication_state {
+            if replication_state.sequence_number <= current_state.sequence_number {
+                info!(
+                    "Skipping diff {}: the map is already at sequence number {}",
+                    replication_state.sequence_number, current_state.sequence_number
+                );
+                return Ok(ChangeSummary::default());
+            }
+
+            if replication_state.sequence_number > current_state.sequence_number + 1 {
+                warn!(
+                    "Applying diff {} on top of {}: the diffs in between are missing",
+                    replication_state.sequence_number, current_state.sequence_number
+                );
+            }
+        }
+
+        let change_summary = self.apply_change_file(change_file_path)?;
+        self.replication_state = Some(replication_state);
+
+        Ok(change_summary)
+    }
+
+    /// Applies an osmChange file, gzipped if it ends in `.gz`.
+    pub fn apply_change_file(&mut self, file_path: &Path) -> Result<ChangeSummary, Box<dyn Error>> {
+        let changes = parse_change_file(file_path)?;
+        Ok(self.apply_changes(changes))
+    }
+
+    /// Applies changes in order, keeping only what the filters the map was built with would keep.
+    /// Adjacency is updated for the changed ways only, and node subsets for the nodes they touch.
+    ///
+    /// Areas are not reassembled, and a way can only gain nodes that are in the map or in the same
+    /// changes; references to other nodes stay dangling until the map is rebuilt. A node change that only
+    /// a later way change pulls into the map counts as applied; one that nothing pulls in as ignored.
+    pub fn apply_changes(&mut self, changes: Vec<OsmChange>) -> ChangeSummary {
+        let mut change_summary = ChangeSummary::default();
+        let mut pending_nodes: HashMap<u64, PendingNode> = HashMap::new();
+        let mut affected_nodes: HashSet<u64> = HashSet::new();
+
+        for change in changes {
+            if let Some(metadata) = &mut self.metadata {
+                let member_type = change.element.member_type();
+                match (change.action, change.metadata) {
+                    (ChangeAction::Delete, _) => metadata.remove(member_type, change.element.id()),
+                    (_, Some(element_metadata)) => {
+                        metadata.insert(member_type, change.element.id(), element_metadata)
+                    }
+                    (_, None) => (),
+                }
+            }
+
+            let is_applied = match (change.action, change.element) {
+                (ChangeAction::Delete, ChangedElement::Node(node)) => {
+                    if let Some(pending_node) = pending_nodes.remove(&node.id) {
+                        for action in pending_node.actions {
+                            change_summary.record(action, false);
+                        }
+                    }
+                    affected_nodes.insert(node.id);
+                    affected_nodes.extend(self.way_nodes_through(node.id));
+                    self.remove_node(node.id)
+                }
+                (action, ChangedElement::Node(node)) => {
+                    affected_nodes.insert(node.id);
+                    if !self.upsert_node(node, action, &mut pending_nodes) {
+                        // Counted once a way pulls the node in, or as ignored at the end.
+                        continue;
+                    }
+                    true
+                }
+                (ChangeAction::Delete, ChangedElement::Way(way)) => {
+                    match self.way_map.remove(&way.id) {
+                        Some(old_way) => {
+                            self.detach_way(&old_way);
+                            affected_nodes.extend(old_way.node_ids.iter().copied());
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                (_, ChangedElement::Way(way)) => self.upsert_way(
+                    way,
+                    &mut pending_nodes,
+                    &mut affected_nodes,
+                    &mut change_summary,
+                ),
+                (ChangeAction::Delete, ChangedElement::Relation(relation)) => {
+                    self.relation_map.remove(&relation.id).is_some()
+                }
+                (_, ChangedElement::Relation(relation)) => {
+                    self.relation_map.insert(relation.id, relation);
+                    true
+                }
+            };
+
+            change_summary.record(change.action, is_applied);
+        }
+
+        for pending_node in pending_nodes.into_values() {
+            for action in pending_node.actions {
+                change_summary.record(action, false);
+            }
+        }
+
+        self.update_node_subsets(&affected_nodes);
+        self.prune_metadata();
+        self.road_components = Default::default();
+
+        info!(
+            "Applied changes: {} created, {} modified, {} deleted, {} ignored",
+            change_summary.created,
+            change_summary.modified,
+            change_summary.deleted,
+            change_summary.ignored
+        );
+
+        change_summary
+    }
+
+    /// Updates a node in the map, or adds it if a landmark filter keeps it. A node that moves loses its
+    /// elevation until `attach_elevations` samples it again.
+    fn upsert_node(
+        &mut self,
+        node: Node,
+        action: ChangeAction,
+        pending_nodes: &mut HashMap<u64, PendingNode>,
+    ) -> bool {
+        if let Some(existing_node) = self.node_map.get_mut(&node.id) {
+            if existing_node.coordinate != node.coordinate {
+                existing_node.coordinate = node.coordinate;
+                existing_node.elevation = None;
+            }
+            existing_node.tags = node.tags;
+            return true;
+        }
+
+        if self.keeps_everything()
+            || self.build_settings.filters.iter().any(|filter| {
+                filter.filter_subset != FilterSubset::Roads && keeps_node(filter, &node)
+            })
+        {
+            self.node_map.insert(node.id, node);
+            return true;
+        }
+
+        match pending_nodes.get_mut(&node.id) {
+            Some(pending_node) => {
+                pending_node.node = node;
+                pending_node.actions.push(action);
+            }
+            None => {
+                pending_nodes.insert(
+                    node.id,
+                    PendingNode {
+                        node,
+                        actions: vec![action],
+                    },
+                );
+            }
+        }
+        false
+    }
+
+    /// Removes the node, and cuts the ways through it at the node, keeping the pieces on both sides.
+    /// Ways referencing a deleted node should have been changed as well; this guards against ones that
+    /// were not.
+    fn remove_node(&mut self, node_id: u64) -> bool {
+        let Some(node) = self.node_map.get(&node_id) else {
+            return false;
+        };
+
+        let mut way_ids = node.ways.clone();
+        way_ids.sort_unstable();
+        way_ids.dedup();
+
+        let ways: Vec<Way> = way_ids
+            .iter()
+            .filter_map(|way_id| self.way_map.remove(way_id))
+            .collect();
+        for way in ways.iter() {
+            self.detach_way(way);
+        }
+
+        let node = self.node_map.remove(&node_id).unwrap();
+        for neighbour_id in node.nodes.iter() {
+            if let Some(neighbour) = self.node_map.get_mut(neighbour_id) {
+                neighbour.nodes.retain(|id| *id != node_id);
+            }
+        }
+
+        let mut next_piece_id = self.next_way_piece_id();
+        for way in ways {
+            let pieces = valid_pieces(&way.node_ids, |id| *id != node_id);
+            for piece in self.split_way(way, pieces, &mut next_piece_id) {
+                self.attach_way(&piece);
+                self.way_map.insert(piece.id, piece);
+            }
+        }
+
+        true
+    }
+
+    /// Nodes of the ways through the node, whose subsets change when the node is removed.
+    fn way_nodes_through(&self, node_id: u64) -> Vec<u64> {
+        self.node_map
+            .get(&node_id)
+            .into_iter()
+            .flat_map(|node| node.ways.iter())
+            .filter_map(|way_id| self.way_map.get(way_id))
+            .flat_map(|way| way.node_ids.iter().copied())
+            .collect()
+    }
+
+    /// Replaces a way, or removes it if the road filters no longer keep it.
+    fn upsert_way(
+        &mut self,
+        way: Way,
+        pending_nodes: &mut HashMap<u64, PendingNode>,
+        affected_nodes: &mut HashSet<u64>,
+        change_summary: &mut ChangeSummary,
+    ) -> bool {
+        let old_way = self.way_map.remove(&way.id);
+        if let Some(old_way) = &old_way {
+            self.detach_way(old_way);
+            affected_nodes.extend(old_way.node_ids.iter().copied());
+        }
+
+        if !self.keeps_way(&way) {
+            return old_way.is_some();
+        }
+
+        for node_id in way.node_ids.iter() {
+            if !self.node_map.contains_key(node_id) {
+                match pending_nodes.remove(node_id) {
+                    Some(pending_node) => {
+                        self.node_map.insert(*node_id, pending_node.node);
+                        for action in pending_node.actions {
+                            change_summary.record(action, true);
+                        }
+                    }
+                    None => warn!(
+                        "Way {} references node {}, which is not in the map",
+                        way.id, node_id
+                    ),
+                }
+            }
+        }
+
+        self.attach_way(&way);
+        affected_nodes.extend(way.node_ids.iter().copied());
+        self.way_map.insert(way.id, way);
+
+        true
+    }
+
+    /// Adds the way and its segments to its nodes, as `update_road_nodes` does for every way.
+    fn attach_way(&mut self, way: &Way) {
+        for node_id in way.node_ids.iter() {
+            if let Some(node) = self.node_map.get_mut(node_id) {
+                node.ways.push(way.id);
+            }
+        }
+
+        for node_ids in way.node_ids.windows(2) {
+            let (left_node_id, right_node_id) = (node_ids[0], node_ids[1]);
+            if !self.node_map.contains_key(&left_node_id)
+                || !self.node_map.contains_key(&right_node_id)
+            {
+                continue;
+            }
+
+            self.node_map
+                .get_mut(&left_node_id)
+                .unwrap()
+                .nodes
+                .push(right_node_id);
+            self.node_map
+                .get_mut(&right_node_id)
+                .unwrap()
+                .nodes
+                .push(left_node_id);
+        }
+    }
+
+    /// Undoes `attach_way`.
+    fn detach_way(&mut self, way: &Way) {
+        for node_id in way.node_ids.iter() {
+            if let Some(node) = self.node_map.get_mut(node_id) {
+                remove_first(&mut node.ways, way.id);
+            }
+        }
+
+        for node_ids in way.node_ids.windows(2) {
+            let (left_node_id, right_node_id) = (node_ids[0], node_ids[1]);
+            if !self.node_map.contains_key(&left_node_id)
+                || !self.node_map.contains_key(&right_node_id)
+            {
+                continue;
+            }
+
+            remove_first(
+                &mut self.node_map.get_mut(&left_node_id).unwrap().nodes,
+                right_node_id,
+            );
+            remove_first(
+                &mut self.node_map.get_mut(&right_node_id).unwrap().nodes,
+                left_node_id,
+            );
+        }
+    }
+
+    /// Moves the affected nodes into the subsets whose filters keep them now, and removes the ones no
+    /// subset or way keeps from the map.
+    fn update_node_subsets(&mut self, affected_nodes: &HashSet<u64>) {
+        if self.keeps_everything() {
+            return;
+        }
+
+        if self.node_subsets.len() != self.build_settings.filters.len() {
+            warn!("The node subsets do not match the filters the map was built with; not updating them");
+            return;
+        }
+
+        let mut kept_nodes: HashSet<u64> = HashSet::new();
+
+        for (subset, filter) in self
+            .node_subsets
+            .iter_mut()
+            .zip(self.build_settings.filters.iter())
+        {
+            for node_id in affected_nodes.iter() {
+                let is_kept =
+                    self.node_map
+                        .get(node_id)
+                        .is_some_and(|node| match filter.filter_subset {
+                            FilterSubset::Roads => node.ways.iter().any(|way_id| {
+                                self.way_map.get(way_id).is_some_and(|way| {
+                                    keeps_tag(filter, way.tags.get(&filter.filter_key))
+                                })
+                            }),
+                            _ => keeps_node(filter, node),
+                        });
+
+                if is_kept {
+                    subset.node_subset.insert(*node_id);
+                    kept_nodes.insert(*node_id);
+                } else {
+                    subset.node_subset.remove(node_id);
+                }
+            }
+        }
+
+        for node_id in affected_nodes.iter() {
+            if !kept_nodes.contains(node_id) && !self.area_map.contains_key(node_id) {
+                self.remove_node(*node_id);
+            }
+        }
+    }
+
+    /// A map that was never filtered keeps every element.
+    fn keeps_everything(&self) -> bool {
+        self.build_settings.filters.is_empty()
+    }
+
+    fn keeps_way(&self, way: &Way) -> bool {
+        self.keeps_everything()
+            || self.build_settings.filters.iter().any(|filter| {
+                filter.filter_subset == FilterSubset::Roads
+                    && keeps_tag(filter, way.tags.get(&filter.filter_key))
+            })
+    }
+}
+
+/// Whether a landmark filter keeps the node. `AllLandmarks` keeps tagged nodes that are not on a way.
+fn keeps_node(filter: &AppliedFilter, node: &Node) -> bool {
+    match filter.filter_subset {
+        FilterSubset::AllLandmarks => !node.tags.is_empty() && node.ways.is_empty(),
+        FilterSubset::Landmark(_) => keeps_tag(filter, node.tags.get(&filter.filter_key)),
+        FilterSubset::Roads => false,
+    }
+}
+
+fn keeps_tag(filter: &AppliedFilter, value: Option<&str>) -> bool {
+    value.is_some_and(|value| {
+        filter
+            .filter_values
+            .binary_search_by(|filter_value| filter_value.as_str().cmp(value))
+            .is_ok()
+    })
+}
+
+fn remove_first(ids: &mut Vec<u64>, id: u64) {
+    if let Some(index) = ids.iter().position(|existing_id| *existing_id == id) {
+        ids.remove(index);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::Path;
+
+use geo::{BoundingRect, Intersects, Point};
+#[allow(unused)]
+use log::{info, warn};
+use rand::rngs::SmallRng;
+
+use crate::data_handling::{ClipArea, OSMData};
+use crate::osm_parsing::{MemberType, RelationMember};
+use crate::utils::geographic_areas::GeographicArea;
+use crate::utils::poly_files::load_poly_file;
+
+impl ClipArea {
+    pub fn from_poly_file(file_path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(ClipArea::Polygon(load_poly_file(file_path)?))
+    }
+
+    /// Whether the coordinate lies inside the area, boundary included.
+    pub fn contains(&self, coordinate: &Point) -> bool {
+        match self {
+            ClipArea::BoundingBox(geographic_area) => geographic_area.contains(coordinate),
+            ClipArea::Polygon(multi_polygon) => multi_polygon.intersects(coordinate),
+        }
+    }
+
+    /// Bounding box as (minimum longitude, minimum latitude, maximum longitude, maximum latitude).
+    pub fn bounds(&self) -> Option<[f64; 4]> {
+        match self {
+            ClipArea::BoundingBox(geographic_area) => Some([
+                geographic_area.minimum_longitude,
+                geographic_area.minimum_latitude,
+                geographic_area.maximum_longitude,
+                geographic_area.maximum_latitude,
+            ]),
+            ClipArea::Polygon(multi_polygon) => {
+                let bounding_rect = multi_polygon.bounding_rect()?;
+                Some([
+                    bounding_rect.min().x,
+                    bounding_rect.min().y,
+                    bounding_rect.max().x,
+                    bounding_rect.max().y,
+                ])
+            }
+        }
+    }
+
+    /// Uniformly distributed random coordinate inside the area, for sampling in analyses.
+    pub fn random_coordinate(&self, rng: &mut SmallRng) -> Point {
+        match self {
+            ClipArea::BoundingBox(geographic_area) => geographic_area.random_coordinate(rng),
+            ClipArea::Polygon(_) => {
+                let [minimum_longitude, minimum_latitude, maximum_longitude, maximum_latitude] =
+                    self.bounds().expect("Cannot sample from an empty polygon");
+                let geographic_area = GeographicArea::new(
+                    minimum_latitude,
+                    maximum_latitude,
+                    minimum_longitude,
+                    maximum_longitude,
+                );
+
+                // Rejection sampling from the bounding box.
+                loop {
+                    let coordinate = geographic_area.random_coordinate(rng);
+                    if self.contains(&coordinate) {
+                        return coordinate;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl OSMData {
+    /// Removes everything outside the area. A way is cut into the runs of its nodes inside the area, each
+    /// extended by one node on both sides so that boundary edges stay routable. The first run keeps the
+    /// way's ID; later runs become new ways with reserved IDs that follow it in its relations (see
+    /// `split_way`).
+    /// Ways without nodes inside are removed, as are relation members outside the area and the relations
+    /// left without members.
+    pub fn clip(&mut self, clip_area: &ClipArea) {
+        self.build_settings.clip_bounds = clip_area.bounds();
+
+        let inside_nodes: HashSet<u64> = self
+            .node_map
+            .values()
+            .filter(|node| clip_area.contains(&node.coordinate))
+            .map(|node| node.id)
+            .collect();
+
+        let mut nodes_to_keep = inside_nodes.clone();
+
+        let mut way_ids: Vec<u64> = self.way_map.keys().copied().collect();
+        way_ids.sort_unstable();
+
+        let mut next_piece_id = self.next_way_piece_id();
+        for way_id in way_ids {
+            let way = self.way_map.remove(&way_id).unwrap();
+            let pieces: Vec<Vec<u64>> = inside_runs(&way.node_ids, &inside_nodes)
+                .into_iter()
+                .map(|(first_kept, last_kept)| way.node_ids[first_kept..=last_kept].to_vec())
+                .collect();
+
+            for piece in self.split_way(way, pieces, &mut next_piece_id) {
+                nodes_to_keep.extend(piece.node_ids.iter().copied());
+                self.way_map.insert(piece.id, piece);
+            }
+        }
+
+        let node_count = self.node_map.len();
+        self.node_map
+            .retain(|node_id, _| nodes_to_keep.contains(node_id));
+
+        self.area_map
+            .retain(|node_id, _| nodes_to_keep.contains(node_id));
+
+        for subset in self.node_subsets.iter_mut() {
+            subset
+                .node_subset
+                .retain(|node_id| nodes_to_keep.contains(node_id));
+        }
+
+        self.prune_relations();
+        self.prune_metadata();
+
+        info!(
+            "Clipped the map: kept {} of {} nodes, {} ways and {} relations",
+            self.node_map.len(),
+            node_count,
+            self.way_map.len(),
+            self.relation_map.len()
+        );
+
+        self.update_road_nodes();
+    }
+
+    /// Whether the element the member refers to is in the map.
+    pub fn contains_member(&self, member: &RelationMember) -> bool {
+        match member.member_type {
+            MemberType::Node => self.node_map.contains_key(&member.member_id),
+            MemberType::Way => self.way_map.contains_key(&member.member_id),
+            MemberType::Relation => self.relation_map.contains_key(&member.member_id),
+        }
+    }
+
+    /// Drops relation members that are not in the map, then the relations left without members,
+    /// until no relation refers to a removed one.
+    fn prune_relations(&mut self) {
+        loop {
+            let relation_count = self.relation_map.len();
+
+            let relation_ids: Vec<u64> = self.relation_map.keys().copied().collect();
+            for relation_id in relation_ids {
+                let members: Vec<RelationMember> = self.relation_map[&relation_id]
+                    .members
+                    .iter()
+                    .filter(|member| self.contains_member(member))
+                    .cloned()
+                    .collect();
+                self.relation_map.get_mut(&relation_id).unwrap().members = members;
+            }
+
+            self.relation_map
+                .retain(|_, relation| !relation.members.is_empty());
+
+            if self.relation_map.len() == relation_count {
+                break;
+            }
+        }
+    }
+}
+
+/// Index ranges of the runs of inside nodes, each extended by one node on both sides. Runs whose
+/// extensions meet are merged.
+fn inside_runs(node_ids: &[u64], inside_nodes: &HashSet<u64>) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+
+    for (index, node_id) in node_ids.iter().enumerate() {
+        if !inside_nodes.contains(node_id) {
+            continue;
+        }
+
+        let first_kept = index.saturating_sub(1);
+        let last_kept = (index + 1).min(node_ids.len() - 1);
+
+        match runs.last_mut() {
+            Some((_, run_end)) if first_kept <= *run_end => *run_end = last_kept,
+            _ => runs.push((first_kept, last_kept)),
+        }
+    }
+
+    runs
+}
@@ -1,34 +1,64 @@
-use log::info;
+use log::{info, warn};
 
 use std::path::Path;
 
-use crate::utils::file_handling::{load_hashmaps, save_hashmaps};
-use crate::{data_handling::OSMData, osm_parsing::osm_parsing::parse_xml};
+use crate::osm_parsing::{ParseOptions, Way};
+use crate::utils::file_handling::{load_hashmaps, save_hashmaps, CacheContents};
+use crate::{
+    data_handling::{BuildSettings, OSMData},
+    osm_parsing::osm_parsing::parse_xml_with_options,
+    osm_parsing::osm_writing::write_xml,
+    osm_parsing::pbf_writing::write_pbf,
+};
 
 use rayon::prelude::*;
-use std::sync::RwLock;
+use std::sync::{OnceLock, RwLock};
 
 impl OSMData {
     pub fn new(file_path: &Path) -> Self {
-        let (node_map, way_map, node_subsets) =
-            match file_path.extension().and_then(|ext| ext.to_str()) {
-                Some("osm") => parse_xml(file_path),
-                Some("hashmap") => load_hashmaps(file_path),
-                _ => panic!("Unsupported file extension or file doesn't exist."),
-            };
+        OSMData::new_with_options(file_path, &ParseOptions::default())
+    }
+
+    /// Loads a map file or cache. Metadata is only kept if the options ask for it.
+    pub fn new_with_options(file_path: &Path, options: &ParseOptions) -> Self {
+        let cache_contents = match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("osm") => {
+                let (map_contents, metadata) = parse_xml_with_options(file_path, options);
+                CacheContents {
+                    map_contents,
+                    build_settings: BuildSettings::default(),
+                    replication_state: None,
+                    metadata,
+                }
+            }
+            Some("hashmap") => load_hashmaps(file_path).unwrap_or_else(|error| {
+                panic!("Failed to load {}: {}", file_path.display(), error)
+            }),
+            _ => panic!("Unsupported file extension or file doesn't exist."),
+        };
+
+        let (node_map, way_map, relation_map, area_map, node_subsets) = cache_contents.map_contents;
 
         OSMData {
             node_map,
             way_map,
+            relation_map,
+            area_map,
             node_subsets,
+            build_settings: cache_contents.build_settings,
+            replication_state: cache_contents.replication_state,
+            metadata: cache_contents.metadata.filter(|_| options.keep_metadata),
+            ..Default::default()
         }
     }
 
     pub fn update_road_nodes(&mut self) {
-        // Deleting all the way entries in the nodes.
+        // Deleting all the way entries and adjacent nodes in the nodes.
         for node in self.node_map.values_mut() {
             node.ways.clear();
+            node.nodes.clear();
         }
+        self.road_components = OnceLock::new();
 
         // Locking the map, then iterating over it.
         // For every way, we push the way id to all the nodes it contains.
@@ -42,31 +72,44 @@ impl OSMData {
         });
 
         // We loop over all the ways, and add the adjacent nodes to those nodes.
+        // Segments to nodes missing from the extract (e.g. at clip boundaries) are skipped.
+        let mut skipped_segments = 0;
         for (_, way) in self.way_map.iter() {
             for node_ids in way.node_ids.windows(2) {
                 let left_node_id = node_ids[0];
                 let right_node_id = node_ids[1];
 
-                self.node_map
-                    .get_mut(&left_node_id)
-                    .expect("Failed to read left node")
-                    .nodes
-                    .push(right_node_id);
-                self.node_map
-                    .get_mut(&right_node_id)
-                    .expect("Failed to read right node")
-                    .nodes
-                    .push(left_node_id);
+                if !self.node_map.contains_key(&left_node_id)
+                    || !self.node_map.contains_key(&right_node_id)
+                {
+                    skipped_segments += 1;
+                    continue;
+                }
+
+                if let Some(left_node) = self.node_map.get_mut(&left_node_id) {
+                    left_node.nodes.push(right_node_id);
+                }
+                if let Some(right_node) = self.node_map.get_mut(&right_node_id) {
+                    right_node.nodes.push(left_node_id);
+                }
             }
         }
+
+        if skipped_segments > 0 {
+            warn!(
+                "Skipped {} way segments referencing nodes missing from the data",
+                skipped_segments
+            );
+        }
     }
 
+    /// Maximum speed (m/s) of the first way through this node that has a readable maxspeed tag.
     pub fn node_max_speed(&self, node_id: u64) -> Option<f64> {
         if let Some(node) = self.node_map.get(&node_id) {
             for way_id in node.ways.iter() {
                 if let Some(way) = self.way_map.get(way_id) {
                     if let Some(max_speed) = way.tags.get("maxspeed") {
-                        if let Ok(max_speed) = max_speed.parse::<f64>() {
+                        if let Some(max_speed) = parse_max_speed(max_speed) {
                             return Some(max_speed);
                         }
                     }
@@ -76,6 +119,21 @@ impl OSMData {
         None
     }
 
+    /// Returns the way that directly connects two adjacent nodes, if any.
+    pub fn connecting_way(&self, from_node_id: u64, to_node_id: u64) -> Option<&Way> {
+        let node = self.node_map.get(&from_node_id)?;
+
+        node.ways
+            .iter()
+            .filter_map(|way_id| self.way_map.get(way_id))
+            .find(|way| {
+                way.node_ids.windows(2).any(|node_ids| {
+                    (node_ids[0] == from_node_id && node_ids[1] == to_node_id)
+                        || (node_ids[0] == to_node_id && node_ids[1] == from_node_id)
+                })
+            })
+    }
+
     pub fn list_ways(&self) {
         for (_, way) in self.way_map.iter() {
             info!("{:?}\n{:?}\n\n", way, way.map_link());
@@ -89,14 +147,55 @@ impl OSMData {
     }
 
     pub fn save_hashmaps(&self, file_path: &Path) {
-        save_hashmaps(file_path, &self.node_map, &self.way_map, &self.node_subsets);
+        save_hashmaps(file_path, self)
+            .unwrap_or_else(|error| panic!("Failed to save {}: {}", file_path.display(), error));
+    }
+
+    /// Writes the map as OSM XML (`.osm`) or PBF (`.pbf`, `.osm.pbf`) for other OSM tools to open.
+    pub fn export_osm(&self, file_path: &Path) {
+        let result = match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("osm") => write_xml(self, file_path),
+            Some("pbf") => write_pbf(self, file_path),
+            _ => panic!("Unsupported export file extension: {}", file_path.display()),
+        };
+
+        result
+            .unwrap_or_else(|error| panic!("Failed to export {}: {}", file_path.display(), error));
     }
 
     pub fn load_hashmaps(&mut self, file_path: &Path) {
-        let (node_map, way_map, node_subsets) = load_hashmaps(file_path);
+        let cache_contents = load_hashmaps(file_path)
+            .unwrap_or_else(|error| panic!("Failed to load {}: {}", file_path.display(), error));
+        let (node_map, way_map, relation_map, area_map, node_subsets) = cache_contents.map_contents;
 
         self.node_map = node_map;
         self.way_map = way_map;
+        self.relation_map = relation_map;
+        self.area_map = area_map;
         self.node_subsets = node_subsets;
+        self.build_settings = cache_contents.build_settings;
+        self.replication_state = cache_contents.replication_state;
+        self.metadata = cache_contents.metadata;
+        self.road_components = OnceLock::new();
     }
 }
+
+/// Converts a maxspeed tag ("50", "80 km/h", "30 mph") to m/s. Values like "none" or "walk" are not handled.
+pub fn parse_max_speed(max_speed: &str) -> Option<f64> {
+    let max_speed = max_speed.trim();
+
+    if let Some(miles_per_hour) = max_speed.strip_suffix("mph") {
+        return miles_per_hour
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(|speed| speed * 1.609344 / 3.6);
+    }
+
+    max_speed
+        .trim_end_matches("km/h")
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(|speed| speed / 3.6)
+}
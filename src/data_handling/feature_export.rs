@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use geo::{Geometry, LineString, MultiPolygon, Polygon};
+#[allow(unused)]
+use log::{info, warn};
+use serde_json::{json, Map, Value};
+
+use crate::data_handling::{FeatureSelection, MapFeature, OSMData};
+use crate::utils::flatgeobuf::write_flatgeobuf;
+
+impl OSMData {
+    /// The selected nodes, ways and areas with their tags, each sorted by ID. Ways with fewer than two
+    /// nodes in the map are left out.
+    pub fn features(&self, selection: &FeatureSelection) -> Vec<MapFeature> {
+        let mut features: Vec<MapFeature> = match selection {
+            FeatureSelection::Subset(filter_subset) => self
+                .node_subsets
+                .iter()
+                .filter(|subset| subset.filter_subset == *filter_subset)
+                .flat_map(|subset| subset.node_subset.iter())
+                // A node can be in several subsets built with the same filter subset.
+                .collect::<HashSet<&u64>>()
+                .into_iter()
+                .filter_map(|node_id| self.node_map.get(node_id))
+                .map(|node| MapFeature {
+                    id: node.id,
+                    geometry: Geometry::Point(node.coordinate),
+                    tags: node.tags.clone(),
+                })
+                .collect(),
+            FeatureSelection::Ways => self
+                .way_map
+                .values()
+                .filter_map(|way| {
+                    let line_string: LineString = way
+                        .node_ids
+                        .iter()
+                        .filter_map(|node_id| self.node_map.get(node_id))
+                        .map(|node| node.coordinate)
+                        .collect();
+
+                    (line_string.0.len() >= 2).then(|| MapFeature {
+                        id: way.id,
+                        geometry: Geometry::LineString(line_string),
+                        tags: way.tags.clone(),
+                    })
+                })
+                .collect(),
+            FeatureSelection::Areas => self
+                .area_map
+                .values()
+                .map(|area| MapFeature {
+                    id: area.way_id().or(area.relation_id()).unwrap_or(area.node_id),
+                    geometry: Geometry::MultiPolygon(area.geometry.clone()),
+                    tags: self
+                        .node_map
+                        .get(&area.node_id)
+                        .map(|node| node.tags.clone())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        };
+
+        features.sort_unstable_by_key(|feature| feature.id);
+        features
+    }
+
+    /// Writes the selected features as GeoJSON (`.geojson`, `.json`) or FlatGeobuf (`.fgb`), with their
+    /// tags as properties.
+    pub fn export_features(
+        &self,
+        selection: &FeatureSelection,
+        file_path: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        let features = self.features(selection);
+
+        match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("geojson") | Some("json") => write_geojson(&features, file_path),
+            Some("fgb") => write_flatgeobuf(&features, file_path),
+            _ => Err(format!("Unsupported export file extension: {}", file_path.display()).into()),
+        }
+    }
+}
+
+/// Writes the features as a GeoJSON FeatureCollection, with their IDs as feature IDs.
+pub fn write_geojson(features: &[MapFeature], file_path: &Path) -> Result<(), Box<dyn Error>> {
+    let features: Vec<Value> = features
+        .iter()
+        .map(|feature| {
+            let properties: Map<String, Value> = feature
+                .tags
+                .iter()
+                .map(|(key, value)| (key.to_string(), Value::from(value)))
+                .collect();
+
+            json!({
+                "type": "Feature",
+                "id": feature.id,
+                "geometry": geometry_json(&feature.geometry),
+                "properties": properties,
+            })
+        })
+        .collect();
+
+    let feature_collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    });
+
+    let writer = BufWriter::new(File::create(file_path)?);
+    serde_json::to_writer(writer, &feature_collection)?;
+
+    info!(
+        "Wrote {} features to {}",
+        features.len(),
+        file_path.display()
+    );
+    Ok(())
+}
+
+fn geometry_json(geometry: &Geometry) -> Value {
+    match geometry {
+        Geometry::Point(point) => json!({
+            "type": "Point",
+            "coordinates": [point.x(), point.y()],
+        }),
+        Geometry::LineString(line_string) => json!({
+            "type": "LineString",
+            "coordinates": line_string_coordinates(line_string),
+        }),
+        Geometry::Polygon(polygon) => json!({
+            "type": "Polygon",
+            "coordinates": polygon_coordinates(polygon),
+        }),
+        Geometry::MultiPolygon(multi_polygon) => json!({
+            "type": "MultiPolygon",
+            "coordinates": multi_polygon_coordinates(multi_polygon),
+        }),
+        _ => {
+            warn!(
+                "Unsupported geometry {:?}, writing an empty geometry",
+                geometry
+            );
+            Value::Null
+        }
+    }
+}
+
+fn line_string_coordinates(line_string: &LineString) -> Vec<[f64; 2]> {
+    line_string
+        .coords()
+        .map(|coordinate| [coordinate.x, coordinate.y])
+        .collect()
+}
+
+fn polygon_coordinates(polygon: &Polygon) -> Vec<Vec<[f64; 2]>> {
+    std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .map(line_string_coordinates)
+        .collect()
+}
+
+fn multi_polygon_coordinates(multi_polygon: &MultiPolygon) -> Vec<Vec<Vec<[f64; 2]>>> {
+    multi_polygon.iter().map(polygon_coordinates).collect()
+}
@@ -1,5 +1,5 @@
-use crate::data_handling::FilterSet;
 use crate::data_handling::NodeSubset;
+use crate::data_handling::{AppliedFilter, FilterSet};
 use crate::data_handling::{FilterSubset, OSMData};
 #[allow(unused)]
 use log::{info, warn};
@@ -16,11 +16,27 @@ impl NodeSubset {
     }
 }
 
+impl AppliedFilter {
+    pub fn from_filter_set(filter: &FilterSet) -> Self {
+        let mut filter_values: Vec<String> = filter.filter_values.iter().cloned().collect();
+        filter_values.sort();
+
+        AppliedFilter {
+            filter_key: filter.filter_key.clone(),
+            filter_values,
+            filter_subset: filter.filter_subset.clone(),
+        }
+    }
+}
+
 impl OSMData {
     pub fn filter(&mut self, filters: Vec<FilterSet>) {
         let mut nodes_to_keep: HashSet<u64> = HashSet::new();
         let mut ways_to_keep: HashSet<u64> = HashSet::new();
 
+        // Areas become landmark nodes, so they have to exist before the landmark filters run.
+        self.assemble_areas(&filters);
+
         // Appling all the filters.
         for filter in filters.iter() {
             let (nodes_filtered, ways_filtered) = match filter.filter_subset {
@@ -36,6 +52,10 @@ impl OSMData {
             // Updating the list of nodes to keep around.
             nodes_to_keep.extend(nodes_filtered);
             ways_to_keep.extend(ways_filtered);
+
+            self.build_settings
+                .filters
+                .push(AppliedFilter::from_filter_set(filter));
         }
 
         self.node_map
@@ -44,6 +64,11 @@ impl OSMData {
         self.way_map
             .retain(|way_id, _| ways_to_keep.contains(way_id));
 
+        self.area_map
+            .retain(|node_id, _| nodes_to_keep.contains(node_id));
+
+        self.prune_metadata();
+
         // This adds the ways each node is part of and adds adjacent nodes to each other for pathfinding.
         self.update_road_nodes();
     }
@@ -98,8 +123,8 @@ impl OSMData {
         self.way_map.par_iter().for_each(|(way_id, way)| {
             let mut keep_way = false;
 
-            if let Some(tag_value) = &way.tags.get(tag_key) {
-                if tag_values.contains(*tag_value) {
+            if let Some(tag_value) = way.tags.get(tag_key) {
+                if tag_values.contains(tag_value) {
                     keep_way = true;
                 }
             }
@@ -0,0 +1,833 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use geo::{GeodesicDistance, Point};
+#[allow(unused)]
+use log::{info, warn};
+use memmap2::Mmap;
+
+use crate::data_handling::{FilterSubset, MappedMap, MappedWay, OSMData};
+use crate::osm_parsing::Tags;
+use crate::path_finding::{RoutingGraph, SpeedProfiles, WayTags};
+
+/// First bytes of every mapped map file.
+pub const MAPPED_MAGIC: [u8; 8] = *b"OSMRGRPH";
+/// Bump whenever the layout of a section changes.
+pub const MAPPED_FORMAT_VERSION: u32 = 2;
+
+/// Nodes in the Roads subset.
+const ROAD_NODE: u8 = 1;
+/// Nodes in the largest road component, the only ones that coordinates are snapped to.
+const MAIN_COMPONENT_NODE: u8 = 2;
+
+/// Marks an edge without a connecting way.
+const NO_WAY: u32 = u32::MAX;
+
+/// Subset kinds without a landmark name.
+const ROADS_SUBSET: u32 = u32::MAX;
+const ALL_LANDMARKS_SUBSET: u32 = u32::MAX - 1;
+
+/// The arrays of a mapped map, in file order. Every array starts 8-byte aligned, holds little-endian
+/// values and has a CRC32 checksum in the header:
+///
+/// ```text
+/// NodeIds          u64 per node, sorted
+/// Coordinates      f64 longitude and latitude per node
+/// Elevations       f32 per node, NaN if unknown
+/// MaxSpeeds        f32 per node (m/s), NaN if no way through the node has a readable maxspeed
+/// NodeFlags        u8 per node
+/// EdgeOffsets      u64 per node plus one: the node's edges in EdgeTargets and EdgeWays
+/// EdgeTargets      u32 node index per edge
+/// EdgeWays         u32 way index per edge
+/// WayIds           u64 per way, sorted
+/// WayTagOffsets    u64 per way plus one: the way's tags in WayTags
+/// WayTags          u32 key and value string index per tag
+/// NodeTagOffsets   u64 per node plus one
+/// NodeTags         u32 key and value string index per tag
+/// SubsetKinds      u32 per node subset: landmark name string index, ROADS_SUBSET or ALL_LANDMARKS_SUBSET
+/// SubsetOffsets    u64 per subset plus one: the subset's nodes in SubsetNodes
+/// SubsetNodes      u32 node index per node in a subset, sorted per subset
+/// StringOffsets    u64 per string plus one: the string's bytes in StringBytes
+/// StringBytes      UTF-8
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MappedSection {
+    NodeIds,
+    Coordinates,
+    Elevations,
+    MaxSpeeds,
+    NodeFlags,
+    EdgeOffsets,
+    EdgeTargets,
+    EdgeWays,
+    WayIds,
+    WayTagOffsets,
+    WayTags,
+    NodeTagOffsets,
+    NodeTags,
+    SubsetKinds,
+    SubsetOffsets,
+    SubsetNodes,
+    StringOffsets,
+    StringBytes,
+}
+
+const SECTION_COUNT: usize = 18;
+
+/// Size of a section entry in the header: offset, length, checksum and padding.
+const SECTION_ENTRY_SIZE: usize = 24;
+
+/// Magic, version, section count and an entry per section.
+const HEADER_SIZE: usize = 8 + 4 + 4 + SECTION_COUNT * SECTION_ENTRY_SIZE;
+
+impl MappedMap {
+    /// Writes the map in the mapped layout. Only what routing, tag and subset lookups need is kept:
+    /// relations and areas are left out.
+    pub fn write(osm_data: &OSMData, file_path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut node_ids: Vec<u64> = osm_data.node_map.keys().copied().collect();
+        node_ids.sort_unstable();
+        let node_indices: HashMap<u64, u32> = node_ids
+            .iter()
+            .enumerate()
+            .map(|(index, node_id)| (*node_id, index as u32))
+            .collect();
+
+        let mut way_ids: Vec<u64> = osm_data.way_map.keys().copied().collect();
+        way_ids.sort_unstable();
+        let way_indices: HashMap<u64, u32> = way_ids
+            .iter()
+            .enumerate()
+            .map(|(index, way_id)| (*way_id, index as u32))
+            .collect();
+
+        let road_nodes: Vec<&u64> = osm_data
+            .node_subsets
+            .iter()
+            .filter(|subset| subset.filter_subset == FilterSubset::Roads)
+            .flat_map(|subset| subset.node_subset.iter())
+            .collect();
+        let road_components = osm_data.road_components();
+        let largest_component = road_components.largest_component();
+
+        let mut flags = vec![0u8; node_ids.len()];
+        for node_id in road_nodes {
+            if let Some(index) = node_indices.get(node_id) {
+                flags[*index as usize] |= ROAD_NODE;
+                if largest_component.is_some()
+                    && road_components.component(*node_id) == largest_component
+                {
+                    flags[*index as usize] |= MAIN_COMPONENT_NODE;
+                }
+            }
+        }
+
+        let mut strings = StringTable::default();
+        let mut writer = SectionWriter::new(BufWriter::new(File::create(file_path)?))?;
+
+        let nodes = || node_ids.iter().map(|node_id| &osm_data.node_map[node_id]);
+
+        writer.write_section(nodes().map(|node| node.id.to_le_bytes()))?;
+        writer.write_section(nodes().flat_map(|node| {
+            [node.coordinate.x(), node.coordinate.y()].map(|value| value.to_le_bytes())
+        }))?;
+        writer.write_section(nodes().map(|node| {
+            (node
+                .elevation
+                .map_or(f32::NAN, |elevation| elevation as f32))
+            .to_le_bytes()
+        }))?;
+        writer.write_section(nodes().map(|node| {
+            (osm_data
+                .node_max_speed(node.id)
+                .map_or(f32::NAN, |max_speed| max_speed as f32))
+            .to_le_bytes()
+        }))?;
+        writer.write_section(flags.iter().map(|flag| [*flag]))?;
+
+        let mut edge_targets = Vec::new();
+        let mut edge_ways = Vec::new();
+        let mut edge_offsets = vec![0u64];
+        for node in nodes() {
+            for neighbour_id in node.nodes.iter() {
+                let Some(neighbour_index) = node_indices.get(neighbour_id) else {
+                    continue;
+                };
+                let way_index = osm_data
+                    .connecting_way(node.id, *neighbour_id)
+                    .and_then(|way| way_indices.get(&way.id).copied())
+                    .unwrap_or(NO_WAY);
+
+                edge_targets.push(*neighbour_index);
+                edge_ways.push(way_index);
+            }
+            edge_offsets.push(edge_targets.len() as u64);
+        }
+        writer.write_section(edge_offsets.iter().map(|offset| offset.to_le_bytes()))?;
+        writer.write_section(edge_targets.iter().map(|index| index.to_le_bytes()))?;
+        writer.write_section(edge_ways.iter().map(|index| index.to_le_bytes()))?;
+
+        writer.write_section(way_ids.iter().map(|way_id| way_id.to_le_bytes()))?;
+        let (way_tag_offsets, way_tags) = strings.tag_table(
+            way_ids
+                .iter()
+                .map(|way_id| sorted_tags(&osm_data.way_map[way_id].tags)),
+        );
+        writer.write_section(way_tag_offsets.iter().map(|offset| offset.to_le_bytes()))?;
+        writer.write_section(way_tags.iter().map(|index| index.to_le_bytes()))?;
+
+        let (node_tag_offsets, node_tags) =
+            strings.tag_table(nodes().map(|node| sorted_tags(&node.tags)));
+        writer.write_section(node_tag_offsets.iter().map(|offset| offset.to_le_bytes()))?;
+        writer.write_section(node_tags.iter().map(|index| index.to_le_bytes()))?;
+
+        let subset_kinds: Vec<u32> = osm_data
+            .node_subsets
+            .iter()
+            .map(|subset| match &subset.filter_subset {
+                FilterSubset::Roads => ROADS_SUBSET,
+                FilterSubset::AllLandmarks => ALL_LANDMARKS_SUBSET,
+                FilterSubset::Landmark(name) => strings.index(name),
+            })
+            .collect();
+        let mut subset_offsets = vec![0u64];
+        let mut subset_nodes = Vec::new();
+        for subset in osm_data.node_subsets.iter() {
+            let mut node_indices: Vec<u32> = subset
+                .node_subset
+                .iter()
+                .filter_map(|node_id| node_indices.get(node_id).copied())
+                .collect();
+            node_indices.sort_unstable();
+
+            subset_nodes.extend(node_indices);
+            subset_offsets.push(subset_nodes.len() as u64);
+        }
+        writer.write_section(subset_kinds.iter().map(|kind| kind.to_le_bytes()))?;
+        writer.write_section(subset_offsets.iter().map(|offset| offset.to_le_bytes()))?;
+        writer.write_section(subset_nodes.iter().map(|index| index.to_le_bytes()))?;
+
+        let mut string_offsets = vec![0u64];
+        for string in strings.strings.iter() {
+            string_offsets.push(string_offsets.last().unwrap() + string.len() as u64);
+        }
+        writer.write_section(string_offsets.iter().map(|offset| offset.to_le_bytes()))?;
+        writer.write_section(strings.strings.iter().map(|string| string.as_bytes()))?;
+
+        writer.finish()?;
+
+        info!(
+            "Wrote mapped map with {} nodes, {} ways and {} edges to {}",
+            node_ids.len(),
+            way_ids.len(),
+            edge_targets.len(),
+            file_path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Maps the file into memory, then checks the header, the section checksums and that every offset
+    /// and index stays within its array, so that lookups never read out of bounds.
+    ///
+    /// The file must not be changed while it is mapped.
+    pub fn open(file_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(file_path)?;
+        // SAFETY: the map is only read through bounds-checked slices, and the file is documented to
+        // stay unchanged while mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < HEADER_SIZE || mmap[..8] != MAPPED_MAGIC {
+            return Err(format!("{} is not a mapped map", file_path.display()).into());
+        }
+
+        let format_version = u32::from_le_bytes(mmap[8..12].try_into()?);
+        if format_version != MAPPED_FORMAT_VERSION {
+            return Err(format!(
+                "Mapped map has format version {}, but only version {} is supported",
+                format_version, MAPPED_FORMAT_VERSION
+            )
+            .into());
+        }
+
+        let section_count = u32::from_le_bytes(mmap[12..16].try_into()?) as usize;
+        if section_count != SECTION_COUNT {
+            return Err(format!("Mapped map has {} sections", section_count).into());
+        }
+
+        let mut sections = Vec::with_capacity(SECTION_COUNT);
+        for section in 0..SECTION_COUNT {
+            let entry = 16 + section * SECTION_ENTRY_SIZE;
+            let offset = u64::from_le_bytes(mmap[entry..entry + 8].try_into()?);
+            let length = u64::from_le_bytes(mmap[entry + 8..entry + 16].try_into()?);
+            let checksum = u32::from_le_bytes(mmap[entry + 16..entry + 20].try_into()?);
+
+            let Some(range) = usize::try_from(offset)
+                .ok()
+                .zip(usize::try_from(length).ok())
+                .and_then(|(offset, length)| Some(offset..offset.checked_add(length)?))
+                .filter(|range| range.start >= HEADER_SIZE && range.end <= mmap.len())
+            else {
+                return Err(format!("Mapped map section {} is truncated", section).into());
+            };
+
+            if crc32fast::hash(&mmap[range.clone()]) != checksum {
+                return Err(format!("Mapped map section {} is corrupt", section).into());
+            }
+            sections.push(range);
+        }
+
+        let mapped_map = MappedMap {
+            mmap,
+            sections,
+            speed_profiles: None,
+        };
+        mapped_map.check_arrays()?;
+
+        info!(
+            "Mapped {} nodes and {} ways from {}",
+            mapped_map.node_count(),
+            mapped_map.way_count(),
+            file_path.display()
+        );
+
+        Ok(mapped_map)
+    }
+
+    pub fn load_speed_profiles(
+        &mut self,
+        file_path: &Path,
+        utc_offset_seconds: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.speed_profiles = Some(SpeedProfiles::from_csv(file_path, utc_offset_seconds)?);
+        Ok(())
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.section(MappedSection::NodeIds).len() / 8
+    }
+
+    pub fn way_count(&self) -> usize {
+        self.section(MappedSection::WayIds).len() / 8
+    }
+
+    /// Position of the node in the sorted node arrays.
+    pub fn node_index(&self, node_id: u64) -> Option<usize> {
+        binary_search(self.section(MappedSection::NodeIds), node_id)
+    }
+
+    pub fn node_tag(&self, node_id: u64, key: &str) -> Option<&str> {
+        let node_index = self.node_index(node_id)?;
+        self.find_tag(
+            MappedSection::NodeTagOffsets,
+            MappedSection::NodeTags,
+            node_index,
+            key,
+        )
+    }
+
+    /// All tags of the node, sorted by key.
+    pub fn node_tags(&self, node_id: u64) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.node_index(node_id)
+            .into_iter()
+            .flat_map(move |node_index| {
+                self.tags(
+                    MappedSection::NodeTagOffsets,
+                    MappedSection::NodeTags,
+                    node_index,
+                )
+            })
+    }
+
+    pub fn way(&self, way_id: u64) -> Option<MappedWay<'_>> {
+        let way_index = binary_search(self.section(MappedSection::WayIds), way_id)?;
+        Some(MappedWay {
+            map: self,
+            way_index,
+        })
+    }
+
+    /// The road node closest to the coordinate within the largest road component, with its distance
+    /// in meters.
+    pub fn closest_road_node(&self, coordinate: Point) -> Option<(u64, f64)> {
+        self.section(MappedSection::NodeFlags)
+            .iter()
+            .enumerate()
+            .filter(|(_, flags)| *flags & MAIN_COMPONENT_NODE != 0)
+            .map(|(node_index, _)| {
+                let distance = coordinate.geodesic_distance(&self.node_coordinate(node_index));
+                (self.node_id(node_index), distance)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// IDs of the nodes in the subsets of this kind, e.g. the stations.
+    pub fn subset_nodes(&self, filter_subset: &FilterSubset) -> impl Iterator<Item = u64> + '_ {
+        let filter_subset = filter_subset.clone();
+        let subset_nodes = self.section(MappedSection::SubsetNodes);
+
+        (0..self.section(MappedSection::SubsetKinds).len() / 4)
+            .filter(move |subset_index| self.subset_kind(*subset_index) == filter_subset)
+            .flat_map(|subset_index| {
+                offset_range(self.section(MappedSection::SubsetOffsets), subset_index)
+            })
+            .map(move |entry| self.node_id(read_u32(subset_nodes, entry) as usize))
+    }
+
+    /// Logs the landmarks with a tag value containing the search string.
+    pub fn search_landmarks(&self, search_string: &str) {
+        let mut result_counter = 0;
+
+        for node_id in self.subset_nodes(&FilterSubset::AllLandmarks) {
+            if self
+                .node_tags(node_id)
+                .any(|(_, value)| value.contains(search_string))
+            {
+                info!(
+                    "{}: {:?}",
+                    node_id,
+                    self.node_tags(node_id).collect::<Vec<_>>()
+                );
+                result_counter += 1;
+            }
+        }
+
+        if result_counter > 0 {
+            info!(
+                "Done! Found {} results for query {}",
+                result_counter, search_string
+            );
+        } else {
+            warn!("No results found for query: {}", search_string);
+        }
+    }
+
+    /// Whether the node lies on a road.
+    pub fn is_road_node(&self, node_id: u64) -> bool {
+        self.node_index(node_id).is_some_and(|node_index| {
+            self.section(MappedSection::NodeFlags)[node_index] & ROAD_NODE != 0
+        })
+    }
+
+    fn subset_kind(&self, subset_index: usize) -> FilterSubset {
+        match read_u32(self.section(MappedSection::SubsetKinds), subset_index) {
+            ROADS_SUBSET => FilterSubset::Roads,
+            ALL_LANDMARKS_SUBSET => FilterSubset::AllLandmarks,
+            string_index => FilterSubset::Landmark(self.string(string_index as usize).to_string()),
+        }
+    }
+
+    /// Checks that the arrays have matching lengths, that offsets are ascending and end at the length of
+    /// the array they point into, and that every index points into its array.
+    fn check_arrays(&self) -> Result<(), Box<dyn Error>> {
+        use MappedSection::*;
+
+        let node_count = self.array_length(NodeIds, 8)?;
+        let way_count = self.array_length(WayIds, 8)?;
+        let string_count = self
+            .array_length(StringOffsets, 8)?
+            .checked_sub(1)
+            .ok_or("Mapped map has no string offsets")?;
+        let subset_count = self.array_length(SubsetKinds, 4)?;
+
+        self.check_length(Coordinates, node_count, 16)?;
+        self.check_length(Elevations, node_count, 4)?;
+        self.check_length(MaxSpeeds, node_count, 4)?;
+        self.check_length(NodeFlags, node_count, 1)?;
+        self.check_ascending_ids(NodeIds)?;
+        self.check_ascending_ids(WayIds)?;
+
+        let edge_count = self.check_offsets(EdgeOffsets, node_count)?;
+        self.check_length(EdgeTargets, edge_count, 4)?;
+        self.check_length(EdgeWays, edge_count, 4)?;
+        self.check_indices(EdgeTargets, node_count, &[])?;
+        self.check_indices(EdgeWays, way_count, &[NO_WAY])?;
+
+        for (offsets, tags, count) in [
+            (WayTagOffsets, WayTags, way_count),
+            (NodeTagOffsets, NodeTags, node_count),
+        ] {
+            let tag_count = self.check_offsets(offsets, count)?;
+            self.check_length(tags, tag_count, 8)?;
+            self.check_indices(tags, string_count, &[])?;
+        }
+
+        self.check_indices(
+            SubsetKinds,
+            string_count,
+            &[ROADS_SUBSET, ALL_LANDMARKS_SUBSET],
+        )?;
+        let subset_node_count = self.check_offsets(SubsetOffsets, subset_count)?;
+        self.check_length(SubsetNodes, subset_node_count, 4)?;
+        self.check_indices(SubsetNodes, node_count, &[])?;
+
+        let string_byte_count = self.check_offsets(StringOffsets, string_count)?;
+        self.check_length(StringBytes, string_byte_count, 1)?;
+        for string_index in 0..string_count {
+            let range = offset_range(self.section(StringOffsets), string_index);
+            std::str::from_utf8(&self.section(StringBytes)[range])
+                .map_err(|_| format!("Mapped map string {} is not UTF-8", string_index))?;
+        }
+
+        Ok(())
+    }
+
+    /// Number of values in the array.
+    fn array_length(
+        &self,
+        section: MappedSection,
+        value_size: usize,
+    ) -> Result<usize, Box<dyn Error>> {
+        let length = self.section(section).len();
+        if !length.is_multiple_of(value_size) {
+            return Err(format!("Mapped map array {:?} has a partial value", section).into());
+        }
+        Ok(length / value_size)
+    }
+
+    fn check_length(
+        &self,
+        section: MappedSection,
+        value_count: usize,
+        value_size: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        if value_count.checked_mul(value_size) != Some(self.section(section).len()) {
+            return Err(format!(
+                "Mapped map array {:?} does not hold {} values",
+                section, value_count
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Checks an offset array for `count` elements and returns its last offset.
+    fn check_offsets(&self, section: MappedSection, count: usize) -> Result<usize, Box<dyn Error>> {
+        self.check_length(section, count + 1, 8)?;
+
+        let offsets = self.section(section);
+        if read_u64(offsets, 0) != 0
+            || (0..count).any(|index| read_u64(offsets, index) > read_u64(offsets, index + 1))
+        {
+            return Err(format!("Mapped map offsets {:?} are not ascending", section).into());
+        }
+
+        usize::try_from(read_u64(offsets, count))
+            .map_err(|_| format!("Mapped map offsets {:?} are too large", section).into())
+    }
+
+    fn check_ascending_ids(&self, section: MappedSection) -> Result<(), Box<dyn Error>> {
+        let ids = self.section(section);
+        if (1..ids.len() / 8).any(|index| read_u64(ids, index - 1) >= read_u64(ids, index)) {
+            return Err(format!("Mapped map IDs {:?} are not sorted", section).into());
+        }
+        Ok(())
+    }
+
+    /// Checks that every u32 in the array is below `count` or one of the markers.
+    fn check_indices(
+        &self,
+        section: MappedSection,
+        count: usize,
+        markers: &[u32],
+    ) -> Result<(), Box<dyn Error>> {
+        let indices = self.section(section);
+        if (0..indices.len() / 4).any(|index| {
+            let value = read_u32(indices, index);
+            value as usize >= count && !markers.contains(&value)
+        }) {
+            return Err(format!("Mapped map array {:?} has an index out of range", section).into());
+        }
+        Ok(())
+    }
+
+    fn section(&self, section: MappedSection) -> &[u8] {
+        &self.mmap[self.sections[section as usize].clone()]
+    }
+
+    fn node_id(&self, node_index: usize) -> u64 {
+        read_u64(self.section(MappedSection::NodeIds), node_index)
+    }
+
+    fn node_coordinate(&self, node_index: usize) -> Point {
+        let coordinates = self.section(MappedSection::Coordinates);
+        Point::new(
+            read_f64(coordinates, 2 * node_index),
+            read_f64(coordinates, 2 * node_index + 1),
+        )
+    }
+
+    fn edges(&self, node_index: usize) -> Range<usize> {
+        offset_range(self.section(MappedSection::EdgeOffsets), node_index)
+    }
+
+    fn string(&self, string_index: usize) -> &str {
+        let range = offset_range(self.section(MappedSection::StringOffsets), string_index);
+        std::str::from_utf8(&self.section(MappedSection::StringBytes)[range])
+            .expect("Mapped map strings are checked on open")
+    }
+
+    fn tags(
+        &self,
+        offsets: MappedSection,
+        tags: MappedSection,
+        index: usize,
+    ) -> impl Iterator<Item = (&str, &str)> + '_ {
+        let tag_section = self.section(tags);
+        offset_range(self.section(offsets), index).map(move |tag_index| {
+            (
+                self.string(read_u32(tag_section, 2 * tag_index) as usize),
+                self.string(read_u32(tag_section, 2 * tag_index + 1) as usize),
+            )
+        })
+    }
+
+    fn find_tag(
+        &self,
+        offsets: MappedSection,
+        tags: MappedSection,
+        index: usize,
+        key: &str,
+    ) -> Option<&str> {
+        self.tags(offsets, tags, index)
+            .find(|(tag_key, _)| *tag_key == key)
+            .map(|(_, value)| value)
+    }
+}
+
+impl<'a> MappedWay<'a> {
+    /// All tags of the way, sorted by key.
+    pub fn tags(&self) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        self.map.tags(
+            MappedSection::WayTagOffsets,
+            MappedSection::WayTags,
+            self.way_index,
+        )
+    }
+}
+
+impl WayTags for MappedWay<'_> {
+    fn way_id(&self) -> u64 {
+        read_u64(self.map.section(MappedSection::WayIds), self.way_index)
+    }
+
+    fn tag(&self, key: &str) -> Option<&str> {
+        self.map.find_tag(
+            MappedSection::WayTagOffsets,
+            MappedSection::WayTags,
+            self.way_index,
+            key,
+        )
+    }
+}
+
+impl RoutingGraph for MappedMap {
+    type Way<'a> = MappedWay<'a>;
+
+    fn contains_node(&self, node_id: u64) -> bool {
+        self.node_index(node_id).is_some()
+    }
+
+    fn coordinate(&self, node_id: u64) -> Option<Point> {
+        Some(self.node_coordinate(self.node_index(node_id)?))
+    }
+
+    fn elevation(&self, node_id: u64) -> Option<f64> {
+        let elevation = read_f32(
+            self.section(MappedSection::Elevations),
+            self.node_index(node_id)?,
+        );
+        (!elevation.is_nan()).then_some(elevation as f64)
+    }
+
+    fn neighbours(&self, node_id: u64) -> impl Iterator<Item = u64> + '_ {
+        let edge_targets = self.section(MappedSection::EdgeTargets);
+        self.node_index(node_id)
+            .into_iter()
+            .flat_map(|node_index| self.edges(node_index))
+            .map(move |edge| self.node_id(read_u32(edge_targets, edge) as usize))
+    }
+
+    fn connecting_way(&self, from_node_id: u64, to_node_id: u64) -> Option<MappedWay<'_>> {
+        let from_index = self.node_index(from_node_id)?;
+        let to_index = self.node_index(to_node_id)? as u32;
+
+        let edge_targets = self.section(MappedSection::EdgeTargets);
+        let edge = self
+            .edges(from_index)
+            .find(|edge| read_u32(edge_targets, *edge) == to_index)?;
+
+        match read_u32(self.section(MappedSection::EdgeWays), edge) {
+            NO_WAY => None,
+            way_index => Some(MappedWay {
+                map: self,
+                way_index: way_index as usize,
+            }),
+        }
+    }
+
+    fn node_max_speed(&self, node_id: u64) -> Option<f64> {
+        let max_speed = read_f32(
+            self.section(MappedSection::MaxSpeeds),
+            self.node_index(node_id)?,
+        );
+        (!max_speed.is_nan()).then_some(max_speed as f64)
+    }
+
+    fn speed_profiles(&self) -> Option<&SpeedProfiles> {
+        self.speed_profiles.as_ref()
+    }
+}
+
+impl OSMData {
+    /// Writes the map in the layout that `MappedMap::open` maps without deserializing.
+    pub fn save_mapped(&self, file_path: &Path) {
+        MappedMap::write(self, file_path)
+            .unwrap_or_else(|error| panic!("Failed to write mapped map: {}", error));
+    }
+}
+
+/// Deduplicates the tag strings of all nodes and ways.
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn index(&mut self, string: &str) -> u32 {
+        if let Some(index) = self.indices.get(string) {
+            return *index;
+        }
+
+        let index = self.strings.len() as u32;
+        self.strings.push(string.to_string());
+        self.indices.insert(string.to_string(), index);
+        index
+    }
+
+    /// Offsets per element and key and value string indices per tag.
+    fn tag_table<'a>(
+        &mut self,
+        elements: impl Iterator<Item = Vec<(&'a str, &'a str)>>,
+    ) -> (Vec<u64>, Vec<u32>) {
+        let mut offsets = vec![0u64];
+        let mut tags = Vec::new();
+
+        for element_tags in elements {
+            for (key, value) in element_tags {
+                tags.push(self.index(key));
+                tags.push(self.index(value));
+            }
+            offsets.push(tags.len() as u64 / 2);
+        }
+
+        (offsets, tags)
+    }
+}
+
+/// Writes sections one after another, then goes back to fill in the header.
+struct SectionWriter<W: Write + Seek> {
+    writer: W,
+    position: u64,
+    sections: Vec<(u64, u64, u32)>,
+}
+
+impl<W: Write + Seek> SectionWriter<W> {
+    fn new(mut writer: W) -> Result<Self, Box<dyn Error>> {
+        writer.write_all(&[0; HEADER_SIZE])?;
+
+        Ok(SectionWriter {
+            writer,
+            position: HEADER_SIZE as u64,
+            sections: Vec::with_capacity(SECTION_COUNT),
+        })
+    }
+
+    fn write_section<B: AsRef<[u8]>>(
+        &mut self,
+        values: impl Iterator<Item = B>,
+    ) -> Result<(), Box<dyn Error>> {
+        let padding = self.position.next_multiple_of(8) - self.position;
+        self.writer.write_all(&vec![0; padding as usize])?;
+        self.position += padding;
+
+        let offset = self.position;
+        let mut hasher = crc32fast::Hasher::new();
+        for value in values {
+            let bytes = value.as_ref();
+            self.writer.write_all(bytes)?;
+            hasher.update(bytes);
+            self.position += bytes.len() as u64;
+        }
+
+        self.sections
+            .push((offset, self.position - offset, hasher.finalize()));
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        assert_eq!(self.sections.len(), SECTION_COUNT);
+
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(&MAPPED_MAGIC);
+        header.extend_from_slice(&MAPPED_FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&(SECTION_COUNT as u32).to_le_bytes());
+        for (offset, length, checksum) in self.sections.iter() {
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(&length.to_le_bytes());
+            header.extend_from_slice(&checksum.to_le_bytes());
+            header.extend_from_slice(&[0; 4]);
+        }
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Tags in a fixed order, so that the same map always gives the same file.
+fn sorted_tags(tags: &Tags) -> Vec<(&'static str, &'static str)> {
+    let mut tags: Vec<(&str, &str)> = tags.iter().collect();
+    tags.sort_unstable();
+    tags
+}
+
+fn read_u64(bytes: &[u8], index: usize) -> u64 {
+    u64::from_le_bytes(bytes[index * 8..index * 8 + 8].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes(bytes[index * 4..index * 4 + 4].try_into().unwrap())
+}
+
+fn read_f64(bytes: &[u8], index: usize) -> f64 {
+    f64::from_le_bytes(bytes[index * 8..index * 8 + 8].try_into().unwrap())
+}
+
+fn read_f32(bytes: &[u8], index: usize) -> f32 {
+    f32::from_le_bytes(bytes[index * 4..index * 4 + 4].try_into().unwrap())
+}
+
+/// The range between the offset at `index` and the next one.
+fn offset_range(offsets: &[u8], index: usize) -> Range<usize> {
+    read_u64(offsets, index) as usize..read_u64(offsets, index + 1) as usize
+}
+
+/// Index of the value in a sorted u64 array.
+fn binary_search(values: &[u8], value: u64) -> Option<usize> {
+    let (mut low, mut high) = (0, values.len() / 8);
+    while low < high {
+        let middle = (low + high) / 2;
+        match read_u64(values, middle).cmp(&value) {
+            std::cmp::Ordering::Less => low = middle + 1,
+            std::cmp::Ordering::Greater => high = middle,
+            std::cmp::Ordering::Equal => return Some(middle),
+        }
+    }
+    None
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::{ClipArea, OSMData};
+use crate::osm_parsing::{ElementMetadata, MemberType};
+
+impl OSMData {
+    pub fn node_metadata(&self, node_id: u64) -> Option<&ElementMetadata> {
+        self.metadata.as_ref()?.get(MemberType::Node, node_id)
+    }
+
+    pub fn way_metadata(&self, way_id: u64) -> Option<&ElementMetadata> {
+        self.metadata.as_ref()?.get(MemberType::Way, way_id)
+    }
+
+    pub fn relation_metadata(&self, relation_id: u64) -> Option<&ElementMetadata> {
+        self.metadata
+            .as_ref()?
+            .get(MemberType::Relation, relation_id)
+    }
+
+    /// Ways last edited at or after `since`, optionally only those with a node inside the area, sorted
+    /// by ID. Ways without a timestamp are left out.
+    pub fn ways_edited_since(&self, since: DateTime<Utc>, area: Option<&ClipArea>) -> Vec<u64> {
+        let mut way_ids: Vec<u64> = self
+            .way_map
+            .values()
+            .filter(|way| is_edited_since(self.way_metadata(way.id), since))
+            .filter(|way| {
+                area.is_none_or(|area| {
+                    way.node_ids.iter().any(|node_id| {
+                        self.node_map
+                            .get(node_id)
+                            .is_some_and(|node| area.contains(&node.coordinate))
+                    })
+                })
+            })
+            .map(|way| way.id)
+            .collect();
+
+        self.warn_without_metadata();
+        way_ids.sort_unstable();
+        way_ids
+    }
+
+    /// Nodes last edited at or after `since`, optionally only those inside the area, sorted by ID.
+    pub fn nodes_edited_since(&self, since: DateTime<Utc>, area: Option<&ClipArea>) -> Vec<u64> {
+        let mut node_ids: Vec<u64> = self
+            .node_map
+            .values()
+            .filter(|node| is_edited_since(self.node_metadata(node.id), since))
+            .filter(|node| area.is_none_or(|area| area.contains(&node.coordinate)))
+            .map(|node| node.id)
+            .collect();
+
+        self.warn_without_metadata();
+        node_ids.sort_unstable();
+        node_ids
+    }
+
+    /// Drops the metadata of elements that are no longer in the map.
+    pub fn prune_metadata(&mut self) {
+        let Some(metadata) = &mut self.metadata else {
+            return;
+        };
+
+        metadata
+            .nodes
+            .retain(|node_id, _| self.node_map.contains_key(node_id));
+        metadata
+            .ways
+            .retain(|way_id, _| self.way_map.contains_key(way_id));
+        metadata
+            .relations
+            .retain(|relation_id, _| self.relation_map.contains_key(relation_id));
+    }
+
+    fn warn_without_metadata(&self) {
+        if self.metadata.is_none() {
+            warn!("The map was loaded without metadata; set ParseOptions::keep_metadata to query edits");
+        }
+    }
+}
+
+fn is_edited_since(metadata: Option<&ElementMetadata>, since: DateTime<Utc>) -> bool {
+    metadata
+        .and_then(|metadata| metadata.timestamp)
+        .is_some_and(|timestamp| timestamp >= since)
+}
@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use geo::GeodesicDistance;
+#[allow(unused)]
+use log::{info, warn};
+use serde_json::json;
+
+use crate::data_handling::{data_handling::parse_max_speed, OSMData};
+use crate::osm_parsing::{MemberType, RelationMember, Way};
+
+use super::{ValidationIssue, ValidationReport};
+
+/// Ways cut into pieces by `repair`, `clip` or a change file get IDs far above any OSM way ID, so they
+/// cannot clash with ways added later by a change file. Exports leave them out.
+pub const WAY_PIECE_OFFSET: u64 = 1 << 62;
+
+/// Consecutive nodes closer than this (in meters) form a zero-length segment.
+const ZERO_LENGTH_DISTANCE: f64 = 0.001;
+
+impl ValidationIssue {
+    pub fn way_id(&self) -> u64 {
+        match self {
+            ValidationIssue::DanglingNodeReference { way_id, .. }
+            | ValidationIssue::ZeroLengthSegment { way_id, .. }
+            | ValidationIssue::DuplicateConsecutiveNode { way_id, .. }
+            | ValidationIssue::DoublesBack { way_id, .. }
+            | ValidationIssue::UnparseableMaxSpeed { way_id, .. }
+            | ValidationIssue::UntaggedWay { way_id } => *way_id,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ValidationIssue::DanglingNodeReference { .. } => "dangling_node_reference",
+            ValidationIssue::ZeroLengthSegment { .. } => "zero_length_segment",
+            ValidationIssue::DuplicateConsecutiveNode { .. } => "duplicate_consecutive_node",
+            ValidationIssue::DoublesBack { .. } => "doubles_back",
+            ValidationIssue::UnparseableMaxSpeed { .. } => "unparseable_max_speed",
+            ValidationIssue::UntaggedWay { .. } => "untagged_way",
+        }
+    }
+
+    /// Whether `OSMData::repair` fixes this issue. The others need a human to look at the data.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            ValidationIssue::DanglingNodeReference { .. }
+                | ValidationIssue::DuplicateConsecutiveNode { .. }
+                | ValidationIssue::DoublesBack { .. }
+        )
+    }
+}
+
+impl ValidationReport {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Number of issues of each kind.
+    pub fn summary(&self) -> BTreeMap<&'static str, usize> {
+        let mut summary = BTreeMap::new();
+        for issue in self.issues.iter() {
+            *summary.entry(issue.kind()).or_insert(0) += 1;
+        }
+        summary
+    }
+
+    /// Writes the summary and every issue as a JSON object.
+    pub fn write_json(&self, file_path: &Path) -> Result<(), Box<dyn Error>> {
+        let report = json!({
+            "summary": self.summary(),
+            "repairable": self.issues.iter().filter(|issue| issue.is_repairable()).count(),
+            "issues": self.issues,
+        });
+
+        let writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer_pretty(writer, &report)?;
+
+        Ok(())
+    }
+}
+
+impl OSMData {
+    /// Checks every way for broken references and suspicious geometry or tags, without changing anything.
+    pub fn validate(&self) -> ValidationReport {
+        let mut way_ids: Vec<u64> = self.way_map.keys().copied().collect();
+        way_ids.sort_unstable();
+
+        let issues: Vec<ValidationIssue> = way_ids
+            .iter()
+            .filter_map(|way_id| self.way_map.get(way_id))
+            .flat_map(|way| self.validate_way(way))
+            .collect();
+
+        info!(
+            "Validated {} ways: found {} issues",
+            way_ids.len(),
+            issues.len()
+        );
+
+        ValidationReport { issues }
+    }
+
+    /// Validates, then drops duplicate consecutive nodes and cuts every way where it references a missing
+    /// node or doubles back, so no edge is made up between the remaining nodes. The first piece keeps the
+    /// way's ID and the others become new ways (see `split_way`). Pieces with fewer than two nodes are
+    /// dropped, as are ways left without pieces. Returns the report from before the repair.
+    pub fn repair(&mut self) -> ValidationReport {
+        let report = self.validate();
+
+        let way_count = self.way_map.len();
+        let mut way_ids: Vec<u64> = self.way_map.keys().copied().collect();
+        way_ids.sort_unstable();
+
+        let mut next_piece_id = self.next_way_piece_id();
+        for way_id in way_ids {
+            let way = self.way_map.remove(&way_id).unwrap();
+            let pieces = valid_pieces(&way.node_ids, |node_id| self.node_map.contains_key(node_id));
+
+            for piece in self.split_way(way, pieces, &mut next_piece_id) {
+                self.way_map.insert(piece.id, piece);
+            }
+        }
+
+        self.prune_metadata();
+
+        info!(
+            "Repaired {} issues, going from {} to {} ways",
+            report
+                .issues
+                .iter()
+                .filter(|issue| issue.is_repairable())
+                .count(),
+            way_count,
+            self.way_map.len()
+        );
+
+        self.update_road_nodes();
+
+        report
+    }
+
+    /// The ID for the first new way piece: one above the largest piece ID in the map, if any.
+    pub(crate) fn next_way_piece_id(&self) -> u64 {
+        self.way_map
+            .keys()
+            .filter(|way_id| **way_id >= WAY_PIECE_OFFSET)
+            .max()
+            .map_or(WAY_PIECE_OFFSET, |way_id| way_id + 1)
+    }
+
+    /// Turns the way into one way per piece, with the same tags. The first piece keeps the way's ID, the
+    /// others get piece IDs counting up from `next_piece_id` and follow the way in every relation it is a
+    /// member of. Returns no ways if there are no pieces; the caller inserts the ways into the map.
+    pub(crate) fn split_way(
+        &mut self,
+        way: Way,
+        pieces: Vec<Vec<u64>>,
+        next_piece_id: &mut u64,
+    ) -> Vec<Way> {
+        let ways: Vec<Way> = pieces
+            .into_iter()
+            .enumerate()
+            .map(|(index, node_ids)| {
+                let id = if index == 0 {
+                    way.id
+                } else {
+                    *next_piece_id += 1;
+                    *next_piece_id - 1
+                };
+
+                Way {
+                    id,
+                    node_ids,
+                    tags: way.tags.clone(),
+                }
+            })
+            .collect();
+
+        if ways.len() > 1 {
+            for relation in self.relation_map.values_mut() {
+                let mut members = Vec::with_capacity(relation.members.len());
+                for member in relation.members.drain(..) {
+                    let is_split_way =
+                        member.member_type == MemberType::Way && member.member_id == way.id;
+                    let role = member.role.clone();
+                    members.push(member);
+
+                    if is_split_way {
+                        members.extend(ways[1..].iter().map(|piece| RelationMember {
+                            member_type: MemberType::Way,
+                            member_id: piece.id,
+                            role: role.clone(),
+                        }));
+                    }
+                }
+                relation.members = members;
+            }
+        }
+
+        ways
+    }
+
+    fn validate_way(&self, way: &Way) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        if way.tags.is_empty() {
+            issues.push(ValidationIssue::UntaggedWay { way_id: way.id });
+        }
+
+        if let Some(max_speed) = way.tags.get("maxspeed") {
+            if parse_max_speed(max_speed).is_none() {
+                issues.push(ValidationIssue::UnparseableMaxSpeed {
+                    way_id: way.id,
+                    max_speed: max_speed.to_string(),
+                });
+            }
+        }
+
+        for node_id in way.node_ids.iter() {
+            if !self.node_map.contains_key(node_id) {
+                issues.push(ValidationIssue::DanglingNodeReference {
+                    way_id: way.id,
+                    node_id: *node_id,
+                });
+            }
+        }
+
+        for node_ids in way.node_ids.windows(2) {
+            let (from_node_id, to_node_id) = (node_ids[0], node_ids[1]);
+
+            if from_node_id == to_node_id {
+                issues.push(ValidationIssue::DuplicateConsecutiveNode {
+                    way_id: way.id,
+                    node_id: from_node_id,
+                });
+                continue;
+            }
+
+            if let (Some(from_node), Some(to_node)) = (
+                self.node_map.get(&from_node_id),
+                self.node_map.get(&to_node_id),
+            ) {
+                if from_node.coordinate.geodesic_distance(&to_node.coordinate)
+                    < ZERO_LENGTH_DISTANCE
+                {
+                    issues.push(ValidationIssue::ZeroLengthSegment {
+                        way_id: way.id,
+                        from_node_id,
+                        to_node_id,
+                    });
+                }
+            }
+        }
+
+        let mut node_ids = way.node_ids.clone();
+        node_ids.dedup();
+
+        for node_ids in node_ids.windows(3) {
+            if node_ids[0] == node_ids[2] {
+                issues.push(ValidationIssue::DoublesBack {
+                    way_id: way.id,
+                    node_id: node_ids[1],
+                });
+            }
+        }
+
+        issues
+    }
+}
+
+/// Splits the node IDs into pieces at missing nodes and where the way turns back (A-B-A becomes A-B and B-A),
+/// skipping duplicate consecutive nodes. Only pieces with at least two nodes are returned, in order.
+pub(crate) fn valid_pieces(node_ids: &[u64], exists: impl Fn(&u64) -> bool) -> Vec<Vec<u64>> {
+    let mut pieces: Vec<Vec<u64>> = vec![Vec::new()];
+
+    for node_id in node_ids.iter() {
+        let piece = pieces.last_mut().unwrap();
+
+        if !exists(node_id) {
+            if !piece.is_empty() {
+                pieces.push(Vec::new());
+            }
+            continue;
+        }
+
+        match piece.as_slice() {
+            [.., last] if last == node_id => {}
+            [.., before_last, last] if before_last == node_id => {
+                let turning_node_id = *last;
+                pieces.push(vec![turning_node_id, *node_id]);
+            }
+            _ => piece.push(*node_id),
+        }
+    }
+
+    pieces.retain(|piece| piece.len() >= 2);
+    pieces
+}
@@ -0,0 +1,22 @@
+pub mod elevation_model;
+pub mod geotiff;
+pub mod hgt;
+
+/// A regular latitude/longitude grid of elevation samples (meters), stored row by row from north to south.
+/// The origin is the center of the north-western sample; voids are stored as NaN.
+#[derive(Debug)]
+pub struct ElevationTile {
+    pub north_latitude: f64,
+    pub west_longitude: f64,
+    pub latitude_step: f64,
+    pub longitude_step: f64,
+    pub rows: usize,
+    pub columns: usize,
+    pub samples: Vec<f32>,
+}
+
+/// A set of elevation tiles loaded from disk, e.g. all SRTM tiles covering the Netherlands.
+#[derive(Debug, Default)]
+pub struct ElevationModel {
+    pub tiles: Vec<ElevationTile>,
+}
@@ -0,0 +1,122 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use geo::Point;
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::OSMData;
+
+use super::geotiff::load_geotiff_tile;
+use super::hgt::load_hgt_tile;
+use super::{ElevationModel, ElevationTile};
+
+impl ElevationTile {
+    /// Bilinearly interpolated elevation, or None outside the tile or next to a void.
+    pub fn elevation_at(&self, coordinate: &Point) -> Option<f64> {
+        let row = (self.north_latitude - coordinate.y()) / self.latitude_step;
+        let column = (coordinate.x() - self.west_longitude) / self.longitude_step;
+
+        if row < 0.0
+            || column < 0.0
+            || row > (self.rows - 1) as f64
+            || column > (self.columns - 1) as f64
+        {
+            return None;
+        }
+
+        let row_0 = (row.floor() as usize).min(self.rows - 2);
+        let column_0 = (column.floor() as usize).min(self.columns - 2);
+
+        let row_fraction = row - row_0 as f64;
+        let column_fraction = column - column_0 as f64;
+
+        let sample = |row: usize, column: usize| self.samples[row * self.columns + column] as f64;
+
+        let north = sample(row_0, column_0) * (1.0 - column_fraction)
+            + sample(row_0, column_0 + 1) * column_fraction;
+        let south = sample(row_0 + 1, column_0) * (1.0 - column_fraction)
+            + sample(row_0 + 1, column_0 + 1) * column_fraction;
+
+        let elevation = north * (1.0 - row_fraction) + south * row_fraction;
+
+        if elevation.is_nan() {
+            None
+        } else {
+            Some(elevation)
+        }
+    }
+}
+
+impl ElevationModel {
+    pub fn new() -> Self {
+        ElevationModel { tiles: Vec::new() }
+    }
+
+    /// Loads every `.hgt`, `.tif` and `.tiff` file in a directory.
+    pub fn from_directory(directory: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut elevation_model = ElevationModel::new();
+
+        for entry in fs::read_dir(directory)? {
+            let file_path = entry?.path();
+
+            match file_path.extension().and_then(|ext| ext.to_str()) {
+                Some("hgt") | Some("tif") | Some("tiff") => elevation_model.add_file(&file_path)?,
+                _ => (),
+            }
+        }
+
+        info!(
+            "Loaded {} elevation tiles from {}",
+            elevation_model.tiles.len(),
+            directory.display()
+        );
+
+        Ok(elevation_model)
+    }
+
+    pub fn add_file(&mut self, file_path: &Path) -> Result<(), Box<dyn Error>> {
+        let tile = match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("hgt") => load_hgt_tile(file_path)?,
+            Some("tif") | Some("tiff") => load_geotiff_tile(file_path)?,
+            _ => return Err(format!("Unsupported elevation file: {}", file_path.display()).into()),
+        };
+
+        self.tiles.push(tile);
+
+        Ok(())
+    }
+
+    /// Elevation from the first tile that covers the coordinate.
+    pub fn elevation_at(&self, coordinate: &Point) -> Option<f64> {
+        self.tiles
+            .iter()
+            .find_map(|tile| tile.elevation_at(coordinate))
+    }
+}
+
+impl OSMData {
+    /// Attaches an elevation to every node that is part of a way. Returns the number of nodes without coverage.
+    pub fn attach_elevations(&mut self, elevation_model: &ElevationModel) -> usize {
+        let mut missing_nodes = 0;
+
+        for node in self.node_map.values_mut() {
+            if node.ways.is_empty() {
+                continue;
+            }
+
+            node.elevation = elevation_model.elevation_at(&node.coordinate);
+
+            if node.elevation.is_none() {
+                missing_nodes += 1;
+            }
+        }
+
+        if missing_nodes > 0 {
+            warn!("No elevation data for {} road nodes", missing_nodes);
+        }
+
+        missing_nodes
+    }
+}
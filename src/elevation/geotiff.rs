@@ -0,0 +1,113 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use tiff::decoder::{Decoder, DecodingResult, Limits};
+use tiff::tags::Tag;
+
+use super::ElevationTile;
+
+const GT_MODEL_TYPE_GEO_KEY: u16 = 1024;
+const GT_RASTER_TYPE_GEO_KEY: u16 = 1025;
+const MODEL_TYPE_GEOGRAPHIC: u16 = 2;
+const RASTER_PIXEL_IS_POINT: u16 = 2;
+
+/// Loads a single-band GeoTIFF elevation tile in geographic (latitude/longitude) coordinates,
+/// such as the SRTM or Copernicus GLO-30 GeoTIFF downloads. Projected rasters are not supported.
+pub fn load_geotiff_tile(file_path: &Path) -> Result<ElevationTile, Box<dyn Error>> {
+    let file = File::open(file_path)?;
+    let mut decoder = Decoder::new(BufReader::new(file))?.with_limits(Limits::unlimited());
+
+    let (columns, rows) = decoder.dimensions()?;
+    let (columns, rows) = (columns as usize, rows as usize);
+
+    if rows < 2 || columns < 2 {
+        return Err(format!(
+            "GeoTIFF of {}x{} pixels is too small to interpolate",
+            columns, rows
+        )
+        .into());
+    }
+
+    let pixel_scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag)?;
+    let tie_point = decoder.get_tag_f64_vec(Tag::ModelTiepointTag)?;
+
+    if pixel_scale.len() < 2 || tie_point.len() < 6 {
+        return Err("Incomplete GeoTIFF georeferencing tags".into());
+    }
+
+    let geo_keys: Vec<u16> = decoder
+        .find_tag_unsigned_vec(Tag::GeoKeyDirectoryTag)?
+        .unwrap_or_default();
+
+    if let Some(model_type) = geo_key(&geo_keys, GT_MODEL_TYPE_GEO_KEY) {
+        if model_type != MODEL_TYPE_GEOGRAPHIC {
+            return Err("Only geographic (latitude/longitude) GeoTIFFs are supported".into());
+        }
+    }
+
+    // Tie points refer to the corner of a pixel unless the raster is marked as point samples.
+    let pixel_offset = match geo_key(&geo_keys, GT_RASTER_TYPE_GEO_KEY) {
+        Some(RASTER_PIXEL_IS_POINT) => 0.0,
+        _ => 0.5,
+    };
+
+    let no_data = decoder
+        .get_tag_ascii_string(Tag::GdalNodata)
+        .ok()
+        .and_then(|no_data| {
+            no_data
+                .trim_matches(char::from(0))
+                .trim()
+                .parse::<f64>()
+                .ok()
+        });
+
+    let samples: Vec<f64> = match decoder.read_image()? {
+        DecodingResult::I16(samples) => samples.into_iter().map(f64::from).collect(),
+        DecodingResult::U16(samples) => samples.into_iter().map(f64::from).collect(),
+        DecodingResult::I32(samples) => samples.into_iter().map(f64::from).collect(),
+        DecodingResult::F32(samples) => samples.into_iter().map(f64::from).collect(),
+        DecodingResult::F64(samples) => samples,
+        _ => return Err("Unsupported GeoTIFF sample format".into()),
+    };
+
+    if samples.len() != rows * columns {
+        return Err("Only single-band GeoTIFFs are supported".into());
+    }
+
+    let samples = samples
+        .into_iter()
+        .map(|sample| {
+            if Some(sample) == no_data || sample <= -32768.0 {
+                f32::NAN
+            } else {
+                sample as f32
+            }
+        })
+        .collect();
+
+    let (longitude_step, latitude_step) = (pixel_scale[0], pixel_scale[1]);
+    let (tie_column, tie_row) = (tie_point[0], tie_point[1]);
+    let (tie_longitude, tie_latitude) = (tie_point[3], tie_point[4]);
+
+    Ok(ElevationTile {
+        north_latitude: tie_latitude + (tie_row - pixel_offset) * latitude_step,
+        west_longitude: tie_longitude - (tie_column - pixel_offset) * longitude_step,
+        latitude_step,
+        longitude_step,
+        rows,
+        columns,
+        samples,
+    })
+}
+
+/// Reads a short value from a GeoKeyDirectory: a 4-value header followed by (key, location, count, value) entries.
+fn geo_key(geo_keys: &[u16], key: u16) -> Option<u16> {
+    geo_keys
+        .get(4..)?
+        .chunks_exact(4)
+        .find(|entry| entry[0] == key && entry[1] == 0)
+        .map(|entry| entry[3])
+}
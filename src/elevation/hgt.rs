@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::ElevationTile;
+
+/// SRTM marks missing samples with the lowest 16-bit value.
+const HGT_VOID: i16 = -32768;
+
+/// Loads an SRTM `.hgt` tile. The tile position is encoded in the file name (e.g. `N51E005.hgt`),
+/// the resolution follows from the file size: 1201x1201 for 3 arc-seconds, 3601x3601 for 1 arc-second.
+pub fn load_hgt_tile(file_path: &Path) -> Result<ElevationTile, Box<dyn Error>> {
+    let file_stem = file_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("Invalid .hgt file name")?;
+
+    let (south_latitude, west_longitude) = parse_hgt_name(file_stem)?;
+
+    let bytes = fs::read(file_path)?;
+    let sample_count = bytes.len() / 2;
+    let size = (sample_count as f64).sqrt() as usize;
+
+    if size < 2 || size * size != sample_count || bytes.len() % 2 != 0 {
+        return Err(format!("Unexpected .hgt file size: {} bytes", bytes.len()).into());
+    }
+
+    let samples: Vec<f32> = bytes
+        .chunks_exact(2)
+        .map(|chunk| {
+            let sample = i16::from_be_bytes([chunk[0], chunk[1]]);
+            if sample == HGT_VOID {
+                f32::NAN
+            } else {
+                sample as f32
+            }
+        })
+        .collect();
+
+    let step = 1.0 / (size - 1) as f64;
+
+    Ok(ElevationTile {
+        north_latitude: south_latitude + 1.0,
+        west_longitude,
+        latitude_step: step,
+        longitude_step: step,
+        rows: size,
+        columns: size,
+        samples,
+    })
+}
+
+/// Parses names like `N51E005` into the latitude/longitude of the south-western corner.
+fn parse_hgt_name(file_stem: &str) -> Result<(f64, f64), Box<dyn Error>> {
+    let file_stem = file_stem.to_uppercase();
+
+    if file_stem.len() != 7 || !file_stem.is_ascii() {
+        return Err(format!("Cannot read tile position from {}", file_stem).into());
+    }
+
+    let latitude = file_stem[1..3].parse::<f64>()?;
+    let longitude = file_stem[4..7].parse::<f64>()?;
+
+    let latitude = match &file_stem[0..1] {
+        "N" => latitude,
+        "S" => -latitude,
+        _ => return Err(format!("Cannot read tile latitude from {}", file_stem).into()),
+    };
+    let longitude = match &file_stem[3..4] {
+        "E" => longitude,
+        "W" => -longitude,
+        _ => return Err(format!("Cannot read tile longitude from {}", file_stem).into()),
+    };
+
+    Ok((latitude, longitude))
+}
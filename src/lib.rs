@@ -1,5 +1,6 @@
 pub mod analysis;
 pub mod data_handling;
+pub mod elevation;
 pub mod osm_parsing;
 pub mod path_finding;
 pub mod public_transport;
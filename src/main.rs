@@ -8,7 +8,10 @@ use osm_rust::analysis::amenity_analysis::amenity_analysis;
 use osm_rust::data_handling::FilterSubset::Landmark;
 use osm_rust::data_handling::OSMData;
 use osm_rust::path_finding::TransportMode;
+use osm_rust::public_transport::{NsApi, TransitOptions};
 use osm_rust::route_manager::transport_options::search_routes;
+use osm_rust::route_manager::{RouteCriterion, RouteTime};
+use osm_rust::utils::cli_interface::launch_mapped_cli_interface;
 use osm_rust::utils::coordinate_files::load_coordinate_file;
 use osm_rust::utils::hashmap_creation::recreate_hashmap;
 use osm_rust::utils::node_examples::get_path_example;
@@ -37,6 +40,10 @@ fn main() {
 
     // let osm_data: OSMData = OSMData::new(file_path);
 
+    // Queries a map saved with `osm_data.save_mapped(..)` without loading it:
+    // launch_mapped_cli_interface(Path::new("data/hashmaps/netherlands.graph"))
+    //     .expect("Failed to open mapped map");
+
     info!(
         "Loaded in the data - took {:.3}s",
         start_time.elapsed().as_secs_f64()
@@ -46,17 +53,37 @@ fn main() {
 
     let (starting_node_id, target_node_id) = get_path_example(&PathExamples::Weert);
     let transport_mode = TransportMode::Bike(20.0 / 3.6);
-    let time = chrono::Utc::now();
-    let minimum_distance_to_station = 8000.0;
+    let time = RouteTime::DepartAt(chrono::Utc::now());
+    let ns_api = NsApi::default();
+    let transit_options = TransitOptions {
+        provider: &ns_api,
+        minimum_distance_to_station: 8000.0,
+    };
 
-    search_routes(
+    let search_result = search_routes(
         &osm_data,
         &transport_mode,
         time,
         starting_node_id,
         target_node_id,
-        minimum_distance_to_station,
+        &transit_options,
+        None,
     );
 
+    for error in search_result.errors.iter() {
+        warn!("{}", error);
+    }
+    if search_result.routes.is_empty() {
+        warn!("No routes found.")
+    }
+    for (index, route) in search_result.routes.iter().enumerate() {
+        if index == 0 {
+            println!("Best route:");
+        } else {
+            println!("Alternative route {}:", index);
+        }
+        route.print_route(&osm_data);
+    }
+
     std::process::exit(0);
 }
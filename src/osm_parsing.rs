@@ -1,11 +1,77 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use geo::Point;
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 
+use crate::data_handling::{Area, NodeSubset};
+use crate::utils::string_table::Symbol;
+
+pub mod change_parsing;
 pub mod osm_data_types;
 pub mod osm_parsing;
+pub mod osm_writing;
+pub mod pbf_writing;
 pub mod state_machine;
+pub mod tags;
+
+/// Everything read from a map file, whether parsed from XML or loaded from a cache.
+pub type MapContents = (
+    HashMap<u64, Node>,
+    HashMap<u64, Way>,
+    HashMap<u64, Relation>,
+    HashMap<u64, Area>,
+    Vec<NodeSubset>,
+);
+
+/// Options for reading a map file.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Keep the version, timestamp, changeset and author of every element, at the cost of memory.
+    pub keep_metadata: bool,
+}
+
+/// The editing history attributes of an OSM element. Each is optional in the source data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElementMetadata {
+    pub version: Option<u32>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub changeset: Option<u64>,
+    pub uid: Option<u64>,
+    pub user: Option<String>,
+}
+
+/// Metadata of the nodes, ways and relations in a map, by element ID.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataStore {
+    pub nodes: HashMap<u64, ElementMetadata>,
+    pub ways: HashMap<u64, ElementMetadata>,
+    pub relations: HashMap<u64, ElementMetadata>,
+}
+
+/// One element of an osmChange (`.osc`) file, with its contents after the change.
+#[derive(Debug)]
+pub struct OsmChange {
+    pub action: ChangeAction,
+    pub element: ChangedElement,
+    pub metadata: Option<ElementMetadata>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeAction {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// A deleted element may come without coordinates, tags or members; only its ID is used.
+#[derive(Debug)]
+pub enum ChangedElement {
+    Node(Node),
+    Way(Way),
+    Relation(Relation),
+}
 
 #[derive(Debug)]
 pub enum CurrentlyReading {
@@ -15,21 +81,54 @@ pub enum CurrentlyReading {
     None,
 }
 
+/// The tags of a node, way or relation as interned key and value pairs, in the order they were added.
+/// Most elements have only a few tags, which are stored inline.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+    pairs: SmallVec<[(Symbol, Symbol); 3]>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct Node {
     pub id: u64,
     pub coordinate: Point,
 
-    pub tags: HashMap<String, String>,
+    pub tags: Tags,
     pub ways: Vec<u64>,
     pub nodes: Vec<u64>,
+
+    /// Meters above sea level, attached from an elevation model after loading.
+    pub elevation: Option<f64>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Way {
     pub id: u64,
     pub node_ids: Vec<u64>,
-    pub tags: HashMap<String, String>,
+    pub tags: Tags,
+}
+
+/// A relation with its ordered members. Only ways and nodes are resolved by the rest of the crate.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Relation {
+    pub id: u64,
+    pub members: Vec<RelationMember>,
+    pub tags: Tags,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RelationMember {
+    pub member_type: MemberType,
+    pub member_id: u64,
+    /// E.g. "outer" or "inner" for boundaries and multipolygons; often empty.
+    pub role: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum MemberType {
+    Node,
+    Way,
+    Relation,
 }
 
 pub struct StateMachine {
@@ -0,0 +1,163 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+#[allow(unused)]
+use log::{info, warn};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::osm_parsing::{
+    ChangeAction, ChangedElement, ElementMetadata, MemberType, Node, OsmChange, Relation,
+    RelationMember, Way,
+};
+use crate::utils::attributes::read_attributes;
+
+impl ChangedElement {
+    pub fn id(&self) -> u64 {
+        match self {
+            ChangedElement::Node(node) => node.id,
+            ChangedElement::Way(way) => way.id,
+            ChangedElement::Relation(relation) => relation.id,
+        }
+    }
+
+    pub fn member_type(&self) -> MemberType {
+        match self {
+            ChangedElement::Node(_) => MemberType::Node,
+            ChangedElement::Way(_) => MemberType::Way,
+            ChangedElement::Relation(_) => MemberType::Relation,
+        }
+    }
+}
+
+/// Reads an osmChange file, gzipped if it ends in `.gz`, into its changes in file order.
+pub fn parse_change_file(file_path: &Path) -> Result<Vec<OsmChange>, Box<dyn Error>> {
+    let file = File::open(file_path)?;
+    let reader: Box<dyn Read> = match file_path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+
+    parse_changes(BufReader::new(reader))
+}
+
+pub fn parse_changes<R: BufRead>(reader: R) -> Result<Vec<OsmChange>, Box<dyn Error>> {
+    let mut reader = Reader::from_reader(reader);
+    reader.config_mut().trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut changes = Vec::new();
+    let mut action: Option<ChangeAction> = None;
+    let mut element: Option<(ChangedElement, Option<ElementMetadata>)> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Eof => break,
+            Event::Start(e) => start_element(&e, &mut action, &mut element)?,
+            Event::Empty(e) => {
+                start_element(&e, &mut action, &mut element)?;
+                finish_element(e.name().as_ref(), action, &mut element, &mut changes)?;
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"create" | b"modify" | b"delete" => action = None,
+                name => finish_element(name, action, &mut element, &mut changes)?,
+            },
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    Ok(changes)
+}
+
+fn start_element(
+    e: &BytesStart,
+    action: &mut Option<ChangeAction>,
+    element: &mut Option<(ChangedElement, Option<ElementMetadata>)>,
+) -> Result<(), Box<dyn Error>> {
+    let mut contents = read_attributes(e.attributes());
+
+    match e.name().as_ref() {
+        b"osmChange" => (),
+        b"create" => *action = Some(ChangeAction::Create),
+        b"modify" => *action = Some(ChangeAction::Modify),
+        b"delete" => *action = Some(ChangeAction::Delete),
+        b"node" => {
+            // Deleted nodes may come without a location.
+            if *action == Some(ChangeAction::Delete) {
+                contents.entry("lat".to_string()).or_insert("0".to_string());
+                contents.entry("lon".to_string()).or_insert("0".to_string());
+            }
+            let metadata = ElementMetadata::from_attributes(&contents);
+            *element = Some((ChangedElement::Node(Node::new(contents)), metadata));
+        }
+        b"way" => {
+            let metadata = ElementMetadata::from_attributes(&contents);
+            *element = Some((ChangedElement::Way(Way::new(contents)), metadata));
+        }
+        b"relation" => {
+            let metadata = ElementMetadata::from_attributes(&contents);
+            *element = Some((ChangedElement::Relation(Relation::new(contents)), metadata));
+        }
+        b"tag" => {
+            let (Some(key), Some(value)) = (contents.get("k"), contents.get("v")) else {
+                return Err("Tag without a key or value".into());
+            };
+            match element {
+                Some((ChangedElement::Node(node), _)) => node.tags.insert(key, value),
+                Some((ChangedElement::Way(way), _)) => way.tags.insert(key, value),
+                Some((ChangedElement::Relation(relation), _)) => relation.tags.insert(key, value),
+                None => warn!("Encountered a tag outside an element"),
+            }
+        }
+        b"nd" => {
+            let node_id = contents
+                .get("ref")
+                .ok_or("Way node without a reference")?
+                .parse::<u64>()?;
+            match element {
+                Some((ChangedElement::Way(way), _)) => way.node_ids.push(node_id),
+                _ => warn!("Encountered a way node outside a way"),
+            }
+        }
+        b"member" => match (element, RelationMember::new(contents)) {
+            (Some((ChangedElement::Relation(relation), _)), Some(member)) => {
+                relation.members.push(member)
+            }
+            (Some((ChangedElement::Relation(_), _)), None) => warn!("Skipped an unreadable member"),
+            _ => warn!("Encountered a member outside a relation"),
+        },
+        _ => warn!("Encountered name {:?}", e.name()),
+    }
+
+    Ok(())
+}
+
+fn finish_element(
+    name: &[u8],
+    action: Option<ChangeAction>,
+    element: &mut Option<(ChangedElement, Option<ElementMetadata>)>,
+    changes: &mut Vec<OsmChange>,
+) -> Result<(), Box<dyn Error>> {
+    if !matches!(name, b"node" | b"way" | b"relation") {
+        return Ok(());
+    }
+
+    let (element, metadata) = element.take().ok_or("Element ended before it started")?;
+    let action = action.ok_or_else(|| {
+        format!(
+            "Element {} is not inside a create, modify or delete block",
+            element.id()
+        )
+    })?;
+
+    changes.push(OsmChange {
+        action,
+        element,
+        metadata,
+    });
+    Ok(())
+}
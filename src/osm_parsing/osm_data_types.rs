@@ -1,13 +1,16 @@
 use core::fmt;
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use geo::Point;
 
-use crate::osm_parsing::{Node, Way};
+use crate::osm_parsing::{
+    ElementMetadata, MemberType, MetadataStore, Node, Relation, RelationMember, Tags, Way,
+};
 
 impl Node {
     pub fn new(node_hashmap: HashMap<String, String>) -> Node {
-        let tags: std::collections::HashMap<String, String> = HashMap::new();
+        let tags = Tags::new();
 
         let nodes: Vec<u64> = Vec::<u64>::new();
         let ways: Vec<u64> = Vec::<u64>::new();
@@ -36,6 +39,7 @@ impl Node {
             tags,
             ways,
             nodes,
+            elevation: None,
         }
     }
 
@@ -71,7 +75,7 @@ impl fmt::Debug for Node {
 impl Way {
     pub fn new(way_hashmap: HashMap<String, String>) -> Way {
         let node_ids: Vec<u64> = Vec::<u64>::new();
-        let tags: HashMap<String, String> = HashMap::new();
+        let tags = Tags::new();
 
         let id = way_hashmap
             .get("id")
@@ -88,3 +92,109 @@ impl Way {
         base_url + &node_id
     }
 }
+
+impl Relation {
+    pub fn new(relation_hashmap: HashMap<String, String>) -> Relation {
+        let id = relation_hashmap
+            .get("id")
+            .unwrap()
+            .parse::<u64>()
+            .expect("Failed to parse id");
+
+        Relation {
+            id,
+            members: Vec::new(),
+            tags: Tags::new(),
+        }
+    }
+
+    pub fn map_link(&self) -> String {
+        let base_url = "https://www.openstreetmap.org/relation/".to_string();
+        let relation_id = self.id.to_string();
+        base_url + &relation_id
+    }
+
+    /// IDs of the way members with the given role, in member order.
+    pub fn way_members<'a>(&'a self, role: &'a str) -> impl Iterator<Item = u64> + 'a {
+        self.members
+            .iter()
+            .filter(move |member| member.member_type == MemberType::Way && member.role == role)
+            .map(|member| member.member_id)
+    }
+}
+
+impl RelationMember {
+    /// Reads a `<member type=".." ref=".." role=".."/>` element. Returns None for unknown member types.
+    pub fn new(member_hashmap: HashMap<String, String>) -> Option<RelationMember> {
+        let member_type = match member_hashmap.get("type")?.as_str() {
+            "node" => MemberType::Node,
+            "way" => MemberType::Way,
+            "relation" => MemberType::Relation,
+            _ => return None,
+        };
+
+        let member_id = member_hashmap.get("ref")?.parse::<u64>().ok()?;
+        let role = member_hashmap.get("role").cloned().unwrap_or_default();
+
+        Some(RelationMember {
+            member_type,
+            member_id,
+            role,
+        })
+    }
+}
+
+impl ElementMetadata {
+    /// Reads the metadata attributes of a `<node>`, `<way>` or `<relation>` element. Returns None if it
+    /// has none, as in extracts exported without metadata.
+    pub fn from_attributes(attributes: &HashMap<String, String>) -> Option<ElementMetadata> {
+        let metadata = ElementMetadata {
+            version: attributes
+                .get("version")
+                .and_then(|value| value.parse().ok()),
+            timestamp: attributes
+                .get("timestamp")
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|timestamp| timestamp.with_timezone(&Utc)),
+            changeset: attributes
+                .get("changeset")
+                .and_then(|value| value.parse().ok()),
+            uid: attributes.get("uid").and_then(|value| value.parse().ok()),
+            user: attributes.get("user").cloned(),
+        };
+
+        let is_empty = metadata.version.is_none()
+            && metadata.timestamp.is_none()
+            && metadata.changeset.is_none()
+            && metadata.uid.is_none()
+            && metadata.user.is_none();
+
+        (!is_empty).then_some(metadata)
+    }
+}
+
+impl MetadataStore {
+    pub fn insert(&mut self, member_type: MemberType, id: u64, metadata: ElementMetadata) {
+        self.elements_mut(member_type).insert(id, metadata);
+    }
+
+    pub fn remove(&mut self, member_type: MemberType, id: u64) {
+        self.elements_mut(member_type).remove(&id);
+    }
+
+    pub fn get(&self, member_type: MemberType, id: u64) -> Option<&ElementMetadata> {
+        match member_type {
+            MemberType::Node => self.nodes.get(&id),
+            MemberType::Way => self.ways.get(&id),
+            MemberType::Relation => self.relations.get(&id),
+        }
+    }
+
+    fn elements_mut(&mut self, member_type: MemberType) -> &mut HashMap<u64, ElementMetadata> {
+        match member_type {
+            MemberType::Node => &mut self.nodes,
+            MemberType::Way => &mut self.ways,
+            MemberType::Relation => &mut self.relations,
+        }
+    }
+}
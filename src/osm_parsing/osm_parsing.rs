@@ -2,7 +2,10 @@ use log::{error, warn};
 
 use crate::data_handling::NodeSubset;
 use crate::osm_parsing::{CurrentlyReading, StateMachine};
-use crate::osm_parsing::{Node, Way};
+use crate::osm_parsing::{
+    ElementMetadata, MapContents, MemberType, MetadataStore, Node, ParseOptions, Relation,
+    RelationMember, Way,
+};
 use crate::utils::attributes::read_attributes;
 
 use quick_xml::events::Event;
@@ -11,7 +14,15 @@ use quick_xml::Reader;
 use std::collections::HashMap;
 use std::path::Path;
 
-pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Vec<NodeSubset>) {
+pub fn parse_xml(file_path: &Path) -> MapContents {
+    parse_xml_with_options(file_path, &ParseOptions::default()).0
+}
+
+/// Parses a map file, also returning the metadata of its elements if `keep_metadata` is set.
+pub fn parse_xml_with_options(
+    file_path: &Path,
+    options: &ParseOptions,
+) -> (MapContents, Option<MetadataStore>) {
     let mut reader = Reader::from_file(file_path).expect("Failed to create reader from file");
 
     reader.config_mut().trim_text(true);
@@ -20,7 +31,9 @@ pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Ve
 
     let mut node_map: HashMap<u64, Node> = HashMap::new();
     let mut way_map: HashMap<u64, Way> = HashMap::new();
+    let mut relation_map: HashMap<u64, Relation> = HashMap::new();
     let node_subset: Vec<NodeSubset> = Vec::new();
+    let mut metadata = options.keep_metadata.then(MetadataStore::default);
 
     let mut state_machine = StateMachine::new();
 
@@ -28,7 +41,10 @@ pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Ve
         match reader.read_event_into(&mut buf) {
             Err(e) => {
                 error!("Error at position {}: {:?}", reader.error_position(), e);
-                return (node_map, way_map, node_subset);
+                return (
+                    (node_map, way_map, relation_map, HashMap::new(), node_subset),
+                    metadata,
+                );
             }
             Ok(Event::Eof) => break,
             Ok(Event::Start(e)) => {
@@ -36,19 +52,25 @@ pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Ve
                 match e.name().as_ref() {
                     b"osm" => (),
                     b"node" => {
+                        record_metadata(&mut metadata, MemberType::Node, &contents);
                         let node = Node::new(contents);
 
                         state_machine.update(CurrentlyReading::Node(node.id));
                         node_map.insert(node.id, node);
                     }
                     b"way" => {
+                        record_metadata(&mut metadata, MemberType::Way, &contents);
                         let way = Way::new(contents);
 
                         state_machine.update(CurrentlyReading::Way(way.id));
                         way_map.insert(way.id, way);
                     }
                     b"relation" => {
-                        state_machine.update(CurrentlyReading::Relation(0));
+                        record_metadata(&mut metadata, MemberType::Relation, &contents);
+                        let relation = Relation::new(contents);
+
+                        state_machine.update(CurrentlyReading::Relation(relation.id));
+                        relation_map.insert(relation.id, relation);
                     }
                     _ => (),
                 };
@@ -60,6 +82,7 @@ pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Ve
                 match e.name().as_ref() {
                     b"node" => {
                         let contents: HashMap<String, String> = read_attributes(e.attributes());
+                        record_metadata(&mut metadata, MemberType::Node, &contents);
                         let node: Node = Node::new(contents);
 
                         state_machine.update(CurrentlyReading::Node(node.id));
@@ -80,15 +103,19 @@ pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Ve
                             CurrentlyReading::Node(id) => {
                                 if let Some(node) = node_map.get_mut(&id) {
                                     // info!("Encountered a tag: {}: {}", key, value);
-                                    node.tags.insert(key, value);
+                                    node.tags.insert(&key, &value);
                                 }
                             }
                             CurrentlyReading::Way(id) => {
                                 if let Some(way) = way_map.get_mut(&id) {
-                                    way.tags.insert(key, value);
+                                    way.tags.insert(&key, &value);
+                                }
+                            }
+                            CurrentlyReading::Relation(id) => {
+                                if let Some(relation) = relation_map.get_mut(id) {
+                                    relation.tags.insert(&key, &value);
                                 }
                             }
-                            CurrentlyReading::Relation(_) => (),
                             _ => warn!(
                                 "Trying to read tag: encountered state machine in {:?}",
                                 state_machine.current_status()
@@ -122,7 +149,17 @@ pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Ve
                             }
                         }
                     }
-                    b"member" => (),
+                    b"member" => {
+                        let contents = read_attributes(e.attributes());
+
+                        if let CurrentlyReading::Relation(id) = state_machine.current_status() {
+                            if let (Some(relation), Some(member)) =
+                                (relation_map.get_mut(id), RelationMember::new(contents))
+                            {
+                                relation.members.push(member);
+                            }
+                        }
+                    }
                     b"bounds" => (),
                     b"meta" => (),
                     _ => warn!("Encountered name {:?}", e.name()),
@@ -133,5 +170,25 @@ pub fn parse_xml(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Ve
     }
     buf.clear();
 
-    (node_map, way_map, node_subset)
+    (
+        (node_map, way_map, relation_map, HashMap::new(), node_subset),
+        metadata,
+    )
+}
+
+fn record_metadata(
+    metadata: &mut Option<MetadataStore>,
+    member_type: MemberType,
+    attributes: &HashMap<String, String>,
+) {
+    let Some(metadata) = metadata else {
+        return;
+    };
+
+    if let (Some(id), Some(element_metadata)) = (
+        attributes.get("id").and_then(|id| id.parse::<u64>().ok()),
+        ElementMetadata::from_attributes(attributes),
+    ) {
+        metadata.insert(member_type, id, element_metadata);
+    }
 }
@@ -0,0 +1,216 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[allow(unused)]
+use log::{info, warn};
+use quick_xml::escape::escape;
+
+use crate::data_handling::areas::WAY_AREA_NODE_OFFSET;
+use crate::data_handling::validation::WAY_PIECE_OFFSET;
+use crate::data_handling::OSMData;
+use crate::osm_parsing::{ElementMetadata, MemberType, Node, Relation, RelationMember, Tags, Way};
+
+/// The nodes, ways and relations to export, each sorted by ID as OSM tools expect. Area nodes and way
+/// pieces are derived from ways and relations rather than OSM elements, so they are left out. Relations
+/// only keep the members that are exported, and relations without any are left out.
+pub(crate) fn sorted_elements(osm_data: &OSMData) -> (Vec<&Node>, Vec<&Way>, Vec<Relation>) {
+    let mut nodes: Vec<&Node> = osm_data
+        .node_map
+        .values()
+        .filter(|node| node.id < WAY_AREA_NODE_OFFSET)
+        .collect();
+    nodes.sort_unstable_by_key(|node| node.id);
+
+    let mut ways: Vec<&Way> = osm_data
+        .way_map
+        .values()
+        .filter(|way| way.id < WAY_PIECE_OFFSET)
+        .collect();
+    ways.sort_unstable_by_key(|way| way.id);
+
+    let is_exported = |member: &RelationMember, relation_ids: &HashSet<u64>| match member
+        .member_type
+    {
+        MemberType::Node => {
+            member.member_id < WAY_AREA_NODE_OFFSET
+                && osm_data.node_map.contains_key(&member.member_id)
+        }
+        MemberType::Way => {
+            member.member_id < WAY_PIECE_OFFSET && osm_data.way_map.contains_key(&member.member_id)
+        }
+        MemberType::Relation => relation_ids.contains(&member.member_id),
+    };
+
+    // Leaving out a relation can leave the relations it is a member of empty.
+    let mut relation_ids: HashSet<u64> = osm_data.relation_map.keys().copied().collect();
+    loop {
+        let kept_relation_ids: HashSet<u64> = relation_ids
+            .iter()
+            .filter(|relation_id| {
+                osm_data.relation_map[relation_id]
+                    .members
+                    .iter()
+                    .any(|member| is_exported(member, &relation_ids))
+            })
+            .copied()
+            .collect();
+
+        if kept_relation_ids.len() == relation_ids.len() {
+            break;
+        }
+        relation_ids = kept_relation_ids;
+    }
+
+    let mut relations: Vec<Relation> = relation_ids
+        .iter()
+        .map(|relation_id| {
+            let relation = &osm_data.relation_map[relation_id];
+            Relation {
+                id: relation.id,
+                members: relation
+                    .members
+                    .iter()
+                    .filter(|member| is_exported(member, &relation_ids))
+                    .cloned()
+                    .collect(),
+                tags: relation.tags.clone(),
+            }
+        })
+        .collect();
+    relations.sort_unstable_by_key(|relation| relation.id);
+
+    (nodes, ways, relations)
+}
+
+/// Writes the map as an OSM XML file that `parse_xml`, JOSM and osmium can read. Metadata is written
+/// for the elements that have it.
+pub fn write_xml(osm_data: &OSMData, file_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    let (nodes, ways, relations) = sorted_elements(osm_data);
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<osm version="0.6" generator="osm-rust">"#)?;
+
+    if let Some([minimum_longitude, minimum_latitude, maximum_longitude, maximum_latitude]) =
+        osm_data.build_settings.clip_bounds
+    {
+        writeln!(
+            writer,
+            r#" <bounds minlat="{:.7}" minlon="{:.7}" maxlat="{:.7}" maxlon="{:.7}"/>"#,
+            minimum_latitude, minimum_longitude, maximum_latitude, maximum_longitude
+        )?;
+    }
+
+    for node in nodes.iter() {
+        write!(
+            writer,
+            r#" <node id="{}"{} lat="{:.7}" lon="{:.7}""#,
+            node.id,
+            metadata_attributes(osm_data.node_metadata(node.id)),
+            node.coordinate.y(),
+            node.coordinate.x()
+        )?;
+
+        if node.tags.is_empty() {
+            writeln!(writer, "/>")?;
+        } else {
+            writeln!(writer, ">")?;
+            write_tags(&mut writer, &node.tags)?;
+            writeln!(writer, " </node>")?;
+        }
+    }
+
+    for way in ways.iter() {
+        writeln!(
+            writer,
+            r#" <way id="{}"{}>"#,
+            way.id,
+            metadata_attributes(osm_data.way_metadata(way.id))
+        )?;
+        for node_id in way.node_ids.iter() {
+            writeln!(writer, r#"  <nd ref="{}"/>"#, node_id)?;
+        }
+        write_tags(&mut writer, &way.tags)?;
+        writeln!(writer, " </way>")?;
+    }
+
+    for relation in relations.iter() {
+        writeln!(
+            writer,
+            r#" <relation id="{}"{}>"#,
+            relation.id,
+            metadata_attributes(osm_data.relation_metadata(relation.id))
+        )?;
+        for member in relation.members.iter() {
+            writeln!(
+                writer,
+                r#"  <member type="{}" ref="{}" role="{}"/>"#,
+                member_type_name(member.member_type),
+                member.member_id,
+                escape(&member.role)
+            )?;
+        }
+        write_tags(&mut writer, &relation.tags)?;
+        writeln!(writer, " </relation>")?;
+    }
+
+    writeln!(writer, "</osm>")?;
+    writer.flush()?;
+
+    info!(
+        "Wrote {} nodes, {} ways and {} relations to {}",
+        nodes.len(),
+        ways.len(),
+        relations.len(),
+        file_path.display()
+    );
+
+    Ok(())
+}
+
+fn member_type_name(member_type: MemberType) -> &'static str {
+    match member_type {
+        MemberType::Node => "node",
+        MemberType::Way => "way",
+        MemberType::Relation => "relation",
+    }
+}
+
+fn write_tags<W: Write>(writer: &mut W, tags: &Tags) -> Result<(), Box<dyn Error>> {
+    for (key, value) in tags.iter() {
+        writeln!(
+            writer,
+            r#"  <tag k="{}" v="{}"/>"#,
+            escape(key),
+            escape(value)
+        )?;
+    }
+    Ok(())
+}
+
+fn metadata_attributes(metadata: Option<&ElementMetadata>) -> String {
+    let Some(metadata) = metadata else {
+        return String::new();
+    };
+
+    let mut attributes = String::new();
+    if let Some(version) = metadata.version {
+        attributes += &format!(r#" version="{}""#, version);
+    }
+    if let Some(timestamp) = metadata.timestamp {
+        attributes += &format!(r#" timestamp="{}""#, timestamp.format("%Y-%m-%dT%H:%M:%SZ"));
+    }
+    if let Some(changeset) = metadata.changeset {
+        attributes += &format!(r#" changeset="{}""#, changeset);
+    }
+    if let Some(uid) = metadata.uid {
+        attributes += &format!(r#" uid="{}""#, uid);
+    }
+    if let Some(user) = &metadata.user {
+        attributes += &format!(r#" user="{}""#, escape(user));
+    }
+    attributes
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::OSMData;
+use crate::osm_parsing::osm_writing::sorted_elements;
+use crate::osm_parsing::{ElementMetadata, MemberType, Node, Relation, Tags, Way};
+
+/// Elements per primitive block; osmium and the reference writers use the same limit.
+const BLOCK_SIZE: usize = 8000;
+/// Coordinates are stored in units of 100 nanodegrees, the default granularity.
+const GRANULARITY: i64 = 100;
+
+/// Writes the map as an OSM PBF file with dense nodes. Metadata is written for the elements that have
+/// it.
+pub fn write_pbf(osm_data: &OSMData, file_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    let (nodes, ways, relations) = sorted_elements(osm_data);
+
+    write_blob(&mut writer, "OSMHeader", &header_block(osm_data).bytes)?;
+
+    for nodes in nodes.chunks(BLOCK_SIZE) {
+        let block = primitive_block(|string_table| dense_nodes(osm_data, nodes, string_table));
+        write_blob(&mut writer, "OSMData", &block.bytes)?;
+    }
+    for ways in ways.chunks(BLOCK_SIZE) {
+        let block = primitive_block(|string_table| way_group(osm_data, ways, string_table));
+        write_blob(&mut writer, "OSMData", &block.bytes)?;
+    }
+    for relations in relations.chunks(BLOCK_SIZE) {
+        let block =
+            primitive_block(|string_table| relation_group(osm_data, relations, string_table));
+        write_blob(&mut writer, "OSMData", &block.bytes)?;
+    }
+
+    writer.flush()?;
+
+    info!(
+        "Wrote {} nodes, {} ways and {} relations to {}",
+        nodes.len(),
+        ways.len(),
+        relations.len(),
+        file_path.display()
+    );
+
+    Ok(())
+}
+
+/// A protobuf message under construction. Fields are appended in the order they are written.
+#[derive(Default)]
+struct Message {
+    bytes: Vec<u8>,
+}
+
+impl Message {
+    fn varint(&mut self, field: u32, value: u64) {
+        self.key(field, 0);
+        write_varint(&mut self.bytes, value);
+    }
+
+    fn sint(&mut self, field: u32, value: i64) {
+        self.varint(field, zigzag(value));
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        write_varint(&mut self.bytes, value.len() as u64);
+        self.bytes.extend_from_slice(value);
+    }
+
+    fn message(&mut self, field: u32, message: &Message) {
+        self.bytes(field, &message.bytes);
+    }
+
+    fn packed_varints(&mut self, field: u32, values: impl IntoIterator<Item = u64>) {
+        let mut packed = Vec::new();
+        for value in values {
+            write_varint(&mut packed, value);
+        }
+        self.bytes(field, &packed);
+    }
+
+    /// Packs signed values as the differences between consecutive ones, as PBF does for IDs and
+    /// coordinates.
+    fn packed_deltas(&mut self, field: u32, values: impl IntoIterator<Item = i64>) {
+        let mut previous = 0;
+        self.packed_varints(
+            field,
+            values.into_iter().map(|value| {
+                let delta = zigzag(value - previous);
+                previous = value;
+                delta
+            }),
+        );
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        write_varint(&mut self.bytes, ((field as u64) << 3) | wire_type as u64);
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// The strings of a primitive block, which its elements refer to by index. Index 0 is reserved.
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn new() -> Self {
+        StringTable {
+            strings: vec![String::new()],
+            indices: HashMap::new(),
+        }
+    }
+
+    fn index(&mut self, string: &str) -> u32 {
+        if let Some(index) = self.indices.get(string) {
+            return *index;
+        }
+
+        let index = self.strings.len() as u32;
+        self.strings.push(string.to_string());
+        self.indices.insert(string.to_string(), index);
+        index
+    }
+}
+
+fn write_blob<W: Write>(
+    writer: &mut W,
+    blob_type: &str,
+    contents: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(contents)?;
+
+    let mut blob = Message::default();
+    blob.varint(2, contents.len() as u64);
+    blob.bytes(3, &encoder.finish()?);
+
+    let mut blob_header = Message::default();
+    blob_header.bytes(1, blob_type.as_bytes());
+    blob_header.varint(3, blob.bytes.len() as u64);
+
+    writer.write_all(&(blob_header.bytes.len() as u32).to_be_bytes())?;
+    writer.write_all(&blob_header.bytes)?;
+    writer.write_all(&blob.bytes)?;
+    Ok(())
+}
+
+fn header_block(osm_data: &OSMData) -> Message {
+    let mut header = Message::default();
+
+    if let Some([minimum_longitude, minimum_latitude, maximum_longitude, maximum_latitude]) =
+        osm_data.build_settings.clip_bounds
+    {
+        let mut bounding_box = Message::default();
+        bounding_box.sint(1, nanodegrees(minimum_longitude));
+        bounding_box.sint(2, nanodegrees(maximum_longitude));
+        bounding_box.sint(3, nanodegrees(maximum_latitude));
+        bounding_box.sint(4, nanodegrees(minimum_latitude));
+        header.message(1, &bounding_box);
+    }
+
+    header.bytes(4, b"OsmSchema-V0.6");
+    header.bytes(4, b"DenseNodes");
+    header.bytes(16, b"osm-rust");
+    header
+}
+
+/// A block with one primitive group, built by `group` while it fills the block's string table.
+fn primitive_block(group: impl FnOnce(&mut StringTable) -> Message) -> Message {
+    let mut string_table = StringTable::new();
+    let group = group(&mut string_table);
+
+    let mut strings = Message::default();
+    for string in string_table.strings.iter() {
+        strings.bytes(1, string.as_bytes());
+    }
+
+    let mut block = Message::default();
+    block.message(1, &strings);
+    block.message(2, &group);
+    block.varint(17, GRANULARITY as u64);
+    block
+}
+
+fn dense_nodes(osm_data: &OSMData, nodes: &[&Node], string_table: &mut StringTable) -> Message {
+    let mut dense = Message::default();
+    dense.packed_deltas(1, nodes.iter().map(|node| node.id as i64));
+
+    let metadata: Vec<Option<&ElementMetadata>> = nodes
+        .iter()
+        .map(|node| osm_data.node_metadata(node.id))
+        .collect();
+    if metadata.iter().any(Option::is_some) {
+        dense.message(5, &dense_info(&metadata, string_table));
+    }
+
+    dense.packed_deltas(8, nodes.iter().map(|node| granular(node.coordinate.y())));
+    dense.packed_deltas(9, nodes.iter().map(|node| granular(node.coordinate.x())));
+
+    // Tags of all nodes in one list, each node's tags ending in a 0.
+    let mut keys_values = Vec::new();
+    for node in nodes.iter() {
+        for (key, value) in node.tags.iter() {
+            keys_values.push(string_table.index(key) as u64);
+            keys_values.push(string_table.index(value) as u64);
+        }
+        keys_values.push(0);
+    }
+    dense.packed_varints(10, keys_values);
+
+    let mut group = Message::default();
+    group.message(2, &dense);
+    group
+}
+
+/// Metadata of every node in a dense block; nodes without it get empty values.
+fn dense_info(metadata: &[Option<&ElementMetadata>], string_table: &mut StringTable) -> Message {
+    let mut dense_info = Message::default();
+    dense_info.packed_varints(
+        1,
+        metadata
+            .iter()
+            .map(|metadata| metadata.and_then(|metadata| metadata.version).unwrap_or(0) as u64),
+    );
+    dense_info.packed_deltas(
+        2,
+        metadata.iter().map(|metadata| {
+            metadata
+                .and_then(|metadata| metadata.timestamp)
+                .map_or(0, |timestamp| timestamp.timestamp())
+        }),
+    );
+    dense_info.packed_deltas(
+        3,
+        metadata.iter().map(|metadata| {
+            metadata
+                .and_then(|metadata| metadata.changeset)
+                .unwrap_or(0) as i64
+        }),
+    );
+    dense_info.packed_deltas(
+        4,
+        metadata
+            .iter()
+            .map(|metadata| metadata.and_then(|metadata| metadata.uid).unwrap_or(0) as i64),
+    );
+    let user_indices: Vec<i64> = metadata
+        .iter()
+        .map(|metadata| {
+            let user = metadata.and_then(|metadata| metadata.user.as_deref());
+            user.map_or(0, |user| string_table.index(user)) as i64
+        })
+        .collect();
+    dense_info.packed_deltas(5, user_indices);
+    dense_info
+}
+
+fn way_group(osm_data: &OSMData, ways: &[&Way], string_table: &mut StringTable) -> Message {
+    let mut group = Message::default();
+
+    for way in ways.iter() {
+        let mut message = Message::default();
+        message.varint(1, way.id);
+        write_tags(&mut message, &way.tags, string_table);
+        if let Some(metadata) = osm_data.way_metadata(way.id) {
+            message.message(4, &info(metadata, string_table));
+        }
+        message.packed_deltas(8, way.node_ids.iter().map(|node_id| *node_id as i64));
+        group.message(3, &message);
+    }
+
+    group
+}
+
+fn relation_group(
+    osm_data: &OSMData,
+    relations: &[Relation],
+    string_table: &mut StringTable,
+) -> Message {
+    let mut group = Message::default();
+
+    for relation in relations.iter() {
+        let mut message = Message::default();
+        message.varint(1, relation.id);
+        write_tags(&mut message, &relation.tags, string_table);
+        if let Some(metadata) = osm_data.relation_metadata(relation.id) {
+            message.message(4, &info(metadata, string_table));
+        }
+
+        let roles: Vec<u64> = relation
+            .members
+            .iter()
+            .map(|member| string_table.index(&member.role) as u64)
+            .collect();
+        message.packed_varints(8, roles);
+        message.packed_deltas(
+            9,
+            relation
+                .members
+                .iter()
+                .map(|member| member.member_id as i64),
+        );
+        message.packed_varints(
+            10,
+            relation
+                .members
+                .iter()
+                .map(|member| match member.member_type {
+                    MemberType::Node => 0,
+                    MemberType::Way => 1,
+                    MemberType::Relation => 2,
+                }),
+        );
+        group.message(4, &message);
+    }
+
+    group
+}
+
+fn write_tags(message: &mut Message, tags: &Tags, string_table: &mut StringTable) {
+    let (keys, values): (Vec<u64>, Vec<u64>) = tags
+        .iter()
+        .map(|(key, value)| {
+            (
+                string_table.index(key) as u64,
+                string_table.index(value) as u64,
+            )
+        })
+        .unzip();
+    message.packed_varints(2, keys);
+    message.packed_varints(3, values);
+}
+
+fn info(metadata: &ElementMetadata, string_table: &mut StringTable) -> Message {
+    let mut info = Message::default();
+    if let Some(version) = metadata.version {
+        info.varint(1, version as u64);
+    }
+    if let Some(timestamp) = metadata.timestamp {
+        info.varint(2, timestamp.timestamp() as u64);
+    }
+    if let Some(changeset) = metadata.changeset {
+        info.varint(3, changeset);
+    }
+    if let Some(uid) = metadata.uid {
+        info.varint(4, uid);
+    }
+    if let Some(user) = &metadata.user {
+        info.varint(5, string_table.index(user) as u64);
+    }
+    info
+}
+
+fn nanodegrees(degrees: f64) -> i64 {
+    (degrees * 1e9).round() as i64
+}
+
+fn granular(degrees: f64) -> i64 {
+    (degrees * 1e9 / GRANULARITY as f64).round() as i64
+}
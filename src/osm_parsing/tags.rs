@@ -0,0 +1,141 @@
+use std::fmt;
+
+use serde::de::{MapAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::osm_parsing::Tags;
+use crate::utils::string_table::{
+    decode_symbol, encode_symbol, find_value, is_reading_symbols, is_writing_symbols, Symbol,
+};
+
+impl Tags {
+    pub fn new() -> Self {
+        Tags::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        find_value(&self.pairs, key)
+    }
+
+    /// Like `get`, for a key that was interned up front.
+    pub fn get_symbol(&self, key: Symbol) -> Option<Symbol> {
+        self.pairs
+            .iter()
+            .find(|(pair_key, _)| *pair_key == key)
+            .map(|(_, value)| *value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        Symbol::lookup(key).is_some_and(|key| self.get_symbol(key).is_some())
+    }
+
+    /// Sets the value of a key, replacing the previous one.
+    pub fn insert(&mut self, key: &str, value: &str) {
+        let (key, value) = (Symbol::intern(key), Symbol::intern(value));
+
+        match self.pairs.iter_mut().find(|(pair_key, _)| *pair_key == key) {
+            Some(pair) => pair.1 = value,
+            None => self.pairs.push((key, value)),
+        }
+    }
+
+    /// Removes a key, returning whether it was present.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let Some(key) = Symbol::lookup(key) else {
+            return false;
+        };
+
+        let length = self.pairs.len();
+        self.pairs.retain(|(pair_key, _)| *pair_key != key);
+        self.pairs.len() < length
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+        self.pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (Symbol, Symbol)> + '_ {
+        self.pairs.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for Tags {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tags = Tags::new();
+        for (key, value) in iter {
+            tags.insert(key.as_ref(), value.as_ref());
+        }
+        tags
+    }
+}
+
+impl fmt::Debug for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Written as a map of strings, or as pairs of symbol IDs inside `write_symbols`, which the map cache
+/// uses to store every string only once.
+impl Serialize for Tags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() && is_writing_symbols() {
+            let mut sequence = serializer.serialize_seq(Some(self.pairs.len()))?;
+            for (key, value) in self.pairs.iter() {
+                sequence.serialize_element(&(encode_symbol(*key), encode_symbol(*value)))?;
+            }
+            return sequence.end();
+        }
+
+        let mut map = serializer.serialize_map(Some(self.pairs.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Tags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() && is_reading_symbols() {
+            let ids = Vec::<(u32, u32)>::deserialize(deserializer)?;
+            let pairs = ids
+                .into_iter()
+                .map(|(key, value)| Some((decode_symbol(key)?, decode_symbol(value)?)))
+                .collect::<Option<_>>()
+                .ok_or_else(|| serde::de::Error::custom("tag refers to an unknown string"))?;
+            return Ok(Tags { pairs });
+        }
+
+        deserializer.deserialize_map(TagsVisitor)
+    }
+}
+
+struct TagsVisitor;
+
+impl<'de> Visitor<'de> for TagsVisitor {
+    type Value = Tags;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of tag keys to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Tags, A::Error> {
+        let mut tags = Tags::new();
+        while let Some((key, value)) = map.next_entry::<String, String>()? {
+            tags.insert(&key, &value);
+        }
+        Ok(tags)
+    }
+}
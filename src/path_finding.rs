@@ -1,6 +1,23 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use geo::Point;
+use serde::{Deserialize, Serialize};
+
+pub mod connected_components;
+pub mod cycling_costs;
+pub mod edge_costs;
+pub mod instructions;
 pub mod nearest_road;
 pub mod path_finding;
 pub mod queue_handling;
+pub mod routing_graph;
+pub mod search_limits;
+pub mod search_trace;
+pub mod speed_profiles;
 
 /// This implements items for the priority queue in the form of a BinaryHeap.
 /// distance_to_start is a u64: we multiply the float distances (in meters) by 1e6 and convert them to u64.
@@ -14,14 +31,26 @@ pub struct QueueItem {
 }
 
 /// For nearest road node: end_node is always the road.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct PathResult {
     pub start_node: u64,
     pub end_node: u64,
 
+    /// Node IDs from the start node up to and including the end node.
     pub found_path: Vec<u64>,
     pub path_length: f64,
     pub path_time: f64,
+
+    /// Meters climbed and descended along the path, only counted where node elevations are known.
+    pub total_ascent: f64,
+    pub total_descent: f64,
+
+    /// When the path is started and finished. A path searched without a departure time starts at the Unix
+    /// epoch, until it is scheduled as part of a route.
+    #[serde(default)]
+    pub departure: DateTime<Utc>,
+    #[serde(default)]
+    pub arrival: DateTime<Utc>,
 }
 
 #[derive(Debug)]
@@ -31,5 +60,176 @@ pub enum TransportMode {
     Walk(f64),
 }
 
+/// Per-request settings for a path search. The defaults reproduce the plain speed-based search, except
+/// that bikes avoid ways that cannot be cycled.
+#[derive(Debug, Clone, Default)]
+pub struct PathOptions {
+    pub cycling_preferences: CyclingPreferences,
+    /// Adjust walking and cycling speeds to the slope, using node elevations from an elevation model.
+    pub use_elevation: bool,
+    /// Departure time for a time-dependent car search using the map's speed profiles.
+    pub departure_time: Option<DateTime<Utc>>,
+    pub limits: SearchLimits,
+}
+
+/// Bounds on a single search, so that unreachable targets fail fast instead of exploring a whole country.
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    /// Maximum number of nodes taken from the queue.
+    pub max_settled_nodes: Option<usize>,
+    /// Maximum search cost in seconds; equal to the travel time unless cycling preferences apply.
+    pub max_cost: Option<f64>,
+    pub deadline: Option<Instant>,
+    pub cancellation_token: Option<CancellationToken>,
+}
+
+/// Shared flag to abort a running search from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathFindingError {
+    /// The whole reachable network was explored without finding the target.
+    NoPath,
+    SettledNodeLimit(usize),
+    CostLimit(f64),
+    Timeout,
+    Cancelled,
+    InvalidOptions(String),
+}
+
+/// Weights used when cycling: surface quality changes the effective speed (and thus the path time),
+/// the infrastructure factors only change how attractive a way is to the search.
+/// A factor below 1.0 makes a way more attractive, above 1.0 less so.
+#[derive(Debug, Clone)]
+pub struct CyclingPreferences {
+    /// Exponent applied to the surface/smoothness speed factor: 0.0 ignores surface quality entirely.
+    pub surface_sensitivity: f64,
+    /// Separated cycle paths: `highway=cycleway`, `cycleway=track` and `bicycle_road=yes` (fietsstraten).
+    pub cycleway_factor: f64,
+    /// Cycle lanes painted on the carriageway: `cycleway=lane` and similar.
+    pub cycle_lane_factor: f64,
+    /// Trunk, primary and secondary roads without any cycling infrastructure.
+    pub busy_road_factor: f64,
+}
+
+/// Car speeds that vary over the week (e.g. rush hour), per highway class or per way.
+/// A way profile takes precedence over the profile of its highway class.
+#[derive(Debug, Default)]
+pub struct SpeedProfiles {
+    /// Offset of the local time the profiles are written in, e.g. 3600 for CET.
+    pub utc_offset_seconds: i32,
+    pub highway_profiles: HashMap<String, WeeklySpeedProfile>,
+    pub way_profiles: HashMap<u64, WeeklySpeedProfile>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct WeeklySpeedProfile {
+    pub intervals: Vec<SpeedInterval>,
+}
+
+/// A speed (m/s) between two moments of the week, in seconds since Monday 00:00 local time.
+#[derive(Debug, Clone, Copy)]
+pub struct SpeedInterval {
+    pub start: f64,
+    pub end: f64,
+    pub speed: f64,
+}
+
+/// Assignment of road nodes to connected components, numbered from 0.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectedComponents {
+    pub component_of: HashMap<u64, usize>,
+    pub component_sizes: Vec<usize>,
+}
+
+/// Hook into a running search, called every time a node is taken from the queue.
+pub trait SearchObserver {
+    fn node_settled(&mut self, settled_node: SettledNode);
+}
+
+/// A node taken from the queue: `cost` is the search cost (seconds) from the start, `order` counts from 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SettledNode {
+    pub node_id: u64,
+    pub parent_node_id: u64,
+    pub cost: f64,
+    pub order: usize,
+}
+
+/// Records the exploration of a search, to be written to CSV or GeoJSON for inspection.
+#[derive(Debug, Clone, Default)]
+pub struct SearchTrace {
+    pub settled_nodes: Vec<SettledNode>,
+}
+
+/// A single step of turn-by-turn navigation along a path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub maneuver: Maneuver,
+    /// Node at which the maneuver takes place.
+    pub node_id: u64,
+    /// Name (or reference, e.g. "N261") of the way followed after the maneuver.
+    pub street_name: Option<String>,
+    /// Meters travelled after the maneuver, until the next instruction.
+    pub distance: f64,
+    /// Direction of travel right after the maneuver, in degrees clockwise from north (0-360).
+    pub bearing: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Maneuver {
+    Depart,
+    Continue,
+    SlightLeft,
+    Left,
+    SharpLeft,
+    SlightRight,
+    Right,
+    SharpRight,
+    UTurn,
+    /// Take the given exit (counting from 1) on a roundabout.
+    Roundabout(usize),
+    Arrive,
+}
+
+/// Travel time (seconds) and search cost of a single edge between two adjacent nodes.
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeCost {
+    pub time: f64,
+    pub cost: f64,
+}
+
+/// Read access to a routable map. Implemented by the in-memory `OSMData` and by the memory-mapped
+/// `MappedMap`, so that the path search runs on either.
+pub trait RoutingGraph {
+    type Way<'a>: WayTags
+    where
+        Self: 'a;
+
+    fn contains_node(&self, node_id: u64) -> bool;
+    fn coordinate(&self, node_id: u64) -> Option<Point>;
+    fn elevation(&self, node_id: u64) -> Option<f64>;
+    fn neighbours(&self, node_id: u64) -> impl Iterator<Item = u64> + '_;
+    /// The way that directly connects two adjacent nodes, if any.
+    fn connecting_way(&self, from_node_id: u64, to_node_id: u64) -> Option<Self::Way<'_>>;
+    /// Maximum speed (m/s) of the first way through this node that has a readable maxspeed tag.
+    fn node_max_speed(&self, node_id: u64) -> Option<f64>;
+    fn speed_profiles(&self) -> Option<&SpeedProfiles>;
+}
+
+/// Tag access for a way, whether it is an owned `Way` or a view into a mapped file.
+pub trait WayTags {
+    fn way_id(&self) -> u64;
+    fn tag(&self, key: &str) -> Option<&str>;
+}
+
 const ROAD_DEFAULT_SPEED: f64 = 60. / 3.6;
-const ROAD_MAXIMUM_SPEED: f64 = 120. / 3.6;
+
+/// Tobler's hiking function peaks at a slight downhill slope of 5%, about 19% above the flat walking speed.
+const WALKING_MAXIMUM_SLOPE_FACTOR: f64 = 1.191;
+/// Cycling downhill is capped at 1.5 times the flat speed.
+const CYCLING_MAXIMUM_SLOPE_FACTOR: f64 = 1.5;
+const ROAD_MAXIMUM_SPEED: f64 = 130. / 3.6;
@@ -0,0 +1,326 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::OSMData;
+use crate::osm_parsing::Way;
+
+use super::edge_costs::edge_cost;
+use super::{ConnectedComponents, PathOptions, TransportMode};
+
+impl ConnectedComponents {
+    fn from_labels(component_of: HashMap<u64, usize>, component_count: usize) -> Self {
+        let mut component_sizes = vec![0; component_count];
+        for component in component_of.values() {
+            component_sizes[*component] += 1;
+        }
+
+        ConnectedComponents {
+            component_of,
+            component_sizes,
+        }
+    }
+
+    pub fn component(&self, node_id: u64) -> Option<usize> {
+        self.component_of.get(&node_id).copied()
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.component_sizes.len()
+    }
+
+    pub fn largest_component(&self) -> Option<usize> {
+        (0..self.component_sizes.len()).max_by_key(|component| self.component_sizes[*component])
+    }
+
+    /// Looks the largest component up on every call; loops over many nodes should call
+    /// `largest_component` once and compare against it.
+    pub fn is_in_largest_component(&self, node_id: u64) -> bool {
+        self.component(node_id).is_some() && self.component(node_id) == self.largest_component()
+    }
+}
+
+impl OSMData {
+    /// Weakly connected components of the road graph, ignoring transport modes and one-way restrictions
+    /// like the path search does. Cached until the road graph is rebuilt.
+    pub fn road_components(&self) -> &ConnectedComponents {
+        self.road_components.get_or_init(|| {
+            weakly_connected_components(self, |node_id| self.road_neighbours(node_id).collect())
+        })
+    }
+
+    /// Weakly connected components over the ways a transport mode may use.
+    pub fn weakly_connected_components(
+        &self,
+        transport_mode: &TransportMode,
+    ) -> ConnectedComponents {
+        weakly_connected_components(self, |node_id| {
+            self.road_neighbours(node_id)
+                .filter(|neighbour_id| {
+                    self.mode_allows_edge(node_id, *neighbour_id, transport_mode, false)
+                        || self.mode_allows_edge(*neighbour_id, node_id, transport_mode, false)
+                })
+                .collect()
+        })
+    }
+
+    /// Strongly connected components over the ways a transport mode may use, honouring one-way streets
+    /// for cars and bikes. The path search does not restrict one-way streets, so it can still find paths
+    /// between these components: use them to find one-way traps in the data, not to predict whether a
+    /// search will succeed.
+    pub fn strongly_connected_components(
+        &self,
+        transport_mode: &TransportMode,
+    ) -> ConnectedComponents {
+        strongly_connected_components(self, |node_id| {
+            self.road_neighbours(node_id)
+                .filter(|neighbour_id| {
+                    self.mode_allows_edge(node_id, *neighbour_id, transport_mode, true)
+                })
+                .collect()
+        })
+    }
+
+    /// Removes road nodes in components with fewer than `minimum_size` nodes, together with their ways.
+    /// Returns the number of removed nodes.
+    pub fn prune_small_components(&mut self, minimum_size: usize) -> usize {
+        self.build_settings.minimum_component_size = Some(minimum_size);
+
+        let road_components = self.road_components();
+
+        let nodes_to_remove: HashSet<u64> = road_components
+            .component_of
+            .iter()
+            .filter(|(_, component)| road_components.component_sizes[**component] < minimum_size)
+            .map(|(node_id, _)| *node_id)
+            .collect();
+
+        if nodes_to_remove.is_empty() {
+            return 0;
+        }
+
+        self.node_map
+            .retain(|node_id, _| !nodes_to_remove.contains(node_id));
+        self.way_map.retain(|_, way| {
+            !way.node_ids
+                .iter()
+                .all(|node_id| nodes_to_remove.contains(node_id))
+        });
+
+        for subset in self.node_subsets.iter_mut() {
+            subset
+                .node_subset
+                .retain(|node_id| !nodes_to_remove.contains(node_id));
+        }
+
+        self.prune_metadata();
+        self.update_road_nodes();
+
+        info!(
+            "Removed {} road nodes in components smaller than {} nodes",
+            nodes_to_remove.len(),
+            minimum_size
+        );
+
+        nodes_to_remove.len()
+    }
+
+    fn road_neighbours(&self, node_id: u64) -> impl Iterator<Item = u64> + '_ {
+        self.node_map
+            .get(&node_id)
+            .into_iter()
+            .flat_map(|node| node.nodes.iter().copied())
+            .filter(|neighbour_id| self.node_map.contains_key(neighbour_id))
+    }
+
+    fn road_node_ids(&self) -> Vec<u64> {
+        self.node_map
+            .values()
+            .filter(|node| !node.ways.is_empty())
+            .map(|node| node.id)
+            .collect()
+    }
+
+    fn mode_allows_edge(
+        &self,
+        from_node_id: u64,
+        to_node_id: u64,
+        transport_mode: &TransportMode,
+        respect_oneway: bool,
+    ) -> bool {
+        let is_usable = edge_cost(
+            self,
+            from_node_id,
+            to_node_id,
+            1.0,
+            transport_mode,
+            &PathOptions::default(),
+            None,
+        )
+        .is_some();
+
+        if !is_usable || !respect_oneway {
+            return is_usable;
+        }
+
+        match self.connecting_way(from_node_id, to_node_id) {
+            Some(way) => !is_oneway_against(way, from_node_id, to_node_id, transport_mode),
+            None => true,
+        }
+    }
+}
+
+/// Whether travelling from one node to the next along the way goes against its one-way direction.
+fn is_oneway_against(
+    way: &Way,
+    from_node_id: u64,
+    to_node_id: u64,
+    transport_mode: &TransportMode,
+) -> bool {
+    let tag = |key: &str| way.tags.get(key);
+
+    if let TransportMode::Walk(_) = transport_mode {
+        return false;
+    }
+
+    if let TransportMode::Bike(_) = transport_mode {
+        let cycling_both_ways = tag("oneway:bicycle") == Some("no")
+            || matches!(
+                tag("cycleway"),
+                Some("opposite") | Some("opposite_lane") | Some("opposite_track")
+            );
+        if cycling_both_ways {
+            return false;
+        }
+    }
+
+    let direction = match tag("oneway") {
+        Some("yes") | Some("true") | Some("1") => 1,
+        Some("-1") | Some("reverse") => -1,
+        Some("no") => 0,
+        _ => {
+            let implied_oneway = matches!(tag("junction"), Some("roundabout") | Some("circular"))
+                || matches!(tag("highway"), Some("motorway") | Some("motorway_link"));
+            i32::from(implied_oneway)
+        }
+    };
+
+    if direction == 0 {
+        return false;
+    }
+
+    let is_forward = way
+        .node_ids
+        .windows(2)
+        .any(|node_ids| node_ids[0] == from_node_id && node_ids[1] == to_node_id);
+
+    (direction == 1) != is_forward
+}
+
+fn weakly_connected_components<F>(osm_data: &OSMData, neighbours: F) -> ConnectedComponents
+where
+    F: Fn(u64) -> Vec<u64>,
+{
+    let mut component_of: HashMap<u64, usize> = HashMap::new();
+    let mut component_count = 0;
+
+    for node_id in osm_data.road_node_ids() {
+        if component_of.contains_key(&node_id) {
+            continue;
+        }
+
+        let mut queue = VecDeque::from([node_id]);
+        component_of.insert(node_id, component_count);
+
+        while let Some(current_node_id) = queue.pop_front() {
+            for neighbour_id in neighbours(current_node_id) {
+                if let std::collections::hash_map::Entry::Vacant(entry) =
+                    component_of.entry(neighbour_id)
+                {
+                    entry.insert(component_count);
+                    queue.push_back(neighbour_id);
+                }
+            }
+        }
+
+        component_count += 1;
+    }
+
+    ConnectedComponents::from_labels(component_of, component_count)
+}
+
+/// Tarjan's algorithm, with an explicit stack so that country-sized graphs do not overflow the call stack.
+fn strongly_connected_components<F>(osm_data: &OSMData, neighbours: F) -> ConnectedComponents
+where
+    F: Fn(u64) -> Vec<u64>,
+{
+    let mut index_of: HashMap<u64, usize> = HashMap::new();
+    let mut lowlink: HashMap<u64, usize> = HashMap::new();
+    let mut on_stack: HashSet<u64> = HashSet::new();
+    let mut node_stack: Vec<u64> = Vec::new();
+
+    let mut component_of: HashMap<u64, usize> = HashMap::new();
+    let mut component_count = 0;
+    let mut next_index = 0;
+
+    for root_node_id in osm_data.road_node_ids() {
+        if index_of.contains_key(&root_node_id) {
+            continue;
+        }
+
+        index_of.insert(root_node_id, next_index);
+        lowlink.insert(root_node_id, next_index);
+        next_index += 1;
+        node_stack.push(root_node_id);
+        on_stack.insert(root_node_id);
+
+        // Each frame holds a node, its successors and the next successor to visit.
+        let mut call_stack: Vec<(u64, Vec<u64>, usize)> =
+            vec![(root_node_id, neighbours(root_node_id), 0)];
+
+        while let Some((node_id, successors, next_successor)) = call_stack.last_mut() {
+            let node_id = *node_id;
+
+            if *next_successor < successors.len() {
+                let successor_id = successors[*next_successor];
+                *next_successor += 1;
+
+                if let Some(successor_index) = index_of.get(&successor_id) {
+                    if on_stack.contains(&successor_id) {
+                        let node_lowlink = lowlink[&node_id].min(*successor_index);
+                        lowlink.insert(node_id, node_lowlink);
+                    }
+                } else {
+                    index_of.insert(successor_id, next_index);
+                    lowlink.insert(successor_id, next_index);
+                    next_index += 1;
+                    node_stack.push(successor_id);
+                    on_stack.insert(successor_id);
+                    call_stack.push((successor_id, neighbours(successor_id), 0));
+                }
+                continue;
+            }
+
+            call_stack.pop();
+
+            if let Some((parent_id, _, _)) = call_stack.last() {
+                let parent_lowlink = lowlink[parent_id].min(lowlink[&node_id]);
+                lowlink.insert(*parent_id, parent_lowlink);
+            }
+
+            if lowlink[&node_id] == index_of[&node_id] {
+                while let Some(member_id) = node_stack.pop() {
+                    on_stack.remove(&member_id);
+                    component_of.insert(member_id, component_count);
+                    if member_id == node_id {
+                        break;
+                    }
+                }
+                component_count += 1;
+            }
+        }
+    }
+
+    ConnectedComponents::from_labels(component_of, component_count)
+}
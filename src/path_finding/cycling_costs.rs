@@ -0,0 +1,191 @@
+use super::{CyclingPreferences, PathFindingError, WayTags};
+
+/// Speed factors never drop below this: even a horrible track is faster than pushing the bike through a field.
+const MINIMUM_SURFACE_FACTOR: f64 = 0.1;
+
+const CYCLEWAY_KEYS: [&str; 4] = [
+    "cycleway",
+    "cycleway:both",
+    "cycleway:left",
+    "cycleway:right",
+];
+
+/// Neutral preferences: only ways that cannot be cycled at all are avoided.
+impl Default for CyclingPreferences {
+    fn default() -> Self {
+        CyclingPreferences {
+            surface_sensitivity: 0.0,
+            cycleway_factor: 1.0,
+            cycle_lane_factor: 1.0,
+            busy_road_factor: 1.0,
+        }
+    }
+}
+
+impl CyclingPreferences {
+    /// Slows down on rough surfaces, favours cycle tracks and lanes and avoids busy roads.
+    pub fn comfortable() -> Self {
+        CyclingPreferences {
+            surface_sensitivity: 1.0,
+            cycleway_factor: 0.8,
+            cycle_lane_factor: 0.9,
+            busy_road_factor: 1.5,
+        }
+    }
+
+    /// Factors must be positive and the surface sensitivity non-negative, otherwise an edge could cost
+    /// less than the A* heuristic assumes.
+    pub fn validate(&self) -> Result<(), PathFindingError> {
+        let factors = [
+            ("cycleway_factor", self.cycleway_factor),
+            ("cycle_lane_factor", self.cycle_lane_factor),
+            ("busy_road_factor", self.busy_road_factor),
+        ];
+        for (name, factor) in factors {
+            if !(factor.is_finite() && factor > 0.0) {
+                return Err(PathFindingError::InvalidOptions(format!(
+                    "{} must be positive, got {}",
+                    name, factor
+                )));
+            }
+        }
+
+        if !(self.surface_sensitivity.is_finite() && self.surface_sensitivity >= 0.0) {
+            return Err(PathFindingError::InvalidOptions(format!(
+                "surface_sensitivity must not be negative, got {}",
+                self.surface_sensitivity
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Fraction of the nominal cycling speed that can be reached on this way, or None if it cannot be cycled.
+    pub fn speed_factor(&self, way: &impl WayTags) -> Option<f64> {
+        if !is_cyclable(way) {
+            return None;
+        }
+
+        let tag = |key: &str| way.tag(key);
+
+        let surface_factor = match tag("surface") {
+            Some(surface) => surface_speed_factor(surface),
+            None => tag("tracktype").map_or(1.0, tracktype_speed_factor),
+        };
+        let smoothness_factor = match tag("smoothness") {
+            Some("impassable") => return None,
+            Some(smoothness) => smoothness_speed_factor(smoothness),
+            None => 1.0,
+        };
+
+        let factor = (surface_factor * smoothness_factor).max(MINIMUM_SURFACE_FACTOR);
+
+        Some(factor.powf(self.surface_sensitivity))
+    }
+
+    /// Preference multiplier on the travel time of this way. Only affects which path is chosen, not its duration.
+    pub fn preference_factor(&self, way: &impl WayTags) -> f64 {
+        let tag = |key: &str| way.tag(key);
+
+        let has_cycle_track = tag("highway") == Some("cycleway")
+            || tag("bicycle_road") == Some("yes")
+            || tag("cyclestreet") == Some("yes")
+            || CYCLEWAY_KEYS.iter().any(|key| tag(key) == Some("track"));
+
+        if has_cycle_track {
+            return self.cycleway_factor;
+        }
+
+        let has_cycle_lane = CYCLEWAY_KEYS.iter().any(|key| {
+            matches!(
+                tag(key),
+                Some("lane") | Some("shared_lane") | Some("opposite_lane")
+            )
+        });
+
+        if has_cycle_lane {
+            return self.cycle_lane_factor;
+        }
+
+        let is_busy_road = matches!(
+            tag("highway"),
+            Some("trunk")
+                | Some("trunk_link")
+                | Some("primary")
+                | Some("primary_link")
+                | Some("secondary")
+                | Some("secondary_link")
+        ) || tag("bicycle") == Some("use_sidepath");
+
+        if is_busy_road {
+            return self.busy_road_factor;
+        }
+
+        1.0
+    }
+
+    /// Lowest preference factor any way can get, used to keep the A* heuristic admissible.
+    pub fn minimum_preference_factor(&self) -> f64 {
+        self.cycleway_factor
+            .min(self.cycle_lane_factor)
+            .min(self.busy_road_factor)
+            .min(1.0)
+    }
+}
+
+fn is_cyclable(way: &impl WayTags) -> bool {
+    let tag = |key: &str| way.tag(key);
+
+    if matches!(tag("bicycle"), Some("no") | Some("private")) {
+        return false;
+    }
+
+    if matches!(
+        tag("bicycle"),
+        Some("yes") | Some("designated") | Some("permissive")
+    ) {
+        return true;
+    }
+
+    !matches!(
+        tag("highway"),
+        Some("motorway") | Some("motorway_link") | Some("footway") | Some("steps")
+    )
+}
+
+fn surface_speed_factor(surface: &str) -> f64 {
+    match surface {
+        "asphalt" | "paved" | "concrete" | "concrete:plates" | "chipseal" => 1.0,
+        "paving_stones" | "concrete:lanes" | "metal" | "wood" => 0.9,
+        "compacted" | "fine_gravel" => 0.85,
+        "sett" | "unhewn_cobblestone" | "cobblestone" | "pebblestone" => 0.7,
+        "unpaved" | "gravel" | "shells" => 0.7,
+        "ground" | "dirt" | "earth" | "woodchips" => 0.6,
+        "grass" | "grass_paver" => 0.5,
+        "sand" | "mud" => 0.35,
+        _ => 1.0,
+    }
+}
+
+fn smoothness_speed_factor(smoothness: &str) -> f64 {
+    match smoothness {
+        "excellent" | "good" => 1.0,
+        "intermediate" => 0.9,
+        "bad" => 0.75,
+        "very_bad" => 0.6,
+        "horrible" => 0.4,
+        "very_horrible" => 0.25,
+        _ => 1.0,
+    }
+}
+
+fn tracktype_speed_factor(tracktype: &str) -> f64 {
+    match tracktype {
+        "grade1" => 1.0,
+        "grade2" => 0.85,
+        "grade3" => 0.7,
+        "grade4" => 0.55,
+        "grade5" => 0.45,
+        _ => 1.0,
+    }
+}
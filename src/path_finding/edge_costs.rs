@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+
+use crate::data_handling::data_handling::parse_max_speed;
+
+use super::{
+    EdgeCost, PathOptions, RoutingGraph, TransportMode, WayTags, CYCLING_MAXIMUM_SLOPE_FACTOR,
+    ROAD_DEFAULT_SPEED, ROAD_MAXIMUM_SPEED, WALKING_MAXIMUM_SLOPE_FACTOR,
+};
+
+impl EdgeCost {
+    /// Plain edge: the search cost is the travel time itself.
+    pub fn from_time(time: f64) -> Self {
+        EdgeCost { time, cost: time }
+    }
+}
+
+/// Travel time and search cost from a parent to an adjacent child node, or None if the mode cannot use the edge.
+/// `entry_time` is when the parent is reached; it only matters for cars on a map with speed profiles.
+pub fn edge_cost<G: RoutingGraph>(
+    osm_data: &G,
+    parent_node_id: u64,
+    child_node_id: u64,
+    distance: f64,
+    transport_mode: &TransportMode,
+    options: &PathOptions,
+    entry_time: Option<DateTime<Utc>>,
+) -> Option<EdgeCost> {
+    let slope = if options.use_elevation {
+        edge_slope(osm_data, parent_node_id, child_node_id, distance)
+    } else {
+        0.0
+    };
+
+    match transport_mode {
+        TransportMode::Walk(walking_speed) => Some(EdgeCost::from_time(
+            distance / (walking_speed * walking_slope_factor(slope)),
+        )),
+        TransportMode::Bike(bike_speed) => {
+            let preferences = &options.cycling_preferences;
+
+            // Nodes without a connecting way (e.g. snapped landmarks) are treated as plain tarmac.
+            let (speed_factor, preference_factor) =
+                match osm_data.connecting_way(parent_node_id, child_node_id) {
+                    Some(way) => (
+                        preferences.speed_factor(&way)?,
+                        preferences.preference_factor(&way),
+                    ),
+                    None => (1.0, 1.0),
+                };
+
+            let time = distance / (bike_speed * speed_factor * cycling_slope_factor(slope));
+
+            Some(EdgeCost {
+                time,
+                cost: time * preference_factor,
+            })
+        }
+        TransportMode::Car => {
+            // The connecting way is only needed for its speed profile, so plain searches skip it.
+            let profiled_way = match (entry_time, osm_data.speed_profiles()) {
+                (Some(entry_time), Some(speed_profiles)) => osm_data
+                    .connecting_way(parent_node_id, child_node_id)
+                    .map(|way| (way, entry_time, speed_profiles)),
+                _ => None,
+            };
+
+            let base_speed = profiled_way
+                .as_ref()
+                .and_then(|(way, _, _)| way.tag("maxspeed"))
+                .and_then(parse_max_speed)
+                .or_else(|| osm_data.node_max_speed(child_node_id))
+                .unwrap_or(ROAD_DEFAULT_SPEED)
+                .min(ROAD_MAXIMUM_SPEED);
+
+            let time = profiled_way
+                .as_ref()
+                .and_then(|(way, entry_time, speed_profiles)| {
+                    Some(speed_profiles.way_profile(way)?.travel_time(
+                        distance,
+                        base_speed,
+                        speed_profiles.seconds_of_week(entry_time),
+                    ))
+                })
+                .unwrap_or(distance / base_speed);
+
+            Some(EdgeCost::from_time(time))
+        }
+    }
+}
+
+/// Speed used by the A* heuristic: the cost per meter can never be lower than 1 / heuristic_speed.
+pub fn heuristic_speed(transport_mode: &TransportMode, options: &PathOptions) -> f64 {
+    let (walking_slope_factor, cycling_slope_factor) = if options.use_elevation {
+        (WALKING_MAXIMUM_SLOPE_FACTOR, CYCLING_MAXIMUM_SLOPE_FACTOR)
+    } else {
+        (1.0, 1.0)
+    };
+
+    match transport_mode {
+        TransportMode::Walk(walking_speed) => walking_speed * walking_slope_factor,
+        TransportMode::Bike(bike_speed) => {
+            bike_speed * cycling_slope_factor
+                / options.cycling_preferences.minimum_preference_factor()
+        }
+        TransportMode::Car => ROAD_MAXIMUM_SPEED,
+    }
+}
+
+/// Rise over run between two nodes, or 0.0 if either elevation is unknown.
+fn edge_slope<G: RoutingGraph>(
+    osm_data: &G,
+    parent_node_id: u64,
+    child_node_id: u64,
+    distance: f64,
+) -> f64 {
+    match (
+        osm_data.elevation(parent_node_id),
+        osm_data.elevation(child_node_id),
+    ) {
+        (Some(parent_elevation), Some(child_elevation)) if distance > 0.0 => {
+            (child_elevation - parent_elevation) / distance
+        }
+        _ => 0.0,
+    }
+}
+
+/// Tobler's hiking function, relative to the speed on flat ground: 6 * exp(-3.5 * |slope + 0.05|) km/h.
+fn walking_slope_factor(slope: f64) -> f64 {
+    ((-3.5 * (slope + 0.05).abs()).exp() / (-3.5 * 0.05_f64).exp())
+        .min(WALKING_MAXIMUM_SLOPE_FACTOR)
+}
+
+/// Climbing 5% costs roughly 40% of the flat speed; descending speeds up until the cap.
+fn cycling_slope_factor(slope: f64) -> f64 {
+    if slope >= 0.0 {
+        1.0 / (1.0 + 12.0 * slope)
+    } else {
+        (1.0 - 5.0 * slope).min(CYCLING_MAXIMUM_SLOPE_FACTOR)
+    }
+}
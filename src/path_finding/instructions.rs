@@ -0,0 +1,311 @@
+use core::fmt;
+use std::collections::HashSet;
+
+use geo::{GeodesicBearing, GeodesicDistance};
+
+use crate::data_handling::OSMData;
+use crate::osm_parsing::Way;
+
+use super::{Instruction, Maneuver, PathResult};
+
+/// Bends smaller than this (in degrees) are never announced.
+const CONTINUE_ANGLE: f64 = 20.0;
+/// Turns on the same street are only announced at junctions, and only when sharper than this.
+const SAME_STREET_TURN_ANGLE: f64 = 45.0;
+
+/// A single edge of the path, with the information needed to build instructions.
+struct PathSegment {
+    from_node_id: u64,
+    to_node_id: u64,
+    street_name: Option<String>,
+    is_roundabout: bool,
+    distance: f64,
+    bearing: f64,
+}
+
+impl PathResult {
+    /// Collapses the node sequence into turn-by-turn instructions, from departure to arrival.
+    pub fn instructions(&self, osm_data: &OSMData) -> Vec<Instruction> {
+        let segments = path_segments(osm_data, &self.found_path);
+
+        let (Some(first_segment), Some(last_segment)) = (segments.first(), segments.last()) else {
+            return Vec::new();
+        };
+
+        let mut instructions = vec![Instruction {
+            maneuver: Maneuver::Depart,
+            node_id: first_segment.from_node_id,
+            street_name: first_segment.street_name.clone(),
+            distance: 0.0,
+            bearing: first_segment.bearing,
+        }];
+
+        let mut index = 0;
+        while index < segments.len() {
+            let segment = &segments[index];
+
+            if index > 0 {
+                let previous_segment = &segments[index - 1];
+
+                if segment.is_roundabout && !previous_segment.is_roundabout {
+                    index = roundabout_instruction(osm_data, &segments, index, &mut instructions);
+                    continue;
+                }
+
+                // Leaving a roundabout is already covered by its instruction.
+                let maneuver = if previous_segment.is_roundabout {
+                    None
+                } else {
+                    turn_maneuver(osm_data, previous_segment, segment)
+                };
+
+                if let Some(maneuver) = maneuver {
+                    instructions.push(Instruction {
+                        maneuver,
+                        node_id: segment.from_node_id,
+                        street_name: segment.street_name.clone(),
+                        distance: 0.0,
+                        bearing: segment.bearing,
+                    });
+                }
+            }
+
+            if let Some(instruction) = instructions.last_mut() {
+                instruction.distance += segment.distance;
+            }
+            index += 1;
+        }
+
+        instructions.push(Instruction {
+            maneuver: Maneuver::Arrive,
+            node_id: last_segment.to_node_id,
+            street_name: last_segment.street_name.clone(),
+            distance: 0.0,
+            bearing: last_segment.bearing,
+        });
+
+        instructions
+    }
+}
+
+/// Renders instructions as numbered lines of text.
+pub fn render_instructions(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| format!("{}. {}", index + 1, instruction))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl Maneuver {
+    /// Classifies a change of direction in degrees: positive is clockwise (to the right).
+    pub fn from_turn_angle(turn_angle: f64) -> Self {
+        let magnitude = turn_angle.abs();
+        let is_right = turn_angle > 0.0;
+
+        match magnitude {
+            magnitude if magnitude <= CONTINUE_ANGLE => Maneuver::Continue,
+            magnitude if magnitude <= 60.0 && is_right => Maneuver::SlightRight,
+            magnitude if magnitude <= 60.0 => Maneuver::SlightLeft,
+            magnitude if magnitude <= 135.0 && is_right => Maneuver::Right,
+            magnitude if magnitude <= 135.0 => Maneuver::Left,
+            magnitude if magnitude <= 170.0 && is_right => Maneuver::SharpRight,
+            magnitude if magnitude <= 170.0 => Maneuver::SharpLeft,
+            _ => Maneuver::UTurn,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let street_name = self.street_name.as_deref();
+
+        let action = match (self.maneuver, street_name) {
+            (Maneuver::Depart, Some(street_name)) => format!(
+                "Head {} on {}",
+                compass_direction(self.bearing),
+                street_name
+            ),
+            (Maneuver::Depart, None) => format!("Head {}", compass_direction(self.bearing)),
+            (Maneuver::Arrive, _) => return write!(f, "Arrive at your destination"),
+            (Maneuver::Roundabout(exit), Some(street_name)) => {
+                format!("At the roundabout, take exit {} onto {}", exit, street_name)
+            }
+            (Maneuver::Roundabout(exit), None) => {
+                format!("At the roundabout, take exit {}", exit)
+            }
+            (maneuver, Some(street_name)) => {
+                format!("{} onto {}", maneuver_text(maneuver), street_name)
+            }
+            (maneuver, None) => maneuver_text(maneuver).to_string(),
+        };
+
+        write!(f, "{} and continue for {:.0} m", action, self.distance)
+    }
+}
+
+fn maneuver_text(maneuver: Maneuver) -> &'static str {
+    match maneuver {
+        Maneuver::Depart => "Depart",
+        Maneuver::Continue => "Continue",
+        Maneuver::SlightLeft => "Keep slightly left",
+        Maneuver::Left => "Turn left",
+        Maneuver::SharpLeft => "Turn sharply left",
+        Maneuver::SlightRight => "Keep slightly right",
+        Maneuver::Right => "Turn right",
+        Maneuver::SharpRight => "Turn sharply right",
+        Maneuver::UTurn => "Make a U-turn",
+        Maneuver::Roundabout(_) => "Take the roundabout",
+        Maneuver::Arrive => "Arrive",
+    }
+}
+
+fn compass_direction(bearing: f64) -> &'static str {
+    const DIRECTIONS: [&str; 8] = [
+        "north",
+        "northeast",
+        "east",
+        "southeast",
+        "south",
+        "southwest",
+        "west",
+        "northwest",
+    ];
+    DIRECTIONS[((bearing / 45.0).round() as usize) % 8]
+}
+
+fn path_segments(osm_data: &OSMData, found_path: &[u64]) -> Vec<PathSegment> {
+    found_path
+        .windows(2)
+        .filter_map(|node_ids| {
+            let from_node = osm_data.node_map.get(&node_ids[0])?;
+            let to_node = osm_data.node_map.get(&node_ids[1])?;
+            let way = osm_data.connecting_way(node_ids[0], node_ids[1]);
+
+            Some(PathSegment {
+                from_node_id: node_ids[0],
+                to_node_id: node_ids[1],
+                street_name: way.and_then(street_name),
+                is_roundabout: way.is_some_and(is_roundabout),
+                distance: from_node.coordinate.geodesic_distance(&to_node.coordinate),
+                bearing: from_node
+                    .coordinate
+                    .geodesic_bearing(to_node.coordinate)
+                    .rem_euclid(360.0),
+            })
+        })
+        .collect()
+}
+
+fn street_name(way: &Way) -> Option<String> {
+    way.tags
+        .get("name")
+        .or_else(|| way.tags.get("ref"))
+        .map(|name| name.to_string())
+}
+
+fn is_roundabout(way: &Way) -> bool {
+    matches!(
+        way.tags.get("junction"),
+        Some("roundabout") | Some("circular")
+    )
+}
+
+/// Returns a maneuver if the transition between two segments is worth announcing.
+fn turn_maneuver(
+    osm_data: &OSMData,
+    previous_segment: &PathSegment,
+    segment: &PathSegment,
+) -> Option<Maneuver> {
+    let turn_angle = (segment.bearing - previous_segment.bearing + 540.0).rem_euclid(360.0) - 180.0;
+    let maneuver = Maneuver::from_turn_angle(turn_angle);
+
+    if segment.street_name != previous_segment.street_name {
+        return Some(maneuver);
+    }
+
+    let is_junction = unique_neighbours(osm_data, segment.from_node_id).len() > 2;
+
+    if is_junction && turn_angle.abs() > SAME_STREET_TURN_ANGLE {
+        Some(maneuver)
+    } else {
+        None
+    }
+}
+
+/// Adds a roundabout instruction for the roundabout entered at `entry_index`, returning the index of the first
+/// segment after the exit. The exit road's distance is added to the roundabout instruction.
+fn roundabout_instruction(
+    osm_data: &OSMData,
+    segments: &[PathSegment],
+    entry_index: usize,
+    instructions: &mut Vec<Instruction>,
+) -> usize {
+    let mut exit_count = 0;
+    let mut roundabout_distance = 0.0;
+    let mut index = entry_index;
+
+    while index < segments.len() && segments[index].is_roundabout {
+        roundabout_distance += segments[index].distance;
+
+        if has_roundabout_exit(osm_data, segments[index].to_node_id) {
+            exit_count += 1;
+        }
+        index += 1;
+    }
+
+    // The exit that is taken always counts, even if the map does not show another road leaving there.
+    let exit_segment = segments.get(index);
+    if exit_segment.is_some() && exit_count == 0 {
+        exit_count = 1;
+    }
+
+    let (street_name, bearing) = match exit_segment {
+        Some(exit_segment) => (exit_segment.street_name.clone(), exit_segment.bearing),
+        None => (
+            segments[index - 1].street_name.clone(),
+            segments[index - 1].bearing,
+        ),
+    };
+
+    instructions.push(Instruction {
+        maneuver: Maneuver::Roundabout(exit_count),
+        node_id: segments[entry_index].from_node_id,
+        street_name,
+        distance: roundabout_distance,
+        bearing,
+    });
+
+    index
+}
+
+/// A roundabout node is an exit if a road leaves the roundabout there that can be driven away from it.
+fn has_roundabout_exit(osm_data: &OSMData, node_id: u64) -> bool {
+    unique_neighbours(osm_data, node_id)
+        .into_iter()
+        .any(
+            |neighbour_id| match osm_data.connecting_way(node_id, neighbour_id) {
+                Some(way) => !is_roundabout(way) && !is_oneway_towards(way, neighbour_id, node_id),
+                None => false,
+            },
+        )
+}
+
+fn is_oneway_towards(way: &Way, from_node_id: u64, to_node_id: u64) -> bool {
+    if way.tags.get("oneway") != Some("yes") {
+        return false;
+    }
+
+    way.node_ids
+        .windows(2)
+        .any(|node_ids| node_ids[0] == from_node_id && node_ids[1] == to_node_id)
+}
+
+fn unique_neighbours(osm_data: &OSMData, node_id: u64) -> HashSet<u64> {
+    osm_data
+        .node_map
+        .get(&node_id)
+        .map(|node| node.nodes.iter().copied().collect())
+        .unwrap_or_default()
+}
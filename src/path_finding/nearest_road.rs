@@ -4,14 +4,34 @@ use crate::data_handling::{FilterSubset, OSMData};
 
 use super::{PathResult, TransportMode, ROAD_DEFAULT_SPEED};
 
+/// Closest road node in the main (largest) road component, so that snapped nodes are never stranded
+/// on small disconnected fragments.
 pub fn find_closest_road_coordinate(osm_data: &OSMData, coordinate: Point) -> (u64, f64) {
+    find_closest_road_coordinate_with(osm_data, coordinate, true)
+}
+
+pub fn find_closest_road_coordinate_with(
+    osm_data: &OSMData,
+    coordinate: Point,
+    main_component_only: bool,
+) -> (u64, f64) {
     let mut minimum_distance: f64 = f64::MAX;
     let mut closest_node_id: u64 = 0;
 
+    let road_components = osm_data.road_components();
+    let largest_component = road_components.largest_component();
+
     for subset in osm_data.node_subsets.iter() {
         match subset.filter_subset {
             FilterSubset::Roads => {
                 for node_id in subset.node_subset.iter() {
+                    if main_component_only
+                        && (largest_component.is_none()
+                            || road_components.component(*node_id) != largest_component)
+                    {
+                        continue;
+                    }
+
                     if let Some(node) = osm_data.node_map.get(node_id) {
                         let distance = coordinate.geodesic_distance(&node.coordinate);
                         if distance < minimum_distance {
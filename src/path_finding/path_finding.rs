@@ -5,19 +5,22 @@ use log::{info, warn};
 
 use crate::{
     data_handling::OSMData,
-    path_finding::{PathResult, QueueItem, ROAD_MAXIMUM_SPEED},
-    route_manager::{Route, RouteComponent},
+    path_finding::{
+        PathFindingError, PathOptions, PathResult, QueueItem, RoutingGraph, SearchObserver,
+        SettledNode,
+    },
+    route_manager::{Route, RouteComponent, RouteTime},
 };
-use std::{
-    collections::{BinaryHeap, HashMap},
-    fs::create_dir_all,
-};
-
-use crate::path_finding::ROAD_DEFAULT_SPEED;
+use std::collections::{BinaryHeap, HashMap};
 
-use crate::utils::distance_utilities::f64_to_u64;
+use crate::utils::distance_utilities::{f64_to_u64, u64_to_f64};
+use crate::utils::time_utilities::add_seconds;
 
-use super::{nearest_road::find_closest_road, TransportMode};
+use super::{
+    edge_costs::{edge_cost, heuristic_speed},
+    nearest_road::find_closest_road,
+    TransportMode,
+};
 
 impl PathResult {
     pub fn new(
@@ -33,89 +36,170 @@ impl PathResult {
             found_path,
             path_length,
             path_time,
+            total_ascent: 0.0,
+            total_descent: 0.0,
+            departure: DateTime::default(),
+            arrival: add_seconds(DateTime::default(), path_time),
+        }
+    }
+
+    pub fn depart_at(&mut self, departure: DateTime<Utc>) {
+        self.departure = departure;
+        self.arrival = add_seconds(departure, self.path_time);
+    }
+}
+
+impl TransportMode {
+    /// Name of the mode in exported routes.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransportMode::Car => "car",
+            TransportMode::Bike(_) => "bike",
+            TransportMode::Walk(_) => "walk",
         }
     }
 }
 
-pub fn process_found_path(
-    osm_data: &OSMData,
+/// Deadline and cancellation are checked once every this many settled nodes.
+const INTERRUPT_CHECK_INTERVAL: usize = 1024;
+
+/// Arrive-by car searches are repeated at most this many more times for the departure to settle.
+const ARRIVE_BY_SEARCHES: usize = 8;
+/// An arrive-by path may arrive this many seconds before or after the time asked for.
+const ARRIVE_BY_TOLERANCE: f64 = 1.0;
+
+pub fn process_found_path<G: RoutingGraph>(
+    osm_data: &G,
     start_node_id: u64,
     target_node_id: u64,
     parent_map: &HashMap<u64, u64>,
     transport_mode: &TransportMode,
+    options: &PathOptions,
 ) -> PathResult {
     let mut child_id = &target_node_id;
 
     let mut found_path: Vec<u64> = Vec::new();
     let mut path_length: f64 = 0.0;
     let mut path_time: f64 = 0.0;
-
-    // let mut found_path_file = File::create(Path::new("results/pathing/found_path.csv"))
-    //     .expect("Failed to open found path file");
-
-    // let headers = "lat,lon,node_id\n";
-    // found_path_file
-    //     .write(headers.as_bytes())
-    //     .expect("Failed to write headers");
+    let mut total_ascent: f64 = 0.0;
+    let mut total_descent: f64 = 0.0;
 
     while *child_id != start_node_id {
-        let parent_id = parent_map.get(&child_id).unwrap();
-        let child_node = osm_data.node_map.get(child_id).unwrap();
-        let parent_node = osm_data.node_map.get(&parent_id).unwrap();
-
-        let distance_parent_child = child_node
-            .coordinate
-            .geodesic_distance(&parent_node.coordinate);
-
-        let node_speed = match transport_mode {
-            TransportMode::Walk(walking_speed) => *walking_speed,
-            TransportMode::Bike(bike_speed) => *bike_speed,
-            TransportMode::Car => osm_data
-                .node_max_speed(*child_id)
-                .unwrap_or(ROAD_DEFAULT_SPEED),
-        };
-
-        path_length += distance_parent_child;
-        path_time += distance_parent_child / node_speed;
+        let parent_id = parent_map.get(child_id).unwrap();
 
         found_path.push(*child_id);
 
         child_id = parent_id;
+    }
+
+    // Times are accumulated from the start, so time-dependent speeds see the right moment for every edge.
+    let forward_path: Vec<u64> = std::iter::once(start_node_id)
+        .chain(found_path.iter().rev().copied())
+        .collect();
+
+    for node_ids in forward_path.windows(2) {
+        let parent_id = node_ids[0];
+        let child_id = node_ids[1];
+        let parent_coordinate = osm_data.coordinate(parent_id).unwrap();
+        let child_coordinate = osm_data.coordinate(child_id).unwrap();
 
-        // let coordinate_data = format!(
-        //     "{},{},{}\n",
-        //     child_node.coordinate.y(),
-        //     child_node.coordinate.x(),
-        //     child_id
-        // );
+        let distance_parent_child = child_coordinate.geodesic_distance(&parent_coordinate);
 
-        // found_path_file
-        //     .write(coordinate_data.as_bytes())
-        //     .expect("Failed to write to coordinates file");
+        let entry_time = options
+            .departure_time
+            .map(|departure_time| add_seconds(departure_time, path_time));
+
+        let parent_child_cost = edge_cost(
+            osm_data,
+            parent_id,
+            child_id,
+            distance_parent_child,
+            transport_mode,
+            options,
+            entry_time,
+        )
+        .expect("Found path contains an edge the transport mode cannot use");
+
+        path_length += distance_parent_child;
+        path_time += parent_child_cost.time;
+
+        if let (Some(parent_elevation), Some(child_elevation)) =
+            (osm_data.elevation(parent_id), osm_data.elevation(child_id))
+        {
+            let climb = child_elevation - parent_elevation;
+            if climb > 0.0 {
+                total_ascent += climb;
+            } else {
+                total_descent -= climb;
+            }
+        }
     }
 
-    PathResult::new(
-        found_path,
+    let mut path_result = PathResult::new(
+        forward_path,
         path_length,
         path_time,
         start_node_id,
         target_node_id,
-    )
+    );
+    path_result.total_ascent = total_ascent;
+    path_result.total_descent = total_descent;
+    if let Some(departure_time) = options.departure_time {
+        path_result.depart_at(departure_time);
+    }
+
+    path_result
 }
 
-pub fn path_finding(
-    osm_data: &OSMData,
+pub fn path_finding<G: RoutingGraph>(
+    osm_data: &G,
     start_node_id: u64,
     target_node_id: u64,
     transport_mode: &TransportMode,
 ) -> Option<PathResult> {
+    path_finding_with_options(
+        osm_data,
+        start_node_id,
+        target_node_id,
+        transport_mode,
+        &PathOptions::default(),
+    )
+    .ok()
+}
+
+pub fn path_finding_with_options<G: RoutingGraph>(
+    osm_data: &G,
+    start_node_id: u64,
+    target_node_id: u64,
+    transport_mode: &TransportMode,
+    options: &PathOptions,
+) -> Result<PathResult, PathFindingError> {
+    path_finding_with_observer(
+        osm_data,
+        start_node_id,
+        target_node_id,
+        transport_mode,
+        options,
+        &mut (),
+    )
+}
+
+/// Path search that reports every settled node to an observer, e.g. a `SearchTrace`.
+pub fn path_finding_with_observer<G: RoutingGraph>(
+    osm_data: &G,
+    start_node_id: u64,
+    target_node_id: u64,
+    transport_mode: &TransportMode,
+    options: &PathOptions,
+    observer: &mut dyn SearchObserver,
+) -> Result<PathResult, PathFindingError> {
     assert!(
-        osm_data.node_map.contains_key(&start_node_id),
+        osm_data.contains_node(start_node_id),
         "Starting node not in map: {}",
         start_node_id
     );
     assert!(
-        osm_data.node_map.contains_key(&target_node_id),
+        osm_data.contains_node(target_node_id),
         "Target node not in map: {}",
         target_node_id
     );
@@ -124,6 +208,7 @@ pub fn path_finding(
         "Start and target node should not be the same: {}",
         start_node_id
     );
+    options.cycling_preferences.validate()?;
 
     let mut node_priority_queue: BinaryHeap<QueueItem> = BinaryHeap::new();
     let mut time_from_start: HashMap<u64, u64> = HashMap::new();
@@ -131,14 +216,10 @@ pub fn path_finding(
     let mut insertion_counter: usize = 0;
 
     let heuristic_weight = 1.0;
+    let heuristic_speed = heuristic_speed(transport_mode, options);
 
     // Getting the target coordinates for A*.
-    let target_coordinate = osm_data
-        .node_map
-        .get(&target_node_id)
-        .unwrap()
-        .coordinate
-        .clone();
+    let target_coordinate = osm_data.coordinate(target_node_id).unwrap();
 
     let starting_node = QueueItem::new(start_node_id, insertion_counter, 0, 0);
 
@@ -147,75 +228,91 @@ pub fn path_finding(
     time_from_start.insert(start_node_id, 0);
 
     let mut has_succeeded = false;
+    let mut settled_nodes: usize = 0;
+    let limits = &options.limits;
 
-    create_dir_all("results/pathing").expect("Failed to create results/pathing directory.");
-    // let mut coordinates_file = File::create("results/pathing/search_coordinates.csv")
-    //     .expect("Failed to open coordinates file");
-
-    // let headers = "lat,lon,node_id\n";
-    // coordinates_file
-    //     .write(headers.as_bytes())
-    //     .expect("Failed to write headers");
+    limits.check_interrupted()?;
 
     // Taking from the front of the queue.
     while let Some(queue_item) = node_priority_queue.pop() {
         // Incrementing the node order counter. Starts at 0 for the first node, so has to be incremented here already.
         insertion_counter += 1;
 
-        if let Some(parent_node) = osm_data.node_map.get(&queue_item.node_id) {
-            // let coordinate_data = format!(
-            //     "{},{},{}\n",
-            //     parent_node.coordinate.y(),
-            //     parent_node.coordinate.x(),
-            //     parent_node.id
-            // );
+        // Skipping stale entries for nodes that have been reached at a lower cost since.
+        if queue_item.time_to_start > time_from_start[&queue_item.node_id] {
+            continue;
+        }
+
+        settled_nodes += 1;
+
+        if let Some(max_settled_nodes) = limits.max_settled_nodes {
+            if settled_nodes > max_settled_nodes {
+                return Err(PathFindingError::SettledNodeLimit(max_settled_nodes));
+            }
+        }
+
+        // The queue is ordered by cost plus an optimistic estimate, so nothing cheaper is left.
+        if let Some(max_cost) = limits.max_cost {
+            if u64_to_f64(queue_item.cost) > max_cost {
+                return Err(PathFindingError::CostLimit(max_cost));
+            }
+        }
 
-            // coordinates_file
-            //     .write(coordinate_data.as_bytes())
-            //     .expect("Failed to write to coordinates file");
+        if settled_nodes.is_multiple_of(INTERRUPT_CHECK_INTERVAL) {
+            limits.check_interrupted()?;
+        }
 
+        observer.node_settled(SettledNode {
+            node_id: queue_item.node_id,
+            parent_node_id: parent_map[&queue_item.node_id],
+            cost: u64_to_f64(queue_item.time_to_start),
+            order: settled_nodes - 1,
+        });
+
+        if let Some(parent_coordinate) = osm_data.coordinate(queue_item.node_id) {
             let time_start_to_parent = queue_item.time_to_start;
 
-            for child_node_id in parent_node.nodes.iter() {
-                if let Some(child_node) = osm_data.node_map.get(child_node_id) {
-                    let distance_parent_child = parent_node
-                        .coordinate
-                        .geodesic_distance(&child_node.coordinate);
-
-                    let node_speed = match transport_mode {
-                        TransportMode::Walk(walking_speed) => *walking_speed,
-                        TransportMode::Bike(bike_speed) => *bike_speed,
-                        TransportMode::Car => osm_data
-                            .node_max_speed(*child_node_id)
-                            .unwrap_or(ROAD_DEFAULT_SPEED),
+            // For cars the cost is the travel time, which makes this a time-dependent (FIFO) search.
+            let entry_time = options.departure_time.map(|departure_time| {
+                add_seconds(departure_time, u64_to_f64(time_start_to_parent))
+            });
+
+            for child_node_id in osm_data.neighbours(queue_item.node_id) {
+                if let Some(child_coordinate) = osm_data.coordinate(child_node_id) {
+                    let distance_parent_child =
+                        parent_coordinate.geodesic_distance(&child_coordinate);
+
+                    let Some(parent_to_child_cost) = edge_cost(
+                        osm_data,
+                        queue_item.node_id,
+                        child_node_id,
+                        distance_parent_child,
+                        transport_mode,
+                        options,
+                        entry_time,
+                    ) else {
+                        continue;
                     };
 
-                    let parent_to_child_time = distance_parent_child / node_speed;
-
+                    // This is a weighted time when cycling preferences apply; the real time is recomputed afterwards.
                     let time_start_to_child =
-                        time_start_to_parent + f64_to_u64(parent_to_child_time);
+                        time_start_to_parent + f64_to_u64(parent_to_child_cost.cost);
 
-                    if !parent_map.contains_key(child_node_id)
-                        || time_start_to_child < *time_from_start.get(child_node_id).unwrap()
+                    if !parent_map.contains_key(&child_node_id)
+                        || time_start_to_child < *time_from_start.get(&child_node_id).unwrap()
                     {
-                        parent_map.insert(*child_node_id, parent_node.id);
+                        parent_map.insert(child_node_id, queue_item.node_id);
 
-                        if *child_node_id == target_node_id {
+                        if child_node_id == target_node_id {
                             node_priority_queue.clear();
                             has_succeeded = true;
                             break;
                         }
 
-                        time_from_start.insert(*child_node_id, time_start_to_child);
-
-                        let heuristic_speed = match transport_mode {
-                            TransportMode::Walk(walking_speed) => walking_speed,
-                            TransportMode::Bike(bike_speed) => bike_speed,
-                            TransportMode::Car => &ROAD_MAXIMUM_SPEED,
-                        };
+                        time_from_start.insert(child_node_id, time_start_to_child);
 
                         let time_to_target = f64_to_u64(
-                            child_node.coordinate.geodesic_distance(&target_coordinate)
+                            child_coordinate.geodesic_distance(&target_coordinate)
                                 / heuristic_speed
                                 * heuristic_weight,
                         );
@@ -223,7 +320,7 @@ pub fn path_finding(
                         let overall_cost = time_start_to_child + time_to_target;
 
                         let child_node_queue_item = QueueItem::new(
-                            *child_node_id,
+                            child_node_id,
                             insertion_counter,
                             overall_cost,
                             time_start_to_child,
@@ -242,17 +339,22 @@ pub fn path_finding(
             target_node_id,
             &parent_map,
             transport_mode,
+            options,
         );
-        Some(found_result)
+        Ok(found_result)
     } else {
-        None
+        Err(PathFindingError::NoPath)
     }
 }
 
+/// The route by road alone: from the start to the nearest road, along the roads, and from the road to
+/// the target. Car speed profiles apply from when the road is entered. When arriving by a time, that is
+/// only known once the path is, so the search is repeated leaving as much before the arrival as the
+/// previous path took, until the departure settles.
 pub fn direct_route(
     osm_data: &OSMData,
     transport_mode: &TransportMode,
-    _time: DateTime<Utc>,
+    time: RouteTime,
     starting_node_id: u64,
     target_node_id: u64,
 ) -> Result<Route, Box<dyn std::error::Error>> {
@@ -263,12 +365,106 @@ pub fn direct_route(
     let start_road_node = start_to_start_road.end_node;
     let target_road_node = target_road_to_target.end_node;
 
-    // Regular path without public transport
-    let regular_path = path_finding(osm_data, start_road_node, target_road_node, &transport_mode)
-        .ok_or("Error: no path found.")?;
+    // Regular path without public transport, entering the road at the given time.
+    let road_path = |road_departure: DateTime<Utc>| {
+        let options = PathOptions {
+            departure_time: Some(road_departure),
+            ..PathOptions::default()
+        };
+        path_finding_with_options(
+            osm_data,
+            start_road_node,
+            target_road_node,
+            transport_mode,
+            &options,
+        )
+    };
+
+    let regular_path = match time {
+        RouteTime::DepartAt(departure) => {
+            road_path(add_seconds(departure, start_to_start_road.path_time))?
+        }
+        RouteTime::ArriveBy(arrival) => {
+            // The time the road has to be left by.
+            let road_arrival = add_seconds(arrival, -target_road_to_target.path_time);
+
+            // Without speed profiles the path doesn't depend on the departure.
+            if matches!(transport_mode, TransportMode::Car) && osm_data.speed_profiles.is_some() {
+                settle_arrive_by_path(road_arrival, road_path)?
+            } else {
+                road_path(road_arrival)?
+            }
+        }
+    };
 
-    let regular_path_component = RouteComponent::Path(regular_path);
-    let route = Route::new(vec![regular_path_component]);
+    let mut route = Route::new(vec![
+        RouteComponent::Path(start_to_start_road),
+        RouteComponent::Path(regular_path),
+        RouteComponent::Path(target_road_to_target),
+    ]);
+    route.schedule_for(time);
 
     Ok(route)
 }
+
+/// Searches a time-dependent path that arrives at the given time, to within a second. The first search
+/// enters the road at the arrival, the second leaves as long before it as the first took, and the
+/// later ones take a secant step through the last two, bisecting between the latest departure known to
+/// be in time and the earliest one known to be late when the step falls outside them. Returns the latest path that arrives in
+/// time, or the last one if none does within `ARRIVE_BY_SEARCHES` more searches.
+fn settle_arrive_by_path(
+    arrival: DateTime<Utc>,
+    road_path: impl Fn(DateTime<Utc>) -> Result<PathResult, PathFindingError>,
+) -> Result<PathResult, PathFindingError> {
+    // Departures and lateness are in seconds relative to the arrival.
+    let mut path = road_path(arrival)?;
+    let mut previous = (0.0, path.path_time);
+    let mut earliest_late_departure = 0.0;
+    let mut departure = -path.path_time;
+    let mut in_time: Option<(f64, PathResult)> = None;
+
+    for _ in 0..ARRIVE_BY_SEARCHES {
+        path = road_path(add_seconds(arrival, departure))?;
+        let lateness = departure + path.path_time;
+
+        if lateness <= ARRIVE_BY_TOLERANCE {
+            if lateness >= -ARRIVE_BY_TOLERANCE {
+                return Ok(path);
+            }
+            if in_time
+                .as_ref()
+                .is_none_or(|(in_time_departure, _)| departure > *in_time_departure)
+            {
+                in_time = Some((departure, path.clone()));
+            }
+        } else {
+            earliest_late_departure = departure.min(earliest_late_departure);
+        }
+
+        let (previous_departure, previous_lateness) = previous;
+        let mut next_departure = if lateness != previous_lateness {
+            departure - lateness * (departure - previous_departure) / (lateness - previous_lateness)
+        } else {
+            departure - lateness
+        };
+
+        let latest_in_time_departure = in_time
+            .as_ref()
+            .map_or(f64::NEG_INFINITY, |(in_time_departure, _)| {
+                *in_time_departure
+            });
+        if !(latest_in_time_departure < next_departure && next_departure < earliest_late_departure)
+        {
+            next_departure = if latest_in_time_departure.is_finite() {
+                (latest_in_time_departure + earliest_late_departure) / 2.0
+            } else {
+                departure - lateness.abs()
+            };
+        }
+
+        previous = (departure, lateness);
+        departure = next_departure;
+    }
+
+    Ok(in_time.map_or(path, |(_, path)| path))
+}
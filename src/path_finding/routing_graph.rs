@@ -0,0 +1,61 @@
+use geo::Point;
+
+use crate::data_handling::OSMData;
+use crate::osm_parsing::Way;
+
+use super::{RoutingGraph, SpeedProfiles, WayTags};
+
+impl WayTags for Way {
+    fn way_id(&self) -> u64 {
+        self.id
+    }
+
+    fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key)
+    }
+}
+
+impl<T: WayTags + ?Sized> WayTags for &T {
+    fn way_id(&self) -> u64 {
+        (**self).way_id()
+    }
+
+    fn tag(&self, key: &str) -> Option<&str> {
+        (**self).tag(key)
+    }
+}
+
+impl RoutingGraph for OSMData {
+    type Way<'a> = &'a Way;
+
+    fn contains_node(&self, node_id: u64) -> bool {
+        self.node_map.contains_key(&node_id)
+    }
+
+    fn coordinate(&self, node_id: u64) -> Option<Point> {
+        self.node_map.get(&node_id).map(|node| node.coordinate)
+    }
+
+    fn elevation(&self, node_id: u64) -> Option<f64> {
+        self.node_map.get(&node_id)?.elevation
+    }
+
+    fn neighbours(&self, node_id: u64) -> impl Iterator<Item = u64> + '_ {
+        self.node_map
+            .get(&node_id)
+            .into_iter()
+            .flat_map(|node| node.nodes.iter().copied())
+    }
+
+    fn connecting_way(&self, from_node_id: u64, to_node_id: u64) -> Option<&Way> {
+        OSMData::connecting_way(self, from_node_id, to_node_id)
+    }
+
+    fn node_max_speed(&self, node_id: u64) -> Option<f64> {
+        OSMData::node_max_speed(self, node_id)
+    }
+
+    fn speed_profiles(&self) -> Option<&SpeedProfiles> {
+        self.speed_profiles.as_ref()
+    }
+}
@@ -0,0 +1,58 @@
+use core::fmt;
+use std::error::Error;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use super::{CancellationToken, PathFindingError, SearchLimits};
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl SearchLimits {
+    /// Checks the limits that do not depend on the search state: the deadline and the cancellation token.
+    pub fn check_interrupted(&self) -> Result<(), PathFindingError> {
+        if let Some(cancellation_token) = &self.cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(PathFindingError::Cancelled);
+            }
+        }
+
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(PathFindingError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for PathFindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathFindingError::NoPath => write!(f, "No path found"),
+            PathFindingError::SettledNodeLimit(limit) => {
+                write!(f, "Search stopped after settling {} nodes", limit)
+            }
+            PathFindingError::CostLimit(limit) => {
+                write!(f, "No path found within a cost of {:.0}s", limit)
+            }
+            PathFindingError::Timeout => write!(f, "Search timed out"),
+            PathFindingError::Cancelled => write!(f, "Search was cancelled"),
+            PathFindingError::InvalidOptions(reason) => write!(f, "Invalid options: {}", reason),
+        }
+    }
+}
+
+impl Error for PathFindingError {}
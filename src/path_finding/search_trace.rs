@@ -0,0 +1,107 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use super::{RoutingGraph, SearchObserver, SearchTrace, SettledNode};
+
+/// Observing nothing: used when the caller does not ask for a trace.
+impl SearchObserver for () {
+    fn node_settled(&mut self, _settled_node: SettledNode) {}
+}
+
+impl SearchObserver for SearchTrace {
+    fn node_settled(&mut self, settled_node: SettledNode) {
+        self.settled_nodes.push(settled_node);
+    }
+}
+
+impl SearchTrace {
+    pub fn new() -> Self {
+        SearchTrace::default()
+    }
+
+    /// Writes one row per settled node, in settle order: `lat,lon,node_id,parent_node_id,cost,order`.
+    pub fn write_csv<G: RoutingGraph>(
+        &self,
+        graph: &G,
+        file_path: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+
+        writer.write_all(b"lat,lon,node_id,parent_node_id,cost,order\n")?;
+
+        for settled_node in self.settled_nodes.iter() {
+            if let Some(coordinate) = graph.coordinate(settled_node.node_id) {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{}",
+                    coordinate.y(),
+                    coordinate.x(),
+                    settled_node.node_id,
+                    settled_node.parent_node_id,
+                    settled_node.cost,
+                    settled_node.order
+                )?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes a GeoJSON FeatureCollection with the edge from its parent for every settled node.
+    /// The start node, which is its own parent, becomes a point.
+    pub fn write_geojson<G: RoutingGraph>(
+        &self,
+        graph: &G,
+        file_path: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        let features: Vec<Value> = self
+            .settled_nodes
+            .iter()
+            .filter_map(|settled_node| {
+                let coordinate = graph.coordinate(settled_node.node_id)?;
+                let parent_coordinate = graph.coordinate(settled_node.parent_node_id)?;
+
+                let geometry = if settled_node.node_id == settled_node.parent_node_id {
+                    json!({
+                        "type": "Point",
+                        "coordinates": [coordinate.x(), coordinate.y()],
+                    })
+                } else {
+                    json!({
+                        "type": "LineString",
+                        "coordinates": [
+                            [parent_coordinate.x(), parent_coordinate.y()],
+                            [coordinate.x(), coordinate.y()],
+                        ],
+                    })
+                };
+
+                Some(json!({
+                    "type": "Feature",
+                    "geometry": geometry,
+                    "properties": {
+                        "node_id": settled_node.node_id,
+                        "parent_node_id": settled_node.parent_node_id,
+                        "cost": settled_node.cost,
+                        "order": settled_node.order,
+                    },
+                }))
+            })
+            .collect();
+
+        let feature_collection = json!({
+            "type": "FeatureCollection",
+            "features": features,
+        });
+
+        let writer = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer(writer, &feature_collection)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+#[allow(unused)]
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::data_handling::OSMData;
+
+use super::{SpeedInterval, SpeedProfiles, WayTags, WeeklySpeedProfile, ROAD_MAXIMUM_SPEED};
+
+const SECONDS_PER_DAY: f64 = 24.0 * 3600.0;
+const SECONDS_PER_WEEK: f64 = 7.0 * SECONDS_PER_DAY;
+
+/// One row of a speed profile CSV. Either `highway` or `way_id` selects the roads, e.g.
+///
+/// ```text
+/// highway,way_id,days,start,end,speed
+/// motorway,,mon-fri,07:00,09:30,55
+/// ,4861234,all,16:00,18:30,20
+/// ```
+///
+/// `days` is `all`, `weekdays`, `weekend`, a day (`mon`) or a range (`mon-fri`); `speed` is in km/h.
+/// An interval whose end lies before its start runs past midnight.
+#[derive(Debug, Deserialize)]
+pub struct SpeedProfileEntry {
+    pub highway: Option<String>,
+    pub way_id: Option<u64>,
+    pub days: String,
+    pub start: String,
+    pub end: String,
+    pub speed: f64,
+}
+
+impl SpeedProfiles {
+    pub fn from_csv(file_path: &Path, utc_offset_seconds: i32) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(file_path)?;
+
+        let mut serde_reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .trim(csv::Trim::All)
+            .from_reader(file);
+
+        let mut speed_profiles = SpeedProfiles {
+            utc_offset_seconds,
+            ..Default::default()
+        };
+
+        for result in serde_reader.deserialize() {
+            let entry: SpeedProfileEntry = result?;
+            speed_profiles.add_entry(&entry)?;
+        }
+
+        info!(
+            "Loaded speed profiles for {} highway classes and {} ways",
+            speed_profiles.highway_profiles.len(),
+            speed_profiles.way_profiles.len()
+        );
+
+        Ok(speed_profiles)
+    }
+
+    pub fn add_entry(&mut self, entry: &SpeedProfileEntry) -> Result<(), Box<dyn Error>> {
+        let profile = match (entry.way_id, &entry.highway) {
+            (Some(way_id), _) => self.way_profiles.entry(way_id).or_default(),
+            (None, Some(highway)) => self.highway_profiles.entry(highway.clone()).or_default(),
+            (None, None) => return Err("Speed profile entry without highway or way_id".into()),
+        };
+
+        let start = parse_time_of_day(&entry.start)?;
+        let end = parse_time_of_day(&entry.end)?;
+        let speed = entry.speed / 3.6;
+
+        for day in parse_days(&entry.days)? {
+            let day_start = day as f64 * SECONDS_PER_DAY;
+
+            if end > start {
+                profile.add_interval(day_start + start, day_start + end, speed);
+            } else {
+                // Running past midnight: split into the evening and the next morning.
+                profile.add_interval(day_start + start, day_start + SECONDS_PER_DAY, speed);
+                let next_day_start = ((day + 1) % 7) as f64 * SECONDS_PER_DAY;
+                profile.add_interval(next_day_start, next_day_start + end, speed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The profile that applies to a way, if any.
+    pub fn way_profile(&self, way: &impl WayTags) -> Option<&WeeklySpeedProfile> {
+        self.way_profiles.get(&way.way_id()).or_else(|| {
+            way.tag("highway")
+                .and_then(|highway| self.highway_profiles.get(highway))
+        })
+    }
+
+    /// Seconds since Monday 00:00 in the local time of the profiles.
+    pub fn seconds_of_week(&self, time: &DateTime<Utc>) -> f64 {
+        let local_time =
+            time.naive_utc() + chrono::Duration::seconds(self.utc_offset_seconds as i64);
+
+        local_time.weekday().num_days_from_monday() as f64 * SECONDS_PER_DAY
+            + local_time.num_seconds_from_midnight() as f64
+            + local_time.nanosecond() as f64 / 1e9
+    }
+}
+
+impl WeeklySpeedProfile {
+    fn add_interval(&mut self, start: f64, end: f64, speed: f64) {
+        self.intervals.push(SpeedInterval { start, end, speed });
+        self.intervals
+            .sort_by(|left, right| left.start.total_cmp(&right.start));
+    }
+
+    /// Speed at a moment of the week, or None if no interval covers it.
+    pub fn speed_at(&self, seconds_of_week: f64) -> Option<f64> {
+        self.intervals
+            .iter()
+            .find(|interval| interval.start <= seconds_of_week && seconds_of_week < interval.end)
+            .map(|interval| interval.speed)
+    }
+
+    /// The next moment after `seconds_of_week` at which the speed may change, possibly in the next week.
+    fn next_change(&self, seconds_of_week: f64) -> f64 {
+        self.intervals
+            .iter()
+            .flat_map(|interval| [interval.start, interval.end])
+            .filter(|moment| *moment > seconds_of_week)
+            .fold(f64::INFINITY, f64::min)
+            .min(
+                self.intervals
+                    .first()
+                    .map_or(f64::INFINITY, |interval| interval.start + SECONDS_PER_WEEK),
+            )
+    }
+
+    /// Time needed to cover a distance when entering at `seconds_of_week`, integrating over speed changes.
+    /// Entering later can never mean arriving earlier, which keeps the time-dependent search correct.
+    pub fn travel_time(&self, distance: f64, base_speed: f64, seconds_of_week: f64) -> f64 {
+        let mut remaining_distance = distance;
+        let mut current_time = seconds_of_week;
+
+        loop {
+            let moment = current_time % SECONDS_PER_WEEK;
+            let speed = self
+                .speed_at(moment)
+                .unwrap_or(base_speed)
+                .clamp(0.1, ROAD_MAXIMUM_SPEED);
+
+            let time_until_change = self.next_change(moment) - moment;
+            let reachable_distance = speed * time_until_change;
+
+            if reachable_distance >= remaining_distance || !time_until_change.is_finite() {
+                current_time += remaining_distance / speed;
+                return current_time - seconds_of_week;
+            }
+
+            remaining_distance -= reachable_distance;
+            current_time += time_until_change;
+        }
+    }
+}
+
+impl OSMData {
+    pub fn load_speed_profiles(
+        &mut self,
+        file_path: &Path,
+        utc_offset_seconds: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.speed_profiles = Some(SpeedProfiles::from_csv(file_path, utc_offset_seconds)?);
+        Ok(())
+    }
+}
+
+fn parse_time_of_day(time: &str) -> Result<f64, Box<dyn Error>> {
+    let (hours, minutes) = time
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid time of day: {}", time))?;
+
+    let hours = hours.parse::<u32>()?;
+    let minutes = minutes.parse::<u32>()?;
+
+    if hours > 24 || minutes >= 60 || (hours == 24 && minutes > 0) {
+        return Err(format!("Invalid time of day: {}", time).into());
+    }
+
+    Ok((hours * 3600 + minutes * 60) as f64)
+}
+
+/// Day numbers from Monday (0) to Sunday (6).
+fn parse_days(days: &str) -> Result<Vec<u32>, Box<dyn Error>> {
+    let day_number = |day: &str| -> Result<u32, Box<dyn Error>> {
+        match day.to_lowercase().as_str() {
+            "mon" => Ok(0),
+            "tue" => Ok(1),
+            "wed" => Ok(2),
+            "thu" => Ok(3),
+            "fri" => Ok(4),
+            "sat" => Ok(5),
+            "sun" => Ok(6),
+            _ => Err(format!("Invalid day: {}", day).into()),
+        }
+    };
+
+    match days.to_lowercase().as_str() {
+        "all" => Ok((0..7).collect()),
+        "weekdays" => Ok((0..5).collect()),
+        "weekend" => Ok(vec![5, 6]),
+        days => match days.split_once('-') {
+            Some((first_day, last_day)) => {
+                let first_day = day_number(first_day)?;
+                let last_day = day_number(last_day)?;
+                Ok((0..7)
+                    .map(|offset| (first_day + offset) % 7)
+                    .take(((last_day + 7 - first_day) % 7 + 1) as usize)
+                    .collect())
+            }
+            None => Ok(vec![day_number(days)?]),
+        },
+    }
+}
@@ -1,22 +1,84 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
+pub mod fixtures;
 pub mod ns_api;
 pub mod public_transport;
+pub mod stand_in;
 pub mod stations;
 
-#[derive(Debug, Default)]
+/// A source of public transport trips between stations, by name.
+pub trait TransitProvider {
+    /// Trips leaving from `time` on, or with `search_for_arrival`, arriving by `time`.
+    fn departures(
+        &self,
+        start_station_name: &str,
+        target_station_name: &str,
+        time: &DateTime<Utc>,
+        search_for_arrival: bool,
+    ) -> Result<Vec<DepartureData>, Box<dyn Error>>;
+}
+
+/// The trips API of the NS (Dutch railways). Pointing the base URL at a `StandInServer` answers the
+/// same requests offline.
+#[derive(Debug, Clone)]
+pub struct NsApi {
+    pub base_url: String,
+    pub subscription_key: String,
+}
+
+/// Trips read from a JSON file, for tests and offline runs.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureProvider {
+    pub trips: Vec<FixtureTrip>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureTrip {
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub departure: DepartureData,
+}
+
+/// Answers NS API trip requests on localhost with the trips of a fixture provider, until dropped.
+#[derive(Debug)]
+pub struct StandInServer {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Where public transport trips come from, and how far away stations are used.
+pub struct TransitOptions<'a> {
+    pub provider: &'a dyn TransitProvider,
+    pub minimum_distance_to_station: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct DepartureData {
     pub time: DateTime<Utc>,
+    /// Seconds until the arrival.
     pub duration: f64,
     pub direction: String,
     pub transfers: i64,
     pub intermediate_stations: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PublicTransportResult {
     pub start_station_id: u64,
     pub end_station_id: u64,
+    /// Departure from the start station and arrival at the end station, as scheduled.
+    pub departure: DateTime<Utc>,
+    pub arrival: DateTime<Utc>,
+    /// Seconds between reaching the start station and the departure.
     pub waiting_time: f64,
     pub journey_duration: f64,
     pub direction: String,
@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+#[allow(unused)]
+use log::{info, warn};
+
+use super::{DepartureData, FixtureProvider, FixtureTrip, TransitProvider};
+use crate::utils::time_utilities::add_seconds;
+
+impl FixtureProvider {
+    pub fn new(trips: Vec<FixtureTrip>) -> Self {
+        FixtureProvider { trips }
+    }
+
+    /// Reads a JSON array of trips, each a `DepartureData` with the names of the stations it runs
+    /// `from` and `to`.
+    pub fn from_file(file_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let reader = BufReader::new(File::open(file_path)?);
+        let trips: Vec<FixtureTrip> = serde_json::from_reader(reader)?;
+
+        info!("Read {} trips from {}", trips.len(), file_path.display());
+        Ok(FixtureProvider::new(trips))
+    }
+}
+
+impl TransitProvider for FixtureProvider {
+    fn departures(
+        &self,
+        start_station_name: &str,
+        target_station_name: &str,
+        time: &DateTime<Utc>,
+        search_for_arrival: bool,
+    ) -> Result<Vec<DepartureData>, Box<dyn Error>> {
+        let mut departures: Vec<DepartureData> = self
+            .trips
+            .iter()
+            .filter(|trip| trip.from == start_station_name && trip.to == target_station_name)
+            .map(|trip| &trip.departure)
+            .filter(|departure| {
+                if search_for_arrival {
+                    add_seconds(departure.time, departure.duration) <= *time
+                } else {
+                    departure.time >= *time
+                }
+            })
+            .cloned()
+            .collect();
+
+        departures.sort_by_key(|departure| departure.time);
+        Ok(departures)
+    }
+}
@@ -1,4 +1,4 @@
-use super::{DepartureData, PublicTransportResult};
+use super::{DepartureData, NsApi, PublicTransportResult, TransitProvider};
 use chrono::{DateTime, Utc};
 #[allow(unused)]
 use log::{info, warn};
@@ -6,28 +6,75 @@ use reqwest::blocking::Client;
 use serde_json::Value;
 use std::error::Error;
 
-pub fn query_ns_api(
-    start_station_name: &String,
-    target_station_name: &String,
-    time: &DateTime<Utc>,
-) -> Result<Vec<DepartureData>, Box<dyn Error>> {
-    let url: String = format!(
-        "https://gateway.apiportal.ns.nl/reisinformatie-api/api/v3/trips?fromStation={}&toStation={}&originWalk=false&originBike=false&originCar=false&destinationWalk=false&destinationBike=false&destinationCar=false&dateTime={}&shorterChange=false&travelAssistance=false&searchForAccessibleTrip=false&localTrainsOnly=false&excludeHighSpeedTrains=false&excludeTrainsWithReservationRequired=false&discount=NO_DISCOUNT&travelClass=2&passing=false&travelRequestType=DEFAULT",
-        start_station_name, target_station_name, time.to_rfc3339()
-    ).replace(" ", "%20");
-
-    let subscription_key = "3cce8b71a9c94892bce40ba5d7d05593";
-    let client = Client::new();
-
-    let response = client
-        .get(&url)
-        .header("Cache-Control", "no-cache")
-        .header("Ocp-Apim-Subscription-Key", subscription_key)
-        .send()?
-        .text()?;
+use crate::utils::time_utilities::add_seconds;
+
+/// The trips endpoint of the NS API.
+pub const NS_API_URL: &str = "https://gateway.apiportal.ns.nl/reisinformatie-api/api/v3/trips";
+
+/// Query parameters sent with every trip request: trains only, between the stations themselves.
+const TRIP_PARAMETERS: [(&str, &str); 16] = [
+    ("originWalk", "false"),
+    ("originBike", "false"),
+    ("originCar", "false"),
+    ("destinationWalk", "false"),
+    ("destinationBike", "false"),
+    ("destinationCar", "false"),
+    ("shorterChange", "false"),
+    ("travelAssistance", "false"),
+    ("searchForAccessibleTrip", "false"),
+    ("localTrainsOnly", "false"),
+    ("excludeHighSpeedTrains", "false"),
+    ("excludeTrainsWithReservationRequired", "false"),
+    ("discount", "NO_DISCOUNT"),
+    ("travelClass", "2"),
+    ("passing", "false"),
+    ("travelRequestType", "DEFAULT"),
+];
+
+impl Default for NsApi {
+    fn default() -> Self {
+        NsApi {
+            base_url: NS_API_URL.to_string(),
+            subscription_key: "3cce8b71a9c94892bce40ba5d7d05593".to_string(),
+        }
+    }
+}
 
-    let data: Value = serde_json::from_str(&response)?;
+impl TransitProvider for NsApi {
+    fn departures(
+        &self,
+        start_station_name: &str,
+        target_station_name: &str,
+        time: &DateTime<Utc>,
+        search_for_arrival: bool,
+    ) -> Result<Vec<DepartureData>, Box<dyn Error>> {
+        let client = Client::new();
+
+        let response = client
+            .get(&self.base_url)
+            .query(&[
+                ("fromStation", start_station_name),
+                ("toStation", target_station_name),
+                ("dateTime", &time.to_rfc3339()),
+                ("searchForArrival", &search_for_arrival.to_string()),
+            ])
+            .query(&TRIP_PARAMETERS)
+            .header("Cache-Control", "no-cache")
+            .header("Ocp-Apim-Subscription-Key", &self.subscription_key)
+            .send()?
+            .text()?;
+
+        let data: Value = serde_json::from_str(&response)?;
+        Ok(parse_trips(&data, start_station_name, target_station_name))
+    }
+}
 
+/// The trips with a normal status in a response of the NS API.
+pub fn parse_trips(
+    data: &Value,
+    start_station_name: &str,
+    target_station_name: &str,
+) -> Vec<DepartureData> {
     let mut departures: Vec<DepartureData> = Vec::new();
 
     if let Some(trips) = data["trips"].as_array() {
@@ -79,45 +126,73 @@ pub fn query_ns_api(
         );
     }
 
-    Ok(departures)
+    departures
 }
 
+/// The first train leaving after `time`.
 pub fn get_next_train(
+    provider: &dyn TransitProvider,
     start_station_id: u64,
     end_station_id: u64,
-    start_station_name: &String,
-    target_station_name: &String,
+    start_station_name: &str,
+    target_station_name: &str,
     time: &DateTime<Utc>,
-) -> Option<PublicTransportResult> {
-    if let Ok(train_departures) = query_ns_api(start_station_name, target_station_name, &time) {
-        for departure in train_departures.iter() {
-            let waiting_time = (departure.time - time).num_seconds() as f64; // seconds
-
-            let journey_duration = departure.duration;
-            let direction = departure.direction.clone();
-
-            let transfers = departure.transfers;
-            let intermediate_stations = departure.intermediate_stations.clone();
-
-            if waiting_time > 0.0 {
-                let transport_result = PublicTransportResult::new(
-                    start_station_id,
-                    end_station_id,
-                    journey_duration,
-                    waiting_time,
-                    direction,
-                    transfers,
-                    intermediate_stations,
-                );
-
-                return Some(transport_result);
-            }
-        }
-    } else {
-        warn!(
-            "Incorrect trip between {} -> {}",
-            start_station_name, target_station_name
-        );
-    }
-    None
+) -> Result<PublicTransportResult, Box<dyn Error>> {
+    let train_departures = provider
+        .departures(start_station_name, target_station_name, time, false)
+        .map_err(|error| {
+            format!(
+                "Incorrect trip between {} -> {}: {}",
+                start_station_name, target_station_name, error
+            )
+        })?;
+
+    train_departures
+        .iter()
+        .find(|departure| departure.time > *time)
+        .map(|departure| {
+            PublicTransportResult::new(start_station_id, end_station_id, departure, *time)
+        })
+        .ok_or_else(|| {
+            format!(
+                "No train between {} -> {} after {}",
+                start_station_name, target_station_name, time
+            )
+            .into()
+        })
+}
+
+/// The last train arriving by `time`.
+pub fn get_last_train(
+    provider: &dyn TransitProvider,
+    start_station_id: u64,
+    end_station_id: u64,
+    start_station_name: &str,
+    target_station_name: &str,
+    time: &DateTime<Utc>,
+) -> Result<PublicTransportResult, Box<dyn Error>> {
+    let train_departures = provider
+        .departures(start_station_name, target_station_name, time, true)
+        .map_err(|error| {
+            format!(
+                "Incorrect trip between {} -> {}: {}",
+                start_station_name, target_station_name, error
+            )
+        })?;
+
+    train_departures
+        .iter()
+        .filter(|departure| add_seconds(departure.time, departure.duration) <= *time)
+        .max_by_key(|departure| departure.time)
+        // The wait depends on when the station is reached, which is known once the route is scheduled.
+        .map(|departure| {
+            PublicTransportResult::new(start_station_id, end_station_id, departure, departure.time)
+        })
+        .ok_or_else(|| {
+            format!(
+                "No train between {} -> {} arriving by {}",
+                start_station_name, target_station_name, time
+            )
+            .into()
+        })
 }
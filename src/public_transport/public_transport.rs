@@ -1,51 +1,53 @@
-use std::time::Duration;
-
 use chrono::{DateTime, Utc};
 
 use crate::data_handling::OSMData;
 use crate::path_finding::nearest_road::find_closest_road;
 use crate::path_finding::path_finding::path_finding;
 use crate::path_finding::TransportMode;
-use crate::route_manager::Route;
-use crate::route_manager::RouteComponent;
+use crate::route_manager::{
+    CandidateError, Route, RouteCandidate, RouteComponent, RouteSearchResult, RouteTime,
+};
+use crate::utils::time_utilities::add_seconds;
 
 #[allow(unused)]
 use log::{info, warn};
 
-use super::ns_api::get_next_train;
+use super::ns_api::{get_last_train, get_next_train};
 use super::stations::find_nearby_stations;
-use super::PublicTransportResult;
+use super::{DepartureData, PublicTransportResult, TransitOptions};
 
 impl PublicTransportResult {
+    /// Taking the departure after reaching the start station at `station_time`.
     pub fn new(
         start_station_id: u64,
         end_station_id: u64,
-        journey_duration: f64,
-        waiting_time: f64,
-        direction: String,
-        transfers: i64,
-        intermediate_stations: Vec<String>,
+        departure: &DepartureData,
+        station_time: DateTime<Utc>,
     ) -> Self {
         PublicTransportResult {
             start_station_id,
             end_station_id,
-            journey_duration,
-            waiting_time,
-            direction,
-            transfers,
-            intermediate_stations,
+            departure: departure.time,
+            arrival: add_seconds(departure.time, departure.duration),
+            waiting_time: (departure.time - station_time).num_milliseconds() as f64 / 1000.0,
+            journey_duration: departure.duration,
+            direction: departure.direction.clone(),
+            transfers: departure.transfers,
+            intermediate_stations: departure.intermediate_stations.clone(),
         }
     }
 }
 
+/// Routes with a train between every pair of stations near the start and target, and the error for
+/// every pair that didn't give one. Arriving by a time takes the last train that still makes it.
 pub fn public_transport(
     osm_data: &OSMData,
     transport_mode: &TransportMode,
-    time: DateTime<Utc>,
-    minimum_distance_to_station: f64,
+    time: RouteTime,
+    transit_options: &TransitOptions,
     starting_node_id: u64,
     target_node_id: u64,
-) -> Result<Vec<Route>, Box<dyn std::error::Error>> {
+) -> RouteSearchResult {
     // Setting up the starting/target node
     let start_landmark = osm_data
         .node_map
@@ -65,15 +67,15 @@ pub fn public_transport(
     let stations_near_start = find_nearby_stations(
         &osm_data,
         &start_landmark.coordinate,
-        minimum_distance_to_station,
+        transit_options.minimum_distance_to_station,
     );
     let stations_near_target = find_nearby_stations(
         &osm_data,
         &target_landmark.coordinate,
-        minimum_distance_to_station,
+        transit_options.minimum_distance_to_station,
     );
 
-    let mut route_options: Vec<Route> = Vec::new();
+    let mut search_result = RouteSearchResult::default();
 
     // Checking all the station combinations
     for start_station_id in stations_near_start.iter() {
@@ -91,17 +93,31 @@ pub fn public_transport(
         let road_to_start_station = find_closest_road(osm_data, *start_station_id, &transport_mode);
 
         let start_road_to_station = path_finding(
-            &osm_data,
+            osm_data,
             start_to_start_road.end_node,
             road_to_start_station.end_node,
             &transport_mode,
-        )
-        .expect("Failed to find path.");
+        );
 
         for target_station_id in stations_near_target.iter() {
             if start_station_id == target_station_id {
                 continue;
             }
+            let mut candidate_error = |message: String| {
+                search_result.errors.push(CandidateError {
+                    candidate: RouteCandidate::PublicTransport {
+                        start_station_id: *start_station_id,
+                        target_station_id: *target_station_id,
+                    },
+                    message,
+                })
+            };
+
+            let Some(start_road_to_station) = &start_road_to_station else {
+                candidate_error("No path to the start station.".to_string());
+                continue;
+            };
+
             let target_station_node = osm_data
                 .node_map
                 .get(target_station_id)
@@ -115,60 +131,91 @@ pub fn public_transport(
             let target_station_to_target_station_road =
                 find_closest_road(osm_data, *target_station_id, &transport_mode);
 
-            let target_station_road_to_target_road = path_finding(
-                &osm_data,
+            let Some(target_station_road_to_target_road) = path_finding(
+                osm_data,
                 target_station_to_target_station_road.end_node,
                 target_road_to_target.end_node,
                 &transport_mode,
-            )
-            .ok_or("Error: no path found.")?;
-
-            // Tracking time spent travelling to the station.
-            let time_to_station = Duration::from_secs_f64(
-                start_to_start_road.path_time
-                    + start_road_to_station.path_time
-                    + road_to_start_station.path_time,
-            );
-
-            let public_transport = get_next_train(
-                *start_station_id,
-                *target_station_id,
-                start_station_name,
-                target_station_name,
-                &(time + time_to_station),
-            );
-
-            if let Some(public_transport) = public_transport {
-                let start_to_start_road_component =
-                    RouteComponent::Path(start_to_start_road.clone());
-                let start_road_to_station_component =
-                    RouteComponent::Path(start_road_to_station.clone());
-                let road_to_start_station_component =
-                    RouteComponent::Path(road_to_start_station.clone());
-                let public_transport_component = RouteComponent::PublicTransport(public_transport);
-                let target_station_to_target_station_road_component =
-                    RouteComponent::Path(target_station_to_target_station_road.clone());
-                let target_station_road_to_target_road_component =
-                    RouteComponent::Path(target_station_road_to_target_road.clone());
-                let target_road_to_target_component =
-                    RouteComponent::Path(target_road_to_target.clone());
-
-                let components = vec![
-                    start_to_start_road_component,
-                    start_road_to_station_component,
-                    road_to_start_station_component,
-                    public_transport_component,
-                    target_station_to_target_station_road_component,
-                    target_station_road_to_target_road_component,
-                    target_road_to_target_component,
-                ];
-
-                let route = Route::new(components);
-
-                route_options.push(route);
-            }
+            ) else {
+                candidate_error("No path from the target station.".to_string());
+                continue;
+            };
+
+            let public_transport = match time {
+                RouteTime::DepartAt(departure) => {
+                    // The time the station is reached.
+                    let station_time = add_seconds(
+                        departure,
+                        start_to_start_road.path_time
+                            + start_road_to_station.path_time
+                            + road_to_start_station.path_time,
+                    );
+
+                    get_next_train(
+                        transit_options.provider,
+                        *start_station_id,
+                        *target_station_id,
+                        start_station_name,
+                        target_station_name,
+                        &station_time,
+                    )
+                }
+                RouteTime::ArriveBy(arrival) => {
+                    // The time the train has to arrive by.
+                    let station_time = add_seconds(
+                        arrival,
+                        -(target_station_to_target_station_road.path_time
+                            + target_station_road_to_target_road.path_time
+                            + target_road_to_target.path_time),
+                    );
+
+                    get_last_train(
+                        transit_options.provider,
+                        *start_station_id,
+                        *target_station_id,
+                        start_station_name,
+                        target_station_name,
+                        &station_time,
+                    )
+                }
+            };
+            let public_transport = match public_transport {
+                Ok(public_transport) => public_transport,
+                Err(error) => {
+                    candidate_error(error.to_string());
+                    continue;
+                }
+            };
+
+            let start_to_start_road_component = RouteComponent::Path(start_to_start_road.clone());
+            let start_road_to_station_component =
+                RouteComponent::Path(start_road_to_station.clone());
+            let road_to_start_station_component =
+                RouteComponent::Path(road_to_start_station.clone());
+            let public_transport_component = RouteComponent::PublicTransport(public_transport);
+            let target_station_to_target_station_road_component =
+                RouteComponent::Path(target_station_to_target_station_road.clone());
+            let target_station_road_to_target_road_component =
+                RouteComponent::Path(target_station_road_to_target_road);
+            let target_road_to_target_component =
+                RouteComponent::Path(target_road_to_target.clone());
+
+            let components = vec![
+                start_to_start_road_component,
+                start_road_to_station_component,
+                road_to_start_station_component,
+                public_transport_component,
+                target_station_to_target_station_road_component,
+                target_station_road_to_target_road_component,
+                target_road_to_target_component,
+            ];
+
+            let mut route = Route::new(components);
+            route.schedule_for(time);
+
+            search_result.routes.push(route);
         }
     }
 
-    Ok(route_options)
+    search_result
 }
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use chrono::{DateTime, Utc};
+#[allow(unused)]
+use log::{info, warn};
+use reqwest::Url;
+use serde_json::{json, Value};
+
+use super::{DepartureData, FixtureProvider, StandInServer, TransitProvider};
+
+impl StandInServer {
+    /// Starts answering on a free port of localhost.
+    pub fn start(provider: FixtureProvider) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let listener_stopped = Arc::clone(&stopped);
+        let thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if listener_stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let result = stream
+                    .map_err(Box::<dyn Error>::from)
+                    .and_then(|stream| answer(stream, &provider));
+                if let Err(error) = result {
+                    warn!("Stand-in failed to answer a request: {}", error);
+                }
+            }
+        });
+
+        info!("Answering NS API requests on {}", address);
+        Ok(StandInServer {
+            address,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    /// The URL to use as the base URL of an `NsApi`.
+    pub fn url(&self) -> String {
+        format!("http://{}/trips", self.address)
+    }
+}
+
+impl Drop for StandInServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wakes up the listener, which then stops without answering.
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn answer(mut stream: TcpStream, provider: &FixtureProvider) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // The headers end with an empty line.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let request_target = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or("Malformed request line")?;
+    let (status, body) = match trips_response(request_target, provider) {
+        Ok(body) => ("200 OK", body),
+        Err(error) => ("400 Bad Request", json!({ "message": error.to_string() })),
+    };
+
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn trips_response(
+    request_target: &str,
+    provider: &FixtureProvider,
+) -> Result<Value, Box<dyn Error>> {
+    let url = Url::parse(&format!("http://localhost{}", request_target))?;
+    let parameters: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let parameter = |name: &str| {
+        parameters
+            .get(name)
+            .ok_or_else(|| format!("Missing parameter {}", name))
+    };
+
+    let start_station_name = parameter("fromStation")?;
+    let target_station_name = parameter("toStation")?;
+    let time = DateTime::parse_from_rfc3339(parameter("dateTime")?)?.with_timezone(&Utc);
+    let search_for_arrival = parameter("searchForArrival").is_ok_and(|value| value == "true");
+
+    let trips: Vec<Value> = provider
+        .departures(
+            start_station_name,
+            target_station_name,
+            &time,
+            search_for_arrival,
+        )?
+        .iter()
+        .map(|departure| trip_json(start_station_name, departure))
+        .collect();
+
+    Ok(json!({ "trips": trips }))
+}
+
+/// A trip the way the NS API describes it: a leg from the start station, then one from every station
+/// where the traveller transfers.
+fn trip_json(start_station_name: &str, departure: &DepartureData) -> Value {
+    let mut legs = vec![json!({
+        "origin": {
+            "name": start_station_name,
+            "plannedDateTime": departure.time.to_rfc3339(),
+        },
+        "direction": departure.direction,
+    })];
+    legs.extend(
+        departure
+            .intermediate_stations
+            .iter()
+            .map(|station_name| json!({ "origin": { "name": station_name } })),
+    );
+
+    json!({
+        "status": "NORMAL",
+        "transfers": departure.transfers,
+        "actualDurationInMinutes": departure.duration / 60.0,
+        "legs": legs,
+    })
+}
@@ -19,8 +19,8 @@ pub fn find_nearby_stations(
                 if *landmark_name == "stations".to_string() {
                     for node_id in node_subset.node_subset.iter() {
                         if let Some(node) = osm_data.node_map.get(node_id) {
-                            if node.tags.get("public_transport") == Some(&"station".to_string())
-                                && node.tags.get("railway") == Some(&"station".to_string())
+                            if node.tags.get("public_transport") == Some("station")
+                                && node.tags.get("railway") == Some("station")
                             {
                                 let distance_to_station =
                                     node.coordinate.geodesic_distance(&coordinate);
@@ -1,15 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
 use crate::{path_finding::PathResult, public_transport::PublicTransportResult};
 
+pub mod itinerary;
+pub mod route_export;
 pub mod route_manager;
 pub mod transport_options;
 
-#[derive(Clone, Debug)]
+/// Version of the `Itinerary` JSON schema. Fields may be added without changing it; it is raised when
+/// a field is removed or changes meaning.
+pub const ITINERARY_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum RouteComponent {
     Path(PathResult),
     PublicTransport(PublicTransportResult),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Route {
     pub components: Vec<RouteComponent>,
 }
+
+/// What the routes of a search are ranked by, from low to high. Ties go to the shortest duration.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RouteCriterion {
+    #[default]
+    Duration,
+    Transfers,
+    /// Meters travelled along roads, i.e. everything but public transport.
+    WalkingDistance,
+    /// Latest departure first; what arrive-by searches rank by by default.
+    LatestDeparture,
+    /// Only the routes no other route beats on every one of the `RouteCosts`, ranked by their weighted
+    /// cost, or by duration without weights.
+    Pareto(Option<RouteWeights>),
+}
+
+/// What a route costs the traveller, in the terms routes are compared by.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RouteCosts {
+    /// Seconds from departure to arrival.
+    pub duration: f64,
+    pub transfers: f64,
+    /// Meters walked, cycled or driven.
+    pub active_distance: f64,
+    /// Seconds spent waiting for public transport.
+    pub waiting_time: f64,
+}
+
+/// How much each of the `RouteCosts` counts, in seconds per unit. The default counts a transfer as
+/// five minutes and otherwise only the duration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteWeights {
+    pub duration: f64,
+    pub transfers: f64,
+    pub active_distance: f64,
+    pub waiting_time: f64,
+}
+
+impl Default for RouteWeights {
+    fn default() -> Self {
+        RouteWeights {
+            duration: 1.0,
+            transfers: 300.0,
+            active_distance: 0.0,
+            waiting_time: 0.0,
+        }
+    }
+}
+
+/// When a route search is pinned: leaving at a time, or arriving by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteTime {
+    DepartAt(DateTime<Utc>),
+    ArriveBy(DateTime<Utc>),
+}
+
+/// A route the search tried: the direct route, or public transport between two stations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteCandidate {
+    Direct,
+    PublicTransport {
+        start_station_id: u64,
+        target_station_id: u64,
+    },
+}
+
+/// Why a candidate didn't give a route.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateError {
+    pub candidate: RouteCandidate,
+    pub message: String,
+}
+
+/// All routes found by a search, best first, and the candidates that failed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteSearchResult {
+    pub routes: Vec<Route>,
+    pub errors: Vec<CandidateError>,
+}
+
+/// A route laid out in time and space for programmatic consumers, e.g. serialized to JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Itinerary {
+    pub schema_version: u32,
+    pub departure: DateTime<Utc>,
+    pub arrival: DateTime<Utc>,
+    /// Seconds from departure to arrival.
+    pub duration: f64,
+    pub legs: Vec<ItineraryLeg>,
+}
+
+/// One route component with its mode and geometry. The component's own fields, including its
+/// departure and arrival, are included as well, next to a `kind` of `path` or `public_transport`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ItineraryLeg {
+    /// `walk`, `bike`, `car` or `train`.
+    pub mode: String,
+    /// Name or address of the node the leg starts at, if it has one.
+    pub start_name: Option<String>,
+    /// `[longitude, latitude]` pairs.
+    pub geometry: Vec<[f64; 2]>,
+    #[serde(flatten)]
+    pub component: RouteComponent,
+}
@@ -0,0 +1,51 @@
+#[allow(unused)]
+use log::{info, warn};
+
+use super::{Itinerary, ItineraryLeg, Route, RouteComponent, ITINERARY_SCHEMA_VERSION};
+use crate::data_handling::OSMData;
+use crate::path_finding::TransportMode;
+use crate::utils::tag_name_utilities::node_name_or_address;
+
+impl Route {
+    /// The route with the times it was scheduled with. An empty route departs and arrives at the Unix
+    /// epoch.
+    pub fn itinerary(&self, osm_data: &OSMData, transport_mode: &TransportMode) -> Itinerary {
+        let legs: Vec<ItineraryLeg> = self
+            .components
+            .iter()
+            .map(|component| {
+                let start_node_id = match component {
+                    RouteComponent::Path(path_result) => path_result.start_node,
+                    RouteComponent::PublicTransport(public_transport) => {
+                        public_transport.start_station_id
+                    }
+                };
+
+                ItineraryLeg {
+                    mode: component.mode_label(transport_mode).to_string(),
+                    start_name: osm_data
+                        .node_map
+                        .get(&start_node_id)
+                        .and_then(node_name_or_address),
+                    geometry: component
+                        .line_string(osm_data)
+                        .coords()
+                        .map(|coordinate| [coordinate.x, coordinate.y])
+                        .collect(),
+                    component: component.clone(),
+                }
+            })
+            .collect();
+
+        let departure = self.departure().unwrap_or_default();
+        let arrival = self.arrival().unwrap_or_default();
+
+        Itinerary {
+            schema_version: ITINERARY_SCHEMA_VERSION,
+            departure,
+            arrival,
+            duration: (arrival - departure).num_milliseconds() as f64 / 1000.0,
+            legs,
+        }
+    }
+}
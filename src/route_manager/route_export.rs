@@ -0,0 +1,220 @@
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use geo::{Coord, LineString};
+#[allow(unused)]
+use log::{info, warn};
+use quick_xml::escape::escape;
+use serde_json::{json, Value};
+
+use super::{Route, RouteComponent};
+use crate::data_handling::OSMData;
+use crate::osm_parsing::Node;
+use crate::path_finding::TransportMode;
+use crate::utils::polylines::encode_polyline;
+
+impl RouteComponent {
+    /// The nodes of a path, or a straight line between the stations of a public transport leg, whose
+    /// track is not known.
+    pub fn line_string(&self, osm_data: &OSMData) -> LineString {
+        self.nodes(osm_data)
+            .iter()
+            .map(|node| node.coordinate)
+            .collect()
+    }
+
+    fn nodes<'a>(&self, osm_data: &'a OSMData) -> Vec<&'a Node> {
+        let node_ids = match self {
+            RouteComponent::Path(path_result) => path_result.found_path.clone(),
+            RouteComponent::PublicTransport(public_transport) => vec![
+                public_transport.start_station_id,
+                public_transport.end_station_id,
+            ],
+        };
+
+        node_ids
+            .iter()
+            .filter_map(|node_id| osm_data.node_map.get(node_id))
+            .collect()
+    }
+
+    /// Travel time in seconds, including the wait for public transport.
+    pub fn duration(&self) -> f64 {
+        match self {
+            RouteComponent::Path(path_result) => path_result.path_time,
+            RouteComponent::PublicTransport(public_transport) => {
+                public_transport.journey_duration + public_transport.waiting_time
+            }
+        }
+    }
+
+    /// The mode of a path is the one the route was searched with.
+    pub fn mode_label(&self, transport_mode: &TransportMode) -> &'static str {
+        match self {
+            RouteComponent::Path(_) => transport_mode.label(),
+            RouteComponent::PublicTransport(_) => "train",
+        }
+    }
+}
+
+impl Route {
+    /// All components as one line, without repeating the point where one component ends and the next
+    /// starts.
+    pub fn line_string(&self, osm_data: &OSMData) -> LineString {
+        let mut coordinates: Vec<Coord> = Vec::new();
+        for component in self.components.iter() {
+            for coordinate in component.line_string(osm_data).coords() {
+                if coordinates.last() != Some(coordinate) {
+                    coordinates.push(*coordinate);
+                }
+            }
+        }
+        LineString::from(coordinates)
+    }
+
+    /// A FeatureCollection with a LineString for every component. Its properties are the mode, the
+    /// duration in seconds and the length in meters, which is null for public transport.
+    pub fn to_geojson(&self, osm_data: &OSMData, transport_mode: &TransportMode) -> Value {
+        let features: Vec<Value> = self
+            .components
+            .iter()
+            .map(|component| {
+                let coordinates: Vec<[f64; 2]> = component
+                    .line_string(osm_data)
+                    .coords()
+                    .map(|coordinate| [coordinate.x, coordinate.y])
+                    .collect();
+
+                let properties = match component {
+                    RouteComponent::Path(path_result) => json!({
+                        "mode": component.mode_label(transport_mode),
+                        "duration": component.duration(),
+                        "length": path_result.path_length,
+                    }),
+                    RouteComponent::PublicTransport(public_transport) => json!({
+                        "mode": component.mode_label(transport_mode),
+                        "duration": component.duration(),
+                        "length": null,
+                        "waiting_time": public_transport.waiting_time,
+                        "direction": public_transport.direction,
+                        "transfers": public_transport.transfers,
+                    }),
+                };
+
+                json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "LineString",
+                        "coordinates": coordinates,
+                    },
+                    "properties": properties,
+                })
+            })
+            .collect();
+
+        json!({
+            "type": "FeatureCollection",
+            "features": features,
+        })
+    }
+
+    /// A GPX 1.1 track with a segment for every component, with elevations where the nodes have them.
+    pub fn to_gpx(&self, osm_data: &OSMData) -> String {
+        let mut gpx = String::new();
+        gpx += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+        gpx += "<gpx version=\"1.1\" creator=\"osm-rust\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n";
+        gpx += " <trk>\n  <name>Route</name>\n";
+
+        for component in self.components.iter() {
+            gpx += "  <trkseg>\n";
+            for node in component.nodes(osm_data) {
+                let _ = write!(
+                    gpx,
+                    "   <trkpt lat=\"{:.7}\" lon=\"{:.7}\">",
+                    node.coordinate.y(),
+                    node.coordinate.x()
+                );
+                if let Some(elevation) = node.elevation {
+                    let _ = write!(gpx, "<ele>{:.1}</ele>", elevation);
+                }
+                gpx += "</trkpt>\n";
+            }
+            gpx += "  </trkseg>\n";
+        }
+
+        gpx += " </trk>\n</gpx>\n";
+        gpx
+    }
+
+    /// A KML document with a placemark for every component, named after its mode.
+    pub fn to_kml(&self, osm_data: &OSMData, transport_mode: &TransportMode) -> String {
+        let mut kml = String::new();
+        kml += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+        kml += "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n";
+        kml += " <Document>\n  <name>Route</name>\n";
+
+        for component in self.components.iter() {
+            let description = match component {
+                RouteComponent::Path(path_result) => format!(
+                    "{:.1} minutes, {:.0} m",
+                    component.duration() / 60.0,
+                    path_result.path_length
+                ),
+                RouteComponent::PublicTransport(public_transport) => format!(
+                    "{:.1} minutes in the direction of {}",
+                    component.duration() / 60.0,
+                    public_transport.direction
+                ),
+            };
+            let coordinates: Vec<String> = component
+                .line_string(osm_data)
+                .coords()
+                .map(|coordinate| format!("{:.7},{:.7}", coordinate.x, coordinate.y))
+                .collect();
+
+            let _ = write!(
+                kml,
+                "  <Placemark>\n   <name>{}</name>\n   <description>{}</description>\n   <LineString><coordinates>{}</coordinates></LineString>\n  </Placemark>\n",
+                component.mode_label(transport_mode),
+                escape(&description),
+                coordinates.join(" ")
+            );
+        }
+
+        kml += " </Document>\n</kml>\n";
+        kml
+    }
+
+    /// The whole route as a Google encoded polyline with precision 5.
+    pub fn to_polyline(&self, osm_data: &OSMData) -> String {
+        encode_polyline(&self.line_string(osm_data), 5)
+    }
+
+    /// Writes the route as GeoJSON (`.geojson`, `.json`), GPX (`.gpx`), KML (`.kml`) or an encoded
+    /// polyline (`.polyline`, `.txt`).
+    pub fn export(
+        &self,
+        osm_data: &OSMData,
+        transport_mode: &TransportMode,
+        file_path: &Path,
+    ) -> Result<(), Box<dyn Error>> {
+        let contents = match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("geojson") | Some("json") => {
+                serde_json::to_string(&self.to_geojson(osm_data, transport_mode))?
+            }
+            Some("gpx") => self.to_gpx(osm_data),
+            Some("kml") => self.to_kml(osm_data, transport_mode),
+            Some("polyline") | Some("txt") => self.to_polyline(osm_data),
+            _ => {
+                return Err(
+                    format!("Unsupported route file extension: {}", file_path.display()).into(),
+                )
+            }
+        };
+
+        fs::write(file_path, contents)?;
+        Ok(())
+    }
+}
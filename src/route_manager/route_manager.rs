@@ -1,8 +1,30 @@
-use super::{Route, RouteComponent};
+use std::fmt::Display;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use super::{Route, RouteComponent, RouteCosts, RouteTime};
+use crate::public_transport::PublicTransportResult;
+use crate::utils::time_utilities::add_seconds;
 use crate::{data_handling::OSMData, utils::tag_name_utilities::node_name_or_address};
 #[allow(unused)]
 use log::{info, warn};
 
+impl RouteComponent {
+    pub fn departure(&self) -> DateTime<Utc> {
+        match self {
+            RouteComponent::Path(path_result) => path_result.departure,
+            RouteComponent::PublicTransport(public_transport) => public_transport.departure,
+        }
+    }
+
+    pub fn arrival(&self) -> DateTime<Utc> {
+        match self {
+            RouteComponent::Path(path_result) => path_result.arrival,
+            RouteComponent::PublicTransport(public_transport) => public_transport.arrival,
+        }
+    }
+}
+
 impl Route {
     pub fn new(components: Vec<RouteComponent>) -> Self {
         Route { components }
@@ -13,20 +35,141 @@ impl Route {
         Route { components }
     }
 
-    pub fn total_duration(&self) -> f64 {
-        let mut total_duration = 0.0;
-        for component in self.components.iter() {
-            let duration = match component {
-                RouteComponent::Path(path_result) => path_result.path_time,
+    /// Times the route from the departure: every path starts when the previous component ends. Public
+    /// transport keeps its timetable; its waiting time becomes the time between reaching the station
+    /// and departing, which is negative if the connection is missed.
+    pub fn schedule(&mut self, departure: DateTime<Utc>) {
+        let mut time = departure;
+
+        for component in self.components.iter_mut() {
+            match component {
+                RouteComponent::Path(path_result) => path_result.depart_at(time),
                 RouteComponent::PublicTransport(public_transport) => {
-                    public_transport.journey_duration + public_transport.waiting_time
+                    public_transport.waiting_time =
+                        (public_transport.departure - time).num_milliseconds() as f64 / 1000.0;
                 }
+            }
+            time = component.arrival();
+        }
+    }
+
+    /// Times the route to arrive by the given time, leaving as late as possible: the components before
+    /// the last public transport are timed backward from it, the ones after it follow on directly.
+    pub fn schedule_arrival(&mut self, arrival: DateTime<Utc>) {
+        let mut time = arrival;
+        for component in self.components.iter().rev() {
+            time = match component {
+                RouteComponent::Path(path_result) => add_seconds(time, -path_result.path_time),
+                RouteComponent::PublicTransport(public_transport) => public_transport.departure,
             };
-            total_duration += duration;
         }
-        total_duration
+
+        self.schedule(time);
+    }
+
+    pub fn schedule_for(&mut self, route_time: RouteTime) {
+        match route_time {
+            RouteTime::DepartAt(departure) => self.schedule(departure),
+            RouteTime::ArriveBy(arrival) => self.schedule_arrival(arrival),
+        }
+    }
+
+    pub fn departure(&self) -> Option<DateTime<Utc>> {
+        self.components.first().map(RouteComponent::departure)
+    }
+
+    pub fn arrival(&self) -> Option<DateTime<Utc>> {
+        self.components.last().map(RouteComponent::arrival)
+    }
+
+    /// Whether every component can be started when the previous one ends, i.e. no connection is missed.
+    pub fn is_feasible(&self) -> bool {
+        self.components
+            .windows(2)
+            .all(|components| components[1].departure() >= components[0].arrival())
+    }
+
+    /// A one-line itinerary in the given time zone, e.g. "depart 08:12, train 08:31–09:40, arrive 09:58".
+    pub fn timeline<Tz: TimeZone>(&self, time_zone: &Tz) -> String
+    where
+        Tz::Offset: Display,
+    {
+        let (Some(departure), Some(arrival)) = (self.departure(), self.arrival()) else {
+            return String::new();
+        };
+        let clock_time = |time: DateTime<Utc>| time.with_timezone(time_zone).format("%H:%M");
+
+        let mut parts = vec![format!("depart {}", clock_time(departure))];
+        for component in self.components.iter() {
+            if let RouteComponent::PublicTransport(public_transport) = component {
+                parts.push(format!(
+                    "train {}–{}",
+                    clock_time(public_transport.departure),
+                    clock_time(public_transport.arrival)
+                ));
+            }
+        }
+        parts.push(format!("arrive {}", clock_time(arrival)));
+
+        parts.join(", ")
+    }
+
+    pub fn total_duration(&self) -> f64 {
+        self.components.iter().map(RouteComponent::duration).sum()
+    }
+
+    /// Transfers within public transport journeys, plus one for every change between them.
+    pub fn transfers(&self) -> i64 {
+        let journeys: Vec<&PublicTransportResult> = self
+            .components
+            .iter()
+            .filter_map(|component| match component {
+                RouteComponent::PublicTransport(public_transport) => Some(public_transport),
+                RouteComponent::Path(_) => None,
+            })
+            .collect();
+
+        journeys
+            .iter()
+            .map(|public_transport| public_transport.transfers)
+            .sum::<i64>()
+            + journeys.len().saturating_sub(1) as i64
+    }
+
+    /// Seconds spent waiting for public transport, not counting missed connections.
+    pub fn waiting_time(&self) -> f64 {
+        self.components
+            .iter()
+            .map(|component| match component {
+                RouteComponent::Path(_) => 0.0,
+                RouteComponent::PublicTransport(public_transport) => {
+                    public_transport.waiting_time.max(0.0)
+                }
+            })
+            .sum()
+    }
+
+    pub fn costs(&self) -> RouteCosts {
+        RouteCosts {
+            duration: self.total_duration(),
+            transfers: self.transfers() as f64,
+            active_distance: self.road_distance(),
+            waiting_time: self.waiting_time(),
+        }
+    }
+
+    /// Meters travelled along roads.
+    pub fn road_distance(&self) -> f64 {
+        self.components
+            .iter()
+            .map(|component| match component {
+                RouteComponent::Path(path_result) => path_result.path_length,
+                RouteComponent::PublicTransport(_) => 0.0,
+            })
+            .sum()
     }
 
+    /// Prints the route for people; programs should serialize `Route::itinerary` instead.
     pub fn print_route(&self, osm_data: &OSMData) {
         for component in self.components.iter() {
             match component {
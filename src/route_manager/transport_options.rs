@@ -1,68 +1,167 @@
-use chrono::{DateTime, Utc};
+use std::fmt;
+
 #[allow(unused)]
 use log::{info, warn};
 
 use crate::{
     data_handling::OSMData,
     path_finding::{path_finding::direct_route, TransportMode},
-    public_transport::public_transport::public_transport,
-    utils::distance_utilities::f64_to_u64,
+    public_transport::{public_transport::public_transport, TransitOptions},
+};
+
+use super::{
+    CandidateError, Route, RouteCandidate, RouteCosts, RouteCriterion, RouteSearchResult,
+    RouteTime, RouteWeights,
 };
 
-use super::Route;
+impl fmt::Display for RouteCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteCandidate::Direct => write!(f, "direct route"),
+            RouteCandidate::PublicTransport {
+                start_station_id,
+                target_station_id,
+            } => write!(
+                f,
+                "public transport from station {} to station {}",
+                start_station_id, target_station_id
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CandidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.candidate, self.message)
+    }
+}
+
+impl RouteCriterion {
+    /// What searches at this time rank by unless told otherwise: the shortest duration when leaving
+    /// at a time, and the latest departure when arriving by it.
+    pub fn for_time(time: RouteTime) -> Self {
+        match time {
+            RouteTime::DepartAt(_) => RouteCriterion::Duration,
+            RouteTime::ArriveBy(_) => RouteCriterion::LatestDeparture,
+        }
+    }
+
+    fn value(&self, route: &Route) -> f64 {
+        match self {
+            RouteCriterion::Duration => route.total_duration(),
+            RouteCriterion::Transfers => route.transfers() as f64,
+            RouteCriterion::WalkingDistance => route.road_distance(),
+            RouteCriterion::LatestDeparture => {
+                route.departure().map_or(f64::INFINITY, |departure| {
+                    -(departure.timestamp_millis() as f64)
+                })
+            }
+            RouteCriterion::Pareto(Some(weights)) => weights.cost(&route.costs()),
+            RouteCriterion::Pareto(None) => route.total_duration(),
+        }
+    }
+}
+
+impl RouteCosts {
+    /// Whether these costs are no higher than the other ones anywhere and lower somewhere.
+    pub fn dominates(&self, other: &RouteCosts) -> bool {
+        let pairs = [
+            (self.duration, other.duration),
+            (self.transfers, other.transfers),
+            (self.active_distance, other.active_distance),
+            (self.waiting_time, other.waiting_time),
+        ];
+
+        pairs.iter().all(|(cost, other_cost)| cost <= other_cost)
+            && pairs.iter().any(|(cost, other_cost)| cost < other_cost)
+    }
+}
+
+impl RouteWeights {
+    pub fn cost(&self, costs: &RouteCosts) -> f64 {
+        self.duration * costs.duration
+            + self.transfers * costs.transfers
+            + self.active_distance * costs.active_distance
+            + self.waiting_time * costs.waiting_time
+    }
+}
 
+impl RouteSearchResult {
+    /// The first route, which is the best one once sorted.
+    pub fn best(&self) -> Option<&Route> {
+        self.routes.first()
+    }
+
+    /// Sorts the routes by the criterion, best first. A Pareto criterion removes the dominated routes.
+    pub fn rank(&mut self, criterion: RouteCriterion) {
+        if let RouteCriterion::Pareto(_) = criterion {
+            self.routes = pareto_front(&self.routes);
+        }
+
+        self.routes.sort_by(|route, other_route| {
+            criterion
+                .value(route)
+                .total_cmp(&criterion.value(other_route))
+                .then(
+                    route
+                        .total_duration()
+                        .total_cmp(&other_route.total_duration()),
+                )
+        });
+    }
+}
+
+/// The routes whose costs aren't dominated by those of another route.
+pub fn pareto_front(routes: &[Route]) -> Vec<Route> {
+    let costs: Vec<RouteCosts> = routes.iter().map(Route::costs).collect();
+
+    routes
+        .iter()
+        .zip(costs.iter())
+        .filter(|(_, route_costs)| {
+            !costs
+                .iter()
+                .any(|other_costs| other_costs.dominates(route_costs))
+        })
+        .map(|(route, _)| route.clone())
+        .collect()
+}
+
+/// Searches public transport between the stations near the start and target, and the direct route,
+/// leaving at or arriving by the time. Every route found is returned, sorted by the criterion, or by
+/// `RouteCriterion::for_time` without one.
 pub fn search_routes(
     osm_data: &OSMData,
     transport_mode: &TransportMode,
-    time: DateTime<Utc>,
+    time: RouteTime,
     starting_node_id: u64,
     target_node_id: u64,
-    minimum_distance_to_station: f64,
-) -> Route {
-    let public_transport_routes = public_transport(
+    transit_options: &TransitOptions,
+    criterion: Option<RouteCriterion>,
+) -> RouteSearchResult {
+    let mut search_result = public_transport(
         osm_data,
-        &transport_mode,
+        transport_mode,
         time,
-        minimum_distance_to_station,
+        transit_options,
         starting_node_id,
         target_node_id,
     );
 
-    let direct_route = direct_route(
+    match direct_route(
         osm_data,
-        &transport_mode,
+        transport_mode,
         time,
         starting_node_id,
         target_node_id,
-    );
-
-    let mut routes: Vec<Route> = Vec::new();
-
-    if let Ok(public_transport_routes) = public_transport_routes {
-        routes.extend(public_transport_routes);
-    }
-
-    if let Ok(direct_route) = direct_route {
-        routes.push(direct_route)
-    }
-
-    routes.sort_by_key(|route| f64_to_u64(route.total_duration()));
-
-    let mut shortest_route: Route = Route::default();
-
-    for (index, route) in routes.iter().enumerate() {
-        if index == 0 {
-            println!("Best route:");
-            shortest_route = route.to_owned();
-        } else {
-            println!("Alternative route {}:", index);
-        }
-        route.print_route(osm_data);
-    }
-
-    if routes.len() == 0 {
-        warn!("No routes found.")
+    ) {
+        Ok(route) => search_result.routes.push(route),
+        Err(error) => search_result.errors.push(CandidateError {
+            candidate: RouteCandidate::Direct,
+            message: error.to_string(),
+        }),
     }
 
-    shortest_route
+    search_result.rank(criterion.unwrap_or(RouteCriterion::for_time(time)));
+    search_result
 }
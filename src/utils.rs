@@ -1,10 +1,16 @@
 pub mod attributes;
+pub mod cache_format;
 pub mod cli_interface;
 pub mod coordinate_files;
 pub mod distance_utilities;
 pub mod file_handling;
 pub mod filtering_utilities;
+pub mod flatgeobuf;
 pub mod geographic_areas;
 pub mod hashmap_creation;
 pub mod node_examples;
+pub mod poly_files;
+pub mod polylines;
+pub mod string_table;
 pub mod tag_name_utilities;
+pub mod time_utilities;
@@ -8,8 +8,9 @@ pub fn read_attributes(attributes: Attributes) -> HashMap<String, String> {
     for attribute in attributes {
         let attribute = attribute.expect("Failed to read attribute");
 
-        let value = std::str::from_utf8(&attribute.value)
-            .expect("Failed to convert into utf8")
+        let value = attribute
+            .unescape_value()
+            .expect("Failed to unescape value")
             .to_string();
 
         let key = std::str::from_utf8(attribute.key.as_ref())
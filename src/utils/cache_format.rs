@@ -0,0 +1,385 @@
+use core::fmt;
+use std::error::Error;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crc32fast::Hasher;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::data_handling::BuildSettings;
+
+/// First bytes of every versioned map cache.
+pub const CACHE_MAGIC: [u8; 8] = *b"OSMRUST\0";
+/// Bump whenever a serialized type (`Node`, `Way`, ...) changes, so that old caches are rejected
+/// instead of silently misread. Version 2 stores tags as IDs into the `Strings` section.
+pub const CACHE_FORMAT_VERSION: u32 = 2;
+/// Oldest version that can still be read: version 1 caches store tags as strings.
+pub const OLDEST_CACHE_FORMAT_VERSION: u32 = 1;
+
+/// Size of a section entry in the header: kind, offset, length and checksum.
+const SECTION_ENTRY_SIZE: u64 = 4 + 8 + 8 + 4;
+
+/// The fixed-size header at the start of a cache file:
+///
+/// ```text
+/// magic (8 bytes) | format version (u32) | build settings fingerprint (u32) | section count (u32)
+/// | sections: kind (u32), offset (u64), length (u64), CRC32 (u32) | header CRC32 (u32)
+/// ```
+///
+/// All integers are little-endian; each section is a bincode blob.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHeader {
+    pub format_version: u32,
+    /// `BuildSettings::fingerprint` of the settings the map was built with.
+    pub settings_fingerprint: u32,
+    pub sections: Vec<CacheSection>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheSection {
+    pub kind: SectionKind,
+    /// Bytes from the start of the file.
+    pub offset: u64,
+    pub length: u64,
+    pub checksum: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    BuildSettings,
+    Nodes,
+    Ways,
+    Relations,
+    Areas,
+    NodeSubsets,
+    /// The tag strings, indexed by the string IDs in the other sections.
+    Strings,
+    /// The `ReplicationState`; missing in caches written before it existed.
+    Replication,
+    /// The `MetadataStore`, if metadata was kept; missing in caches written before it existed.
+    Metadata,
+}
+
+#[derive(Debug)]
+pub enum CacheError {
+    Io(io::Error),
+    Serialization(bincode::Error),
+    /// The file does not start with the cache magic, e.g. because it predates versioned caches.
+    NotVersioned,
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+    HeaderChecksumMismatch,
+    SectionChecksumMismatch(SectionKind),
+    MissingSection(SectionKind),
+    /// The cache was built with other settings than expected.
+    BuildSettingsMismatch {
+        expected: u32,
+        found: u32,
+    },
+}
+
+impl SectionKind {
+    pub const ALL: [SectionKind; 9] = [
+        SectionKind::BuildSettings,
+        SectionKind::Nodes,
+        SectionKind::Ways,
+        SectionKind::Relations,
+        SectionKind::Areas,
+        SectionKind::NodeSubsets,
+        SectionKind::Strings,
+        SectionKind::Replication,
+        SectionKind::Metadata,
+    ];
+
+    fn id(&self) -> u32 {
+        match self {
+            SectionKind::BuildSettings => 1,
+            SectionKind::Nodes => 2,
+            SectionKind::Ways => 3,
+            SectionKind::Relations => 4,
+            SectionKind::Areas => 5,
+            SectionKind::NodeSubsets => 6,
+            SectionKind::Strings => 7,
+            SectionKind::Replication => 8,
+            SectionKind::Metadata => 9,
+        }
+    }
+
+    fn from_id(id: u32) -> Option<Self> {
+        SectionKind::ALL.into_iter().find(|kind| kind.id() == id)
+    }
+}
+
+impl BuildSettings {
+    /// CRC32 of the serialized settings: equal settings always give equal fingerprints.
+    pub fn fingerprint(&self) -> u32 {
+        let bytes = bincode::serialize(self).expect("Failed to serialize build settings");
+        crc32fast::hash(&bytes)
+    }
+}
+
+impl CacheHeader {
+    pub fn section(&self, kind: SectionKind) -> Result<&CacheSection, CacheError> {
+        self.sections
+            .iter()
+            .find(|section| section.kind == kind)
+            .ok_or(CacheError::MissingSection(kind))
+    }
+
+    fn size(section_count: usize) -> u64 {
+        8 + 4 + 4 + 4 + section_count as u64 * SECTION_ENTRY_SIZE + 4
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::size(self.sections.len()) as usize);
+
+        bytes.extend_from_slice(&CACHE_MAGIC);
+        bytes.extend_from_slice(&self.format_version.to_le_bytes());
+        bytes.extend_from_slice(&self.settings_fingerprint.to_le_bytes());
+        bytes.extend_from_slice(&(self.sections.len() as u32).to_le_bytes());
+
+        for section in self.sections.iter() {
+            bytes.extend_from_slice(&section.kind.id().to_le_bytes());
+            bytes.extend_from_slice(&section.offset.to_le_bytes());
+            bytes.extend_from_slice(&section.length.to_le_bytes());
+            bytes.extend_from_slice(&section.checksum.to_le_bytes());
+        }
+
+        let header_checksum = crc32fast::hash(&bytes);
+        bytes.extend_from_slice(&header_checksum.to_le_bytes());
+
+        bytes
+    }
+
+    /// Reads and checks the header: magic, version and header checksum.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, CacheError> {
+        let mut hasher = Hasher::new();
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != CACHE_MAGIC {
+            return Err(CacheError::NotVersioned);
+        }
+        hasher.update(&magic);
+
+        let format_version = read_u32(reader, &mut hasher)?;
+        if !(OLDEST_CACHE_FORMAT_VERSION..=CACHE_FORMAT_VERSION).contains(&format_version) {
+            return Err(CacheError::UnsupportedVersion {
+                found: format_version,
+                supported: CACHE_FORMAT_VERSION,
+            });
+        }
+
+        let settings_fingerprint = read_u32(reader, &mut hasher)?;
+        let section_count = read_u32(reader, &mut hasher)?;
+        let mut sections = Vec::new();
+
+        for _ in 0..section_count {
+            let kind_id = read_u32(reader, &mut hasher)?;
+            let offset = read_u64(reader, &mut hasher)?;
+            let length = read_u64(reader, &mut hasher)?;
+            let checksum = read_u32(reader, &mut hasher)?;
+
+            // Sections unknown to this version are skipped.
+            if let Some(kind) = SectionKind::from_id(kind_id) {
+                sections.push(CacheSection {
+                    kind,
+                    offset,
+                    length,
+                    checksum,
+                });
+            }
+        }
+
+        let expected_checksum = hasher.finalize();
+        let mut header_checksum = [0; 4];
+        reader.read_exact(&mut header_checksum)?;
+        if u32::from_le_bytes(header_checksum) != expected_checksum {
+            return Err(CacheError::HeaderChecksumMismatch);
+        }
+
+        Ok(CacheHeader {
+            format_version,
+            settings_fingerprint,
+            sections,
+        })
+    }
+}
+
+/// Writes sections one after another, then goes back to fill in the header.
+pub struct CacheWriter<W: Write + Seek> {
+    writer: W,
+    section_count: usize,
+    settings_fingerprint: u32,
+    sections: Vec<CacheSection>,
+}
+
+impl<W: Write + Seek> CacheWriter<W> {
+    /// Reserves space for a header with `section_count` sections.
+    pub fn new(
+        mut writer: W,
+        section_count: usize,
+        settings_fingerprint: u32,
+    ) -> Result<Self, CacheError> {
+        writer.write_all(&vec![0; CacheHeader::size(section_count) as usize])?;
+
+        Ok(CacheWriter {
+            writer,
+            section_count,
+            settings_fingerprint,
+            sections: Vec::with_capacity(section_count),
+        })
+    }
+
+    pub fn write_section<T: Serialize + ?Sized>(
+        &mut self,
+        kind: SectionKind,
+        value: &T,
+    ) -> Result<(), CacheError> {
+        assert!(
+            self.sections.len() < self.section_count,
+            "More sections written than reserved in the header"
+        );
+
+        let offset = self.writer.stream_position()?;
+
+        let mut checksum_writer = ChecksumWriter {
+            writer: &mut self.writer,
+            hasher: Hasher::new(),
+            length: 0,
+        };
+        bincode::serialize_into(&mut checksum_writer, value)?;
+
+        let (length, checksum) = (checksum_writer.length, checksum_writer.hasher.finalize());
+        self.sections.push(CacheSection {
+            kind,
+            offset,
+            length,
+            checksum,
+        });
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, CacheError> {
+        let header = CacheHeader {
+            format_version: CACHE_FORMAT_VERSION,
+            settings_fingerprint: self.settings_fingerprint,
+            sections: self.sections,
+        };
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header.to_bytes())?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Verifies the checksum of a section before deserializing it, so that corrupt length prefixes never
+/// reach bincode.
+pub fn read_section<R: Read + Seek, T: DeserializeOwned>(
+    reader: &mut R,
+    header: &CacheHeader,
+    kind: SectionKind,
+) -> Result<T, CacheError> {
+    let section = header.section(kind)?;
+
+    reader.seek(SeekFrom::Start(section.offset))?;
+    let mut hasher = Hasher::new();
+    let mut section_reader = reader.take(section.length);
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let bytes_read = section_reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    if section_reader.limit() > 0 || hasher.finalize() != section.checksum {
+        return Err(CacheError::SectionChecksumMismatch(kind));
+    }
+
+    reader.seek(SeekFrom::Start(section.offset))?;
+    Ok(bincode::deserialize_from(reader.take(section.length))?)
+}
+
+struct ChecksumWriter<'a, W: Write> {
+    writer: &'a mut W,
+    hasher: Hasher,
+    length: u64,
+}
+
+impl<W: Write> Write for ChecksumWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes_written = self.writer.write(buf)?;
+        self.hasher.update(&buf[..bytes_written]);
+        self.length += bytes_written as u64;
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R, hasher: &mut Hasher) -> Result<u32, CacheError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    hasher.update(&bytes);
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R, hasher: &mut Hasher) -> Result<u64, CacheError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    hasher.update(&bytes);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+impl From<io::Error> for CacheError {
+    fn from(error: io::Error) -> Self {
+        CacheError::Io(error)
+    }
+}
+
+impl From<bincode::Error> for CacheError {
+    fn from(error: bincode::Error) -> Self {
+        CacheError::Serialization(error)
+    }
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::Io(error) => write!(f, "I/O error: {}", error),
+            CacheError::Serialization(error) => write!(f, "serialization error: {}", error),
+            CacheError::NotVersioned => write!(
+                f,
+                "not a versioned map cache; caches from before format version 1 can be converted with migrate_cache"
+            ),
+            CacheError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "map cache has format version {}, but versions up to {} are supported; rebuild the cache from the source file",
+                found, supported
+            ),
+            CacheError::HeaderChecksumMismatch => write!(f, "map cache header is corrupt"),
+            CacheError::SectionChecksumMismatch(kind) => {
+                write!(f, "map cache section {:?} is corrupt", kind)
+            }
+            CacheError::MissingSection(kind) => {
+                write!(f, "map cache has no {:?} section", kind)
+            }
+            CacheError::BuildSettingsMismatch { expected, found } => write!(
+                f,
+                "map cache was built with other settings (fingerprint {:08x}, expected {:08x})",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for CacheError {}
@@ -2,12 +2,14 @@ use log::error;
 #[allow(unused)]
 use log::{info, warn};
 
-use crate::data_handling::OSMData;
-use crate::path_finding::nearest_road::find_closest_road;
-use crate::path_finding::path_finding::path_finding;
-use crate::path_finding::TransportMode;
-
-use std::process::Command;
+use crate::data_handling::{MappedMap, OSMData};
+use crate::path_finding::nearest_road::find_closest_road_coordinate;
+use crate::path_finding::path_finding::{path_finding_with_observer, path_finding_with_options};
+use crate::path_finding::{PathOptions, RoutingGraph, SearchTrace, TransportMode};
+
+use std::error::Error;
+use std::fs::create_dir_all;
+use std::path::Path;
 use std::time::Instant;
 
 pub fn get_input() -> String {
@@ -19,10 +21,41 @@ pub fn get_input() -> String {
 }
 
 pub fn launch_cli_interface(osm_data: &OSMData) {
+    run_cli_interface(
+        osm_data,
+        |node_id| {
+            let coordinate = osm_data.node_map.get(&node_id)?.coordinate;
+            Some(find_closest_road_coordinate(osm_data, coordinate))
+        },
+        |search_string| osm_data.search_landmarks(search_string),
+    );
+}
+
+/// Like `launch_cli_interface`, for a map written by `OSMData::save_mapped`. The map is memory-mapped
+/// instead of loaded, so queries start right away.
+pub fn launch_mapped_cli_interface(file_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mapped_map = MappedMap::open(file_path)?;
+
+    run_cli_interface(
+        &mapped_map,
+        |node_id| mapped_map.closest_road_node(mapped_map.coordinate(node_id)?),
+        |search_string| mapped_map.search_landmarks(&search_string),
+    );
+
+    Ok(())
+}
+
+/// The query loop, given the closest road node (and its distance) to a node, and a landmark search.
+fn run_cli_interface<G: RoutingGraph>(
+    graph: &G,
+    closest_road: impl Fn(u64) -> Option<(u64, f64)>,
+    search_landmarks: impl Fn(String),
+) {
     let mut start_node_id: u64 = 0;
     let mut target_node_id: u64 = 0;
 
     let transport_mode = TransportMode::Bike(20.0 / 3.6);
+    let mut write_trace = false;
 
     loop {
         info!(
@@ -31,6 +64,7 @@ pub fn launch_cli_interface(osm_data: &OSMData) {
 
         Enter 'start' or 'target' to specify path locations.
         Enter 'path' to search for a path between the locations.
+        Enter 'trace' to toggle writing the search trace to results/pathing.
         Enter 'exit' to cancel.
 
         Other inputs will search the database."
@@ -50,26 +84,25 @@ pub fn launch_cli_interface(osm_data: &OSMData) {
             };
 
             if let Some(node_id) = node_id_option {
-                if osm_data.node_map.contains_key(&node_id) {
-                    let closest_road_result = find_closest_road(osm_data, node_id, &transport_mode);
-
-                    if let Some(_) = node_id_option {
-                        match input_string.as_str() {
-                            "start" => {
-                                start_node_id = *closest_road_result.found_path.last().unwrap();
-                                let minimum_distance = closest_road_result.path_length;
-                                info!("Set start ID: road node {:.3}m away.", minimum_distance);
-                            }
-                            "target" => {
-                                target_node_id = *closest_road_result.found_path.last().unwrap();
-                                let minimum_distance = closest_road_result.path_length;
-                                info!("Set target ID: road node {:.3}m away.", minimum_distance);
-                            }
-                            _ => {
-                                error!("Unexpected input: {}", input_string);
-                            }
-                        };
-                    }
+                if graph.contains_node(node_id) {
+                    let Some((closest_node_id, minimum_distance)) = closest_road(node_id) else {
+                        error!("No road node found near node {}", node_id);
+                        continue;
+                    };
+
+                    match input_string.as_str() {
+                        "start" => {
+                            start_node_id = closest_node_id;
+                            info!("Set start ID: road node {:.3}m away.", minimum_distance);
+                        }
+                        "target" => {
+                            target_node_id = closest_node_id;
+                            info!("Set target ID: road node {:.3}m away.", minimum_distance);
+                        }
+                        _ => {
+                            error!("Unexpected input: {}", input_string);
+                        }
+                    };
                 } else {
                     error!("Node ID not found in dataset: {}", node_id);
                 }
@@ -77,6 +110,19 @@ pub fn launch_cli_interface(osm_data: &OSMData) {
             continue;
         }
 
+        if input_string == "trace" {
+            write_trace = !write_trace;
+            info!(
+                "Search traces are {}.",
+                if write_trace {
+                    "written"
+                } else {
+                    "not written"
+                }
+            );
+            continue;
+        }
+
         if input_string == "path".to_string() {
             if start_node_id == 0 || target_node_id == 0 {
                 warn!(
@@ -87,25 +133,56 @@ pub fn launch_cli_interface(osm_data: &OSMData) {
             }
 
             let start_time = Instant::now();
-            let path_result =
-                path_finding(&osm_data, start_node_id, target_node_id, &transport_mode);
+            let mut search_trace = SearchTrace::new();
+            let path_result = if write_trace {
+                path_finding_with_observer(
+                    graph,
+                    start_node_id,
+                    target_node_id,
+                    &transport_mode,
+                    &PathOptions::default(),
+                    &mut search_trace,
+                )
+            } else {
+                path_finding_with_options(
+                    graph,
+                    start_node_id,
+                    target_node_id,
+                    &transport_mode,
+                    &PathOptions::default(),
+                )
+            };
 
-            if let Some(path) = path_result {
-                info!(
+            match path_result {
+                Ok(path) => info!(
                     "Succeeded. Found path is {:.1}m ({:.1} km, {} nodes) long. Took {:.3}s",
                     path.path_length,
                     path.path_length / 1000.,
                     path.found_path.len(),
                     start_time.elapsed().as_secs_f64()
-                );
-                Command::new("python")
-                    .arg("scripts/found_path_plotly.py")
-                    .output()
-                    .expect("Failed to execute Python script");
-            } else {
-                info!(
-                    "Failed to find path. Took {:.3}s",
+                ),
+                Err(error) => info!(
+                    "Failed to find path: {}. Took {:.3}s",
+                    error,
                     start_time.elapsed().as_secs_f64()
+                ),
+            }
+
+            if write_trace {
+                // Used by scripts/search_visualization.py.
+                let trace_directory = Path::new("results/pathing");
+                create_dir_all(trace_directory)
+                    .expect("Failed to create results/pathing directory.");
+                search_trace
+                    .write_csv(graph, &trace_directory.join("search_coordinates.csv"))
+                    .expect("Failed to write search trace");
+                search_trace
+                    .write_geojson(graph, &trace_directory.join("search_trace.geojson"))
+                    .expect("Failed to write search trace");
+                info!(
+                    "Wrote the search trace ({} nodes) to {}",
+                    search_trace.settled_nodes.len(),
+                    trace_directory.display()
                 );
             }
             continue;
@@ -115,6 +192,6 @@ pub fn launch_cli_interface(osm_data: &OSMData) {
             break;
         }
 
-        osm_data.search_landmarks(input_string);
+        search_landmarks(input_string);
     }
 }
@@ -1,49 +1,254 @@
-use bincode;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
+use std::io::{self, BufRead, BufReader, BufWriter};
 use std::path::Path;
 
-use crate::data_handling::{FilterSet, NodeSubset, OSMData};
-use crate::osm_parsing::{Node, Way};
+use geo::Point;
+#[allow(unused)]
+use log::{info, warn};
+use serde::Deserialize;
 
-pub fn save_hashmaps(
+use crate::data_handling::{
+    BuildSettings, ClipArea, FilterSet, NodeSubset, OSMData, ReplicationState,
+};
+use crate::osm_parsing::{MapContents, MetadataStore, Node, Way};
+use crate::utils::cache_format::{read_section, CacheError, CacheHeader, CacheWriter, SectionKind};
+use crate::utils::string_table::{read_symbols, write_symbols};
+
+/// Everything stored in a map cache.
+#[derive(Debug)]
+pub struct CacheContents {
+    pub map_contents: MapContents,
+    pub build_settings: BuildSettings,
+    pub replication_state: Option<ReplicationState>,
+    pub metadata: Option<MetadataStore>,
+}
+
+/// Writes a versioned map cache; see `CacheHeader` for the layout.
+pub fn save_hashmaps(file_path: &Path, osm_data: &OSMData) -> Result<(), CacheError> {
+    let writer = BufWriter::new(File::create(file_path)?);
+    let mut cache_writer = CacheWriter::new(
+        writer,
+        SectionKind::ALL.len(),
+        osm_data.build_settings.fingerprint(),
+    )?;
+
+    // Tags are written as string IDs; the strings follow once all of them have been seen.
+    let (result, strings) = write_symbols(|| -> Result<(), CacheError> {
+        cache_writer.write_section(SectionKind::BuildSettings, &osm_data.build_settings)?;
+        cache_writer.write_section(SectionKind::Nodes, &osm_data.node_map)?;
+        cache_writer.write_section(SectionKind::Ways, &osm_data.way_map)?;
+        cache_writer.write_section(SectionKind::Relations, &osm_data.relation_map)?;
+        cache_writer.write_section(SectionKind::Areas, &osm_data.area_map)?;
+        cache_writer.write_section(SectionKind::NodeSubsets, &osm_data.node_subsets)
+    });
+    result?;
+
+    cache_writer.write_section(SectionKind::Strings, &strings)?;
+    cache_writer.write_section(SectionKind::Replication, &osm_data.replication_state)?;
+    cache_writer.write_section(SectionKind::Metadata, &osm_data.metadata)?;
+    cache_writer.finish()?;
+
+    Ok(())
+}
+
+pub fn load_hashmaps(file_path: &Path) -> Result<CacheContents, CacheError> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    let header = CacheHeader::read_from(&mut reader)?;
+
+    read_sections(&mut reader, &header)
+}
+
+/// Loads a cache only if it was built with the expected settings, checked before reading any section.
+pub fn load_hashmaps_with_settings(
     file_path: &Path,
-    node_map: &HashMap<u64, Node>,
-    way_map: &HashMap<u64, Way>,
-    node_subsets: &Vec<NodeSubset>,
-) {
-    let file = File::create(file_path).expect("Failed to create file");
-    let mut writer = BufWriter::new(file);
+    expected_settings: &BuildSettings,
+) -> Result<CacheContents, CacheError> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    let header = CacheHeader::read_from(&mut reader)?;
+
+    if header.settings_fingerprint != expected_settings.fingerprint() {
+        return Err(CacheError::BuildSettingsMismatch {
+            expected: expected_settings.fingerprint(),
+            found: header.settings_fingerprint,
+        });
+    }
+
+    read_sections(&mut reader, &header)
+}
+
+/// Reads only the header, e.g. to check the format version or fingerprint of a cache.
+pub fn read_cache_header(file_path: &Path) -> Result<CacheHeader, CacheError> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    CacheHeader::read_from(&mut reader)
+}
+
+/// Converts a cache written before format version 1 (back-to-back bincode blobs without a header) to the
+/// current format. The settings such a cache was built with are unknown, so they are stored as empty.
+pub fn migrate_cache(source_path: &Path, destination_path: &Path) -> Result<(), CacheError> {
+    let (node_map, way_map, relation_map, area_map, node_subsets) =
+        load_legacy_hashmaps(source_path)?;
+
+    let osm_data = OSMData {
+        node_map,
+        way_map,
+        relation_map,
+        area_map,
+        node_subsets,
+        ..Default::default()
+    };
+    save_hashmaps(destination_path, &osm_data)?;
 
-    bincode::serialize_into(&mut writer, node_map).expect("Failed to serialize node_map");
-    bincode::serialize_into(&mut writer, way_map).expect("Failed to serialize way_map");
-    bincode::serialize_into(&mut writer, node_subsets).expect("Failed to serialize node_subsets");
-    writer.flush().expect("Failed to flush writer");
+    info!(
+        "Migrated {} to format version {}",
+        source_path.display(),
+        crate::utils::cache_format::CACHE_FORMAT_VERSION
+    );
+
+    Ok(())
+}
+
+fn read_sections(
+    reader: &mut BufReader<File>,
+    header: &CacheHeader,
+) -> Result<CacheContents, CacheError> {
+    // Version 1 caches store tags as strings.
+    let strings: Option<Vec<String>> = match header.format_version {
+        1 => None,
+        _ => Some(read_section(reader, header, SectionKind::Strings)?),
+    };
+
+    // Sections added after format version 2 may be missing.
+    let replication_state = match header.section(SectionKind::Replication) {
+        Ok(_) => read_section(reader, header, SectionKind::Replication)?,
+        Err(_) => None,
+    };
+    let metadata = match header.section(SectionKind::Metadata) {
+        Ok(_) => read_section(reader, header, SectionKind::Metadata)?,
+        Err(_) => None,
+    };
+
+    let mut read_contents = || -> Result<(MapContents, BuildSettings), CacheError> {
+        let build_settings = read_section(reader, header, SectionKind::BuildSettings)?;
+        let node_map = read_section(reader, header, SectionKind::Nodes)?;
+        let way_map = read_section(reader, header, SectionKind::Ways)?;
+        let relation_map = read_section(reader, header, SectionKind::Relations)?;
+        let area_map = read_section(reader, header, SectionKind::Areas)?;
+        let node_subsets = read_section(reader, header, SectionKind::NodeSubsets)?;
+
+        Ok((
+            (node_map, way_map, relation_map, area_map, node_subsets),
+            build_settings,
+        ))
+    };
+
+    let (map_contents, build_settings) = match strings {
+        Some(strings) => read_symbols(&strings, read_contents)?,
+        None => read_contents()?,
+    };
+
+    Ok(CacheContents {
+        map_contents,
+        build_settings,
+        replication_state,
+        metadata,
+    })
+}
+
+/// A node as cached before format version 1: string tags and no elevation.
+#[derive(Deserialize)]
+struct LegacyNode {
+    id: u64,
+    coordinate: Point,
+    tags: HashMap<String, String>,
+    ways: Vec<u64>,
+    nodes: Vec<u64>,
+}
+
+/// A way as cached before format version 1: string tags.
+#[derive(Deserialize)]
+struct LegacyWay {
+    id: u64,
+    node_ids: Vec<u64>,
+    tags: HashMap<String, String>,
 }
 
-pub fn load_hashmaps(file_path: &Path) -> (HashMap<u64, Node>, HashMap<u64, Way>, Vec<NodeSubset>) {
-    let file = File::open(file_path).expect("Failed to open file");
-    let mut reader = BufReader::new(file);
+/// Node, way and subset blobs, the only contents of caches from before format version 1.
+fn load_legacy_hashmaps(file_path: &Path) -> Result<MapContents, CacheError> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+
+    let legacy_node_map: HashMap<u64, LegacyNode> = bincode::deserialize_from(&mut reader)?;
+    let legacy_way_map: HashMap<u64, LegacyWay> = bincode::deserialize_from(&mut reader)?;
+    let node_subsets: Vec<NodeSubset> = bincode::deserialize_from(&mut reader)?;
+
+    if !reader.fill_buf()?.is_empty() {
+        return Err(CacheError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected data after the node subsets of a legacy cache",
+        )));
+    }
 
-    let node_map: HashMap<u64, Node> =
-        bincode::deserialize_from(&mut reader).expect("Failed to deserialize node_map");
-    let way_map: HashMap<u64, Way> =
-        bincode::deserialize_from(&mut reader).expect("Failed to deserialize way_map");
-    let node_subsets: Vec<NodeSubset> =
-        bincode::deserialize_from(&mut reader).expect("Failed to deserialize node_subsets");
+    let node_map = legacy_node_map
+        .into_iter()
+        .map(|(node_id, node)| {
+            (
+                node_id,
+                Node {
+                    id: node.id,
+                    coordinate: node.coordinate,
+                    tags: node.tags.iter().collect(),
+                    ways: node.ways,
+                    nodes: node.nodes,
+                    elevation: None,
+                },
+            )
+        })
+        .collect();
 
-    (node_map, way_map, node_subsets)
+    let way_map = legacy_way_map
+        .into_iter()
+        .map(|(way_id, way)| {
+            (
+                way_id,
+                Way {
+                    id: way.id,
+                    node_ids: way.node_ids,
+                    tags: way.tags.iter().collect(),
+                },
+            )
+        })
+        .collect();
+
+    Ok((
+        node_map,
+        way_map,
+        HashMap::new(),
+        HashMap::new(),
+        node_subsets,
+    ))
 }
 
+/// Parses, optionally clips, filters and saves a map. Road fragments smaller than `minimum_component_size`
+/// nodes are dropped.
 pub fn reload_and_save(
     file_path: &Path,
     destination_path: &Path,
     filters: Vec<FilterSet>,
+    clip_area: Option<&ClipArea>,
+    minimum_component_size: Option<usize>,
 ) -> OSMData {
     let mut osm_data = OSMData::new(file_path);
+
+    if let Some(clip_area) = clip_area {
+        osm_data.clip(clip_area);
+    }
+
     osm_data.filter(filters);
 
+    if let Some(minimum_component_size) = minimum_component_size {
+        osm_data.prune_small_components(minimum_component_size);
+    }
+
     osm_data.save_hashmaps(destination_path);
 
     osm_data
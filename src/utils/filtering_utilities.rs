@@ -29,6 +29,19 @@ pub fn filter_highways() -> FilterSet {
     }
 }
 
+/// The regular road network plus dedicated cycle paths, for bike routing over fietspaden.
+pub fn filter_cycling_network() -> FilterSet {
+    let mut filter_set = filter_highways();
+
+    filter_set.filter_values.extend([
+        "cycleway".to_string(),
+        "track".to_string(),
+        "service".to_string(),
+    ]);
+
+    filter_set
+}
+
 pub fn filter_stations() -> FilterSet {
     let filter_key = "public_transport".to_string();
     let filter_values = HashSet::from(["station".to_string()]);
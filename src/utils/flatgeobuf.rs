@@ -0,0 +1,369 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use geo::{BoundingRect, Coord, Geometry, LineString, Polygon, Rect};
+#[allow(unused)]
+use log::{info, warn};
+
+use crate::data_handling::MapFeature;
+
+/// "fgb", major version 3, "fgb", patch version 0.
+const MAGIC_BYTES: [u8; 8] = [0x66, 0x67, 0x62, 0x03, 0x66, 0x67, 0x62, 0x00];
+
+/// Geometry types as numbered in the FlatGeobuf schema.
+const UNKNOWN: u8 = 0;
+const POINT: u8 = 1;
+const LINE_STRING: u8 = 2;
+const POLYGON: u8 = 3;
+const MULTI_POLYGON: u8 = 6;
+
+/// Column types as numbered in the FlatGeobuf schema.
+const COLUMN_TYPE_ULONG: u8 = 8;
+const COLUMN_TYPE_STRING: u8 = 11;
+
+/// Writes the features as a FlatGeobuf file in WGS 84, without a spatial index. The IDs go in an `id`
+/// column and every tag key gets a string column, left empty for features without the tag.
+pub fn write_flatgeobuf(features: &[MapFeature], file_path: &Path) -> Result<(), Box<dyn Error>> {
+    let keys: Vec<&str> = features
+        .iter()
+        .flat_map(|feature| feature.tags.iter().map(|(key, _)| key))
+        .collect::<BTreeSet<&str>>()
+        .into_iter()
+        .collect();
+
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    writer.write_all(&MAGIC_BYTES)?;
+
+    let header = finish(&header(features, &keys));
+    writer.write_all(&(header.len() as u32).to_le_bytes())?;
+    writer.write_all(&header)?;
+
+    for feature in features.iter() {
+        let Some(geometry) = geometry_table(&feature.geometry) else {
+            warn!("Skipping feature {} with unsupported geometry", feature.id);
+            continue;
+        };
+
+        let mut properties = Vec::new();
+        properties.extend_from_slice(&0u16.to_le_bytes());
+        properties.extend_from_slice(&feature.id.to_le_bytes());
+        for (key, value) in feature.tags.iter() {
+            // Column 0 is the ID.
+            let column_index = keys.binary_search(&key).unwrap() as u16 + 1;
+            properties.extend_from_slice(&column_index.to_le_bytes());
+            properties.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            properties.extend_from_slice(value.as_bytes());
+        }
+
+        let feature = finish(&Table::new(vec![
+            (0, Field::Table(geometry)),
+            (1, Field::Bytes(properties)),
+        ]));
+        writer.write_all(&(feature.len() as u32).to_le_bytes())?;
+        writer.write_all(&feature)?;
+    }
+
+    writer.flush()?;
+
+    info!(
+        "Wrote {} features to {}",
+        features.len(),
+        file_path.display()
+    );
+    Ok(())
+}
+
+fn header(features: &[MapFeature], keys: &[&str]) -> Table {
+    let geometry_types: BTreeSet<u8> = features
+        .iter()
+        .map(|feature| geometry_type(&feature.geometry))
+        .collect();
+    let geometry_type = match geometry_types.len() {
+        1 => *geometry_types.first().unwrap(),
+        _ => UNKNOWN,
+    };
+
+    let columns: Vec<Table> = std::iter::once(column("id", COLUMN_TYPE_ULONG))
+        .chain(keys.iter().map(|key| column(key, COLUMN_TYPE_STRING)))
+        .collect();
+
+    let crs = Table::new(vec![
+        (0, Field::String("EPSG".to_string())),
+        (1, Field::Int(4326)),
+    ]);
+
+    let mut fields = vec![
+        (0, Field::String("osm-rust".to_string())),
+        (2, Field::UByte(geometry_type)),
+        (7, Field::Tables(columns)),
+        (8, Field::ULong(features.len() as u64)),
+        // The default node size of 16 would promise a spatial index.
+        (9, Field::UShort(0)),
+        (10, Field::Table(crs)),
+    ];
+
+    let bounding_rect = features
+        .iter()
+        .filter_map(|feature| feature.geometry.bounding_rect())
+        .reduce(|left, right| {
+            Rect::new(
+                Coord {
+                    x: left.min().x.min(right.min().x),
+                    y: left.min().y.min(right.min().y),
+                },
+                Coord {
+                    x: left.max().x.max(right.max().x),
+                    y: left.max().y.max(right.max().y),
+                },
+            )
+        });
+    if let Some(bounding_rect) = bounding_rect {
+        fields.push((
+            1,
+            Field::Doubles(vec![
+                bounding_rect.min().x,
+                bounding_rect.min().y,
+                bounding_rect.max().x,
+                bounding_rect.max().y,
+            ]),
+        ));
+    }
+
+    Table::new(fields)
+}
+
+fn column(name: &str, column_type: u8) -> Table {
+    Table::new(vec![
+        (0, Field::String(name.to_string())),
+        (1, Field::UByte(column_type)),
+    ])
+}
+
+fn geometry_type(geometry: &Geometry) -> u8 {
+    match geometry {
+        Geometry::Point(_) => POINT,
+        Geometry::LineString(_) => LINE_STRING,
+        Geometry::Polygon(_) => POLYGON,
+        Geometry::MultiPolygon(_) => MULTI_POLYGON,
+        _ => UNKNOWN,
+    }
+}
+
+fn geometry_table(geometry: &Geometry) -> Option<Table> {
+    let table = match geometry {
+        Geometry::Point(point) => Table::new(vec![
+            (1, Field::Doubles(vec![point.x(), point.y()])),
+            (6, Field::UByte(POINT)),
+        ]),
+        Geometry::LineString(line_string) => Table::new(vec![
+            (1, Field::Doubles(coordinates(line_string))),
+            (6, Field::UByte(LINE_STRING)),
+        ]),
+        Geometry::Polygon(polygon) => polygon_table(polygon),
+        Geometry::MultiPolygon(multi_polygon) => Table::new(vec![
+            (6, Field::UByte(MULTI_POLYGON)),
+            (
+                7,
+                Field::Tables(multi_polygon.iter().map(polygon_table).collect()),
+            ),
+        ]),
+        _ => return None,
+    };
+
+    Some(table)
+}
+
+/// The rings of a polygon in one coordinate list, with the index after each ring in `ends`.
+fn polygon_table(polygon: &Polygon) -> Table {
+    let mut xy = Vec::new();
+    let mut ends = Vec::new();
+    for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+        xy.extend(coordinates(ring));
+        ends.push((xy.len() / 2) as u32);
+    }
+
+    Table::new(vec![
+        (0, Field::UInts(ends)),
+        (1, Field::Doubles(xy)),
+        (6, Field::UByte(POLYGON)),
+    ])
+}
+
+fn coordinates(line_string: &LineString) -> Vec<f64> {
+    line_string
+        .coords()
+        .flat_map(|coordinate| [coordinate.x, coordinate.y])
+        .collect()
+}
+
+/// A FlatBuffers table: fields by their index in the schema. Fields left out take their default value.
+struct Table {
+    fields: Vec<(u16, Field)>,
+}
+
+enum Field {
+    UByte(u8),
+    UShort(u16),
+    Int(i32),
+    ULong(u64),
+    String(String),
+    Bytes(Vec<u8>),
+    UInts(Vec<u32>),
+    Doubles(Vec<f64>),
+    Table(Table),
+    Tables(Vec<Table>),
+}
+
+impl Table {
+    fn new(fields: Vec<(u16, Field)>) -> Self {
+        Table { fields }
+    }
+}
+
+impl Field {
+    /// Size of the field inside its table; strings, vectors and tables are referred to by a 32-bit
+    /// offset.
+    fn inline_size(&self) -> usize {
+        match self {
+            Field::UByte(_) => 1,
+            Field::UShort(_) => 2,
+            Field::Int(_) => 4,
+            Field::ULong(_) => 8,
+            _ => 4,
+        }
+    }
+}
+
+/// Lays out a FlatBuffers buffer with the table as its root. Unlike the reference builders, this writes
+/// front to back: every vtable precedes its table, and every table precedes what it refers to.
+fn finish(root: &Table) -> Vec<u8> {
+    let mut buffer = vec![0; 4];
+    let root_position = write_table(&mut buffer, root);
+    patch_offset(&mut buffer, 0, root_position);
+    buffer
+}
+
+fn write_table(buffer: &mut Vec<u8>, table: &Table) -> usize {
+    // Larger fields first, so that aligning them wastes no space.
+    let mut fields: Vec<&(u16, Field)> = table.fields.iter().collect();
+    fields.sort_by_key(|(_, field)| std::cmp::Reverse(field.inline_size()));
+
+    // The table starts with the offset to its vtable.
+    let mut field_offsets = Vec::new();
+    let mut table_size: usize = 4;
+    for (_, field) in fields.iter() {
+        table_size = table_size.next_multiple_of(field.inline_size());
+        field_offsets.push(table_size);
+        table_size += field.inline_size();
+    }
+
+    let slot_count = table
+        .fields
+        .iter()
+        .map(|(index, _)| *index + 1)
+        .max()
+        .unwrap_or(0);
+    let mut slots = vec![0u16; slot_count as usize];
+    for ((index, _), offset) in fields.iter().zip(field_offsets.iter()) {
+        slots[*index as usize] = *offset as u16;
+    }
+
+    align(buffer, 2);
+    let vtable_position = buffer.len();
+    buffer.extend_from_slice(&(4 + 2 * slot_count).to_le_bytes());
+    buffer.extend_from_slice(&(table_size as u16).to_le_bytes());
+    for slot in slots {
+        buffer.extend_from_slice(&slot.to_le_bytes());
+    }
+
+    align(buffer, 8);
+    let table_position = buffer.len();
+    buffer.extend_from_slice(&((table_position - vtable_position) as i32).to_le_bytes());
+    buffer.resize(table_position + table_size, 0);
+
+    for ((_, field), offset) in fields.iter().zip(field_offsets.iter()) {
+        let position = table_position + offset;
+        let bytes = match field {
+            Field::UByte(value) => value.to_le_bytes().to_vec(),
+            Field::UShort(value) => value.to_le_bytes().to_vec(),
+            Field::Int(value) => value.to_le_bytes().to_vec(),
+            Field::ULong(value) => value.to_le_bytes().to_vec(),
+            _ => continue,
+        };
+        buffer[position..position + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    for ((_, field), offset) in fields.iter().zip(field_offsets.iter()) {
+        if let Some(child_position) = write_child(buffer, field) {
+            patch_offset(buffer, table_position + offset, child_position);
+        }
+    }
+
+    table_position
+}
+
+/// Writes what the field refers to, returning its position, or nothing for a scalar field.
+fn write_child(buffer: &mut Vec<u8>, field: &Field) -> Option<usize> {
+    let position = match field {
+        Field::UByte(_) | Field::UShort(_) | Field::Int(_) | Field::ULong(_) => return None,
+        Field::String(string) => {
+            let position = write_length(buffer, string.len(), 4);
+            buffer.extend_from_slice(string.as_bytes());
+            buffer.push(0);
+            position
+        }
+        Field::Bytes(bytes) => {
+            let position = write_length(buffer, bytes.len(), 4);
+            buffer.extend_from_slice(bytes);
+            position
+        }
+        Field::UInts(values) => {
+            let position = write_length(buffer, values.len(), 4);
+            for value in values.iter() {
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+            position
+        }
+        Field::Doubles(values) => {
+            let position = write_length(buffer, values.len(), 8);
+            for value in values.iter() {
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+            position
+        }
+        Field::Table(table) => write_table(buffer, table),
+        Field::Tables(tables) => {
+            let position = write_length(buffer, tables.len(), 4);
+            buffer.resize(position + 4 + 4 * tables.len(), 0);
+            for (index, table) in tables.iter().enumerate() {
+                let table_position = write_table(buffer, table);
+                patch_offset(buffer, position + 4 + 4 * index, table_position);
+            }
+            position
+        }
+    };
+
+    Some(position)
+}
+
+/// Writes the length of a vector so that its elements, which follow it, are aligned.
+fn write_length(buffer: &mut Vec<u8>, length: usize, element_alignment: usize) -> usize {
+    while !(buffer.len() + 4).is_multiple_of(element_alignment) {
+        buffer.push(0);
+    }
+    let position = buffer.len();
+    buffer.extend_from_slice(&(length as u32).to_le_bytes());
+    position
+}
+
+fn align(buffer: &mut Vec<u8>, alignment: usize) {
+    buffer.resize(buffer.len().next_multiple_of(alignment), 0);
+}
+
+/// Offsets point forward, relative to where they are stored.
+fn patch_offset(buffer: &mut [u8], position: usize, target: usize) {
+    buffer[position..position + 4].copy_from_slice(&((target - position) as u32).to_le_bytes());
+}
@@ -2,6 +2,7 @@ use geo::Point;
 use rand::rngs::SmallRng;
 use rand::Rng;
 
+#[derive(Debug, Clone)]
 pub struct GeographicArea {
     pub minimum_latitude: f64,
     pub maximum_latitude: f64,
@@ -36,4 +37,10 @@ impl GeographicArea {
 
         Point::new(longitude, latitude)
     }
+
+    /// Whether the coordinate lies inside the area, boundary included.
+    pub fn contains(&self, coordinate: &Point) -> bool {
+        (self.minimum_latitude..=self.maximum_latitude).contains(&coordinate.y())
+            && (self.minimum_longitude..=self.maximum_longitude).contains(&coordinate.x())
+    }
 }
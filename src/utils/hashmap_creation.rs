@@ -20,5 +20,5 @@ pub fn recreate_hashmap(file_path: &Path, destination_path: &Path) -> OSMData {
         amenities_filter,
         bus_stops_filter,
     ];
-    reload_and_save(file_path, destination_path, filters)
+    reload_and_save(file_path, destination_path, filters, None, None)
 }
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+
+/// Loads an Osmosis polygon filter file: a name line, then sections of `longitude latitude` lines that each
+/// end with `END`, and a final `END`. Sections whose name starts with `!` are holes in the preceding polygon.
+pub fn load_poly_file(file_path: &Path) -> Result<MultiPolygon, Box<dyn Error>> {
+    let contents = fs::read_to_string(file_path)?;
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    // The first line only names the polygon.
+    lines.next().ok_or("Empty polygon file")?;
+
+    let mut polygons: Vec<Polygon> = Vec::new();
+
+    loop {
+        let section_name = lines.next().ok_or("Polygon file ends without END")?;
+        if section_name == "END" {
+            break;
+        }
+
+        let mut coordinates: Vec<Coord> = Vec::new();
+        loop {
+            let line = lines
+                .next()
+                .ok_or_else(|| format!("Section {} ends without END", section_name))?;
+            if line == "END" {
+                break;
+            }
+
+            let mut values = line.split_whitespace().map(str::parse::<f64>);
+            match (values.next(), values.next()) {
+                (Some(Ok(longitude)), Some(Ok(latitude))) => coordinates.push(Coord {
+                    x: longitude,
+                    y: latitude,
+                }),
+                _ => return Err(format!("Invalid polygon coordinate: {}", line).into()),
+            }
+        }
+
+        let ring = LineString::from(coordinates);
+
+        if section_name.starts_with('!') {
+            let polygon = polygons
+                .last_mut()
+                .ok_or_else(|| format!("Hole {} without a polygon", section_name))?;
+            polygon.interiors_push(ring);
+        } else {
+            polygons.push(Polygon::new(ring, Vec::new()));
+        }
+    }
+
+    if polygons.is_empty() {
+        return Err("Polygon file contains no polygons".into());
+    }
+
+    Ok(MultiPolygon::new(polygons))
+}
@@ -0,0 +1,86 @@
+use geo::{Coord, LineString};
+
+/// Encodes coordinates in Google's encoded polyline format, e.g. with precision 5 for the Maps API and 6
+/// for OSRM and Valhalla.
+pub fn encode_polyline(line_string: &LineString, precision: u32) -> String {
+    let factor = 10f64.powi(precision as i32);
+    let mut encoded = String::new();
+    let (mut previous_latitude, mut previous_longitude) = (0, 0);
+
+    for coordinate in line_string.coords() {
+        let latitude = (coordinate.y * factor).round() as i64;
+        let longitude = (coordinate.x * factor).round() as i64;
+
+        encode_value(latitude - previous_latitude, &mut encoded);
+        encode_value(longitude - previous_longitude, &mut encoded);
+
+        previous_latitude = latitude;
+        previous_longitude = longitude;
+    }
+
+    encoded
+}
+
+/// Decodes an encoded polyline written with the given precision.
+pub fn decode_polyline(encoded: &str, precision: u32) -> Result<LineString, String> {
+    let factor = 10f64.powi(precision as i32);
+    let mut bytes = encoded.bytes();
+    let mut coordinates: Vec<Coord> = Vec::new();
+    let (mut latitude, mut longitude) = (0, 0);
+
+    while let Some(latitude_change) = decode_value(&mut bytes)? {
+        let longitude_change = decode_value(&mut bytes)?.ok_or("Polyline ends after a latitude")?;
+        latitude += latitude_change;
+        longitude += longitude_change;
+
+        coordinates.push(Coord {
+            x: longitude as f64 / factor,
+            y: latitude as f64 / factor,
+        });
+    }
+
+    Ok(LineString::from(coordinates))
+}
+
+/// Writes the zigzag-encoded value in chunks of five bits, lowest first, each offset by 63 to make it
+/// printable.
+fn encode_value(value: i64, encoded: &mut String) {
+    let mut value = if value < 0 { !(value << 1) } else { value << 1 } as u64;
+
+    while value >= 0x20 {
+        encoded.push((((value & 0x1f) | 0x20) as u8 + 63) as char);
+        value >>= 5;
+    }
+    encoded.push((value as u8 + 63) as char);
+}
+
+fn decode_value(bytes: &mut impl Iterator<Item = u8>) -> Result<Option<i64>, String> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+
+    loop {
+        let Some(byte) = bytes.next() else {
+            return match shift {
+                0 => Ok(None),
+                _ => Err("Polyline ends inside a value".to_string()),
+            };
+        };
+
+        if !(63..127).contains(&byte) || shift > 60 {
+            return Err(format!("Invalid polyline character {:?}", byte as char));
+        }
+
+        let chunk = (byte - 63) as u64;
+        value |= (chunk & 0x1f) << shift;
+        shift += 5;
+
+        if chunk < 0x20 {
+            let value = value as i64;
+            return Ok(Some(if value & 1 == 1 {
+                !(value >> 1)
+            } else {
+                value >> 1
+            }));
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// An interned string: the ID of its entry in the global string table. Equal strings always get the
+/// same symbol, so symbols can be compared instead of strings.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// The symbols of every string interned by this process, only locked to intern or to look up a string
+/// that the thread has not seen yet. Entries are never removed, so the strings are leaked and can be
+/// handed out as `&'static str`.
+#[derive(Default)]
+struct StringTable {
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+/// The first chunk of `STRINGS` holds this many strings, and every next chunk twice as many as the one
+/// before, so that 28 chunks cover every u32 symbol.
+const FIRST_CHUNK_SIZE: usize = 32;
+const CHUNK_COUNT: usize = 28;
+
+type StringChunk = Box<[OnceLock<&'static str>]>;
+
+/// A looked-up symbol, and the string count at the time.
+type CachedLookup = (Option<Symbol>, usize);
+
+/// The interned strings by symbol. Slots are filled once and chunks are never moved, so symbols are
+/// resolved without taking a lock.
+static STRINGS: [OnceLock<StringChunk>; CHUNK_COUNT] = [const { OnceLock::new() }; CHUNK_COUNT];
+
+/// Number of interned strings, which is also the next symbol.
+static STRING_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// How `Tags` are written by binary serializers on this thread. Outside a scope they are written as
+/// strings, which is valid in any process; the map cache opts into writing symbols and stores the
+/// strings they stand for in a section of their own.
+enum SymbolEncoding {
+    /// Symbols are written as IDs into `strings`, which collects the strings in order of first use.
+    Write {
+        ids: HashMap<Symbol, u32>,
+        strings: Vec<&'static str>,
+    },
+    /// IDs are read as the symbols at their index.
+    Read(Vec<Symbol>),
+}
+
+thread_local! {
+    static SYMBOL_ENCODING: RefCell<Option<SymbolEncoding>> = const { RefCell::new(None) };
+
+    /// Strings this thread looked up, with their symbol. A string that was not interned is stored with
+    /// the string count at the time, and looked up again once more strings have been interned.
+    static LOOKUP_CACHE: RefCell<HashMap<Box<str>, CachedLookup>> = RefCell::new(HashMap::new());
+}
+
+fn string_table() -> &'static Mutex<StringTable> {
+    static STRING_TABLE: OnceLock<Mutex<StringTable>> = OnceLock::new();
+    STRING_TABLE.get_or_init(|| Mutex::new(StringTable::default()))
+}
+
+/// Chunk and slot of a symbol in `STRINGS`.
+fn string_slot(index: usize) -> (usize, usize) {
+    let position = index + FIRST_CHUNK_SIZE;
+    let chunk = (position.ilog2() - FIRST_CHUNK_SIZE.ilog2()) as usize;
+    (chunk, position - (FIRST_CHUNK_SIZE << chunk))
+}
+
+impl Symbol {
+    pub fn intern(string: &str) -> Symbol {
+        let mut table = string_table().lock().unwrap();
+        if let Some(symbol) = table.symbols.get(string) {
+            return *symbol;
+        }
+
+        let index = STRING_COUNT.load(Ordering::Relaxed);
+        let symbol =
+            Symbol(u32::try_from(index).expect("String table holds more than 2^32 strings"));
+        let string: &'static str = Box::leak(string.into());
+
+        let (chunk, slot) = string_slot(index);
+        STRINGS[chunk].get_or_init(|| {
+            (0..FIRST_CHUNK_SIZE << chunk)
+                .map(|_| OnceLock::new())
+                .collect()
+        })[slot]
+            .set(string)
+            .expect("String slot filled twice");
+
+        table.symbols.insert(string, symbol);
+        STRING_COUNT.store(index + 1, Ordering::Release);
+        symbol
+    }
+
+    /// The symbol of the string if it was ever interned. A string that was not cannot occur in any tags.
+    pub fn lookup(string: &str) -> Option<Symbol> {
+        let string_count = STRING_COUNT.load(Ordering::Acquire);
+
+        let cached = LOOKUP_CACHE.with_borrow(|cache| match cache.get(string) {
+            Some((Some(symbol), _)) => Some(Some(*symbol)),
+            Some((None, cached_count)) if *cached_count == string_count => Some(None),
+            _ => None,
+        });
+        if let Some(symbol) = cached {
+            return symbol;
+        }
+
+        let symbol = string_table().lock().unwrap().symbols.get(string).copied();
+        LOOKUP_CACHE.with_borrow_mut(|cache| cache.insert(string.into(), (symbol, string_count)));
+        symbol
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        let (chunk, slot) = string_slot(self.0 as usize);
+        STRINGS[chunk]
+            .get()
+            .and_then(|strings| strings[slot].get())
+            .expect("Symbols are only created by interning")
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Number of strings interned so far.
+pub fn interned_string_count() -> usize {
+    STRING_COUNT.load(Ordering::Acquire)
+}
+
+/// Runs `write` with `Tags` serialized as symbol IDs, and returns the strings those IDs index.
+pub fn write_symbols<T>(write: impl FnOnce() -> T) -> (T, Vec<&'static str>) {
+    let previous = SYMBOL_ENCODING.replace(Some(SymbolEncoding::Write {
+        ids: HashMap::new(),
+        strings: Vec::new(),
+    }));
+    let result = write();
+    let encoding = SYMBOL_ENCODING.replace(previous);
+
+    match encoding {
+        Some(SymbolEncoding::Write { strings, .. }) => (result, strings),
+        _ => unreachable!("Symbol encoding changed while writing"),
+    }
+}
+
+/// Runs `read` with `Tags` deserialized from symbol IDs that index `strings`.
+pub fn read_symbols<T>(strings: &[String], read: impl FnOnce() -> T) -> T {
+    let symbols = strings
+        .iter()
+        .map(|string| Symbol::intern(string))
+        .collect();
+    let previous = SYMBOL_ENCODING.replace(Some(SymbolEncoding::Read(symbols)));
+    let result = read();
+    SYMBOL_ENCODING.set(previous);
+    result
+}
+
+/// The ID of a symbol inside `write_symbols`.
+pub(crate) fn encode_symbol(symbol: Symbol) -> u32 {
+    SYMBOL_ENCODING.with_borrow_mut(|encoding| match encoding {
+        Some(SymbolEncoding::Write { ids, strings }) => *ids.entry(symbol).or_insert_with(|| {
+            strings.push(symbol.as_str());
+            strings.len() as u32 - 1
+        }),
+        _ => panic!("Symbols can only be encoded inside write_symbols"),
+    })
+}
+
+/// Whether binary serializers on this thread write symbol IDs.
+pub(crate) fn is_writing_symbols() -> bool {
+    SYMBOL_ENCODING.with_borrow(|encoding| matches!(encoding, Some(SymbolEncoding::Write { .. })))
+}
+
+/// Whether binary deserializers on this thread read symbol IDs.
+pub(crate) fn is_reading_symbols() -> bool {
+    SYMBOL_ENCODING.with_borrow(|encoding| matches!(encoding, Some(SymbolEncoding::Read(_))))
+}
+
+/// The symbol an ID read inside `read_symbols` stands for.
+pub(crate) fn decode_symbol(id: u32) -> Option<Symbol> {
+    SYMBOL_ENCODING.with_borrow(|encoding| match encoding {
+        Some(SymbolEncoding::Read(symbols)) => symbols.get(id as usize).copied(),
+        _ => None,
+    })
+}
+
+/// The value of the first pair with this key.
+pub(crate) fn find_value(pairs: &[(Symbol, Symbol)], key: &str) -> Option<&'static str> {
+    let key = Symbol::lookup(key)?;
+
+    pairs
+        .iter()
+        .find(|(pair_key, _)| *pair_key == key)
+        .map(|(_, value)| value.as_str())
+}
@@ -0,0 +1,6 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Adds a (fractional) number of seconds to a timestamp, rounded to the millisecond.
+pub fn add_seconds(time: DateTime<Utc>, seconds: f64) -> DateTime<Utc> {
+    time + Duration::milliseconds((seconds * 1000.0).round() as i64)
+}
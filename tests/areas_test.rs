@@ -0,0 +1,59 @@
+use geo::{Contains, GeodesicDistance, Point};
+use osm_rust::{
+    data_handling::{
+        areas::{RELATION_AREA_NODE_OFFSET, WAY_AREA_NODE_OFFSET},
+        AreaSource,
+    },
+    path_finding::{nearest_road::find_closest_road, TransportMode},
+    public_transport::stations::find_nearby_stations,
+};
+
+mod common;
+use common::areas_network;
+
+#[test]
+fn closed_way_becomes_landmark() {
+    let osm_data = areas_network();
+    let station_node_id = WAY_AREA_NODE_OFFSET + 310;
+
+    let station = osm_data
+        .area(station_node_id)
+        .expect("Station area missing");
+    assert_eq!(station.source, AreaSource::Way(310));
+    assert!(station.geometry.contains(&station.representative_point));
+    assert!(
+        station
+            .centroid
+            .geodesic_distance(&Point::new(5.0820, 51.5611))
+            < 1.0
+    );
+
+    let station_node = &osm_data.node_map[&station_node_id];
+    assert_eq!(station_node.tags.get("name"), Some("Station Teststad"));
+
+    let stations = find_nearby_stations(&osm_data, &Point::new(5.0800, 51.5600), 1000.0);
+    assert_eq!(stations, vec![station_node_id]);
+
+    let road_result = find_closest_road(&osm_data, station_node_id, &TransportMode::Walk(1.4));
+    assert_eq!(road_result.found_path, vec![station_node_id, 2]);
+}
+
+#[test]
+fn multipolygon_relation_becomes_landmark() {
+    let osm_data = areas_network();
+    let school_node_id = RELATION_AREA_NODE_OFFSET + 410;
+
+    let school = osm_data.area(school_node_id).expect("School area missing");
+    assert_eq!(school.relation_id(), Some(410));
+    assert_eq!(school.geometry.0[0].interiors().len(), 1);
+
+    // The centroid of a ring-shaped area lies in its hole, the representative point does not.
+    assert!(!school.geometry.contains(&school.centroid));
+    assert!(school.geometry.contains(&school.representative_point));
+
+    assert!(osm_data
+        .node_subsets
+        .iter()
+        .any(|subset| subset.node_subset.contains(&school_node_id)));
+    assert!(!osm_data.node_map[&school_node_id].tags.contains_key("type"));
+}
@@ -0,0 +1,189 @@
+use geo::Point;
+use osm_rust::{
+    data_handling::OSMData,
+    utils::{
+        cache_format::{CacheError, SectionKind, CACHE_FORMAT_VERSION},
+        file_handling::{
+            load_hashmaps, load_hashmaps_with_settings, migrate_cache, read_cache_header,
+            save_hashmaps,
+        },
+        filtering_utilities::{filter_highways, filter_stations},
+    },
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+mod common;
+use common::{filtered_map, test_directory};
+
+/// A node as the baseline cache stored it: string tags and no elevation.
+#[derive(Serialize)]
+struct LegacyNode {
+    id: u64,
+    coordinate: Point,
+    tags: HashMap<String, String>,
+    ways: Vec<u64>,
+    nodes: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct LegacyWay {
+    id: u64,
+    node_ids: Vec<u64>,
+    tags: HashMap<String, String>,
+}
+
+fn string_tags<'a>(tags: impl Iterator<Item = (&'a str, &'a str)>) -> HashMap<String, String> {
+    tags.map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Writes the map in the layout used before caches had a header.
+fn write_legacy_cache(file_path: &Path, osm_data: &OSMData) {
+    let node_map: HashMap<u64, LegacyNode> = osm_data
+        .node_map
+        .values()
+        .map(|node| {
+            (
+                node.id,
+                LegacyNode {
+                    id: node.id,
+                    coordinate: node.coordinate,
+                    tags: string_tags(node.tags.iter()),
+                    ways: node.ways.clone(),
+                    nodes: node.nodes.clone(),
+                },
+            )
+        })
+        .collect();
+    let way_map: HashMap<u64, LegacyWay> = osm_data
+        .way_map
+        .values()
+        .map(|way| {
+            (
+                way.id,
+                LegacyWay {
+                    id: way.id,
+                    node_ids: way.node_ids.clone(),
+                    tags: string_tags(way.tags.iter()),
+                },
+            )
+        })
+        .collect();
+
+    let mut writer = BufWriter::new(fs::File::create(file_path).expect("Failed to create"));
+    bincode::serialize_into(&mut writer, &node_map).expect("Failed to serialize");
+    bincode::serialize_into(&mut writer, &way_map).expect("Failed to serialize");
+    bincode::serialize_into(&mut writer, &osm_data.node_subsets).expect("Failed to serialize");
+    writer.flush().expect("Failed to flush");
+}
+
+fn filtered_network() -> OSMData {
+    filtered_map(
+        "areas_network.osm",
+        vec![filter_highways(), filter_stations()],
+    )
+}
+
+#[test]
+fn round_trip_keeps_contents_and_settings() {
+    let osm_data = filtered_network();
+    let file_path = test_directory("cache_test_round_trip").join("map.hashmap");
+
+    osm_data.save_hashmaps(&file_path);
+
+    let header = read_cache_header(&file_path).expect("Failed to read header");
+    assert_eq!(header.format_version, CACHE_FORMAT_VERSION);
+    assert_eq!(
+        header.settings_fingerprint,
+        osm_data.build_settings.fingerprint()
+    );
+    assert!(header.section(SectionKind::Nodes).is_ok());
+
+    let loaded_data = OSMData::new(&file_path);
+    assert_eq!(loaded_data.node_map.len(), osm_data.node_map.len());
+    assert_eq!(loaded_data.way_map.len(), osm_data.way_map.len());
+    assert_eq!(loaded_data.area_map.len(), osm_data.area_map.len());
+    assert_eq!(loaded_data.build_settings, osm_data.build_settings);
+    assert_eq!(loaded_data.build_settings.filters.len(), 2);
+
+    let other_data = filtered_map("areas_network.osm", vec![filter_highways()]);
+    assert!(matches!(
+        load_hashmaps_with_settings(&file_path, &other_data.build_settings),
+        Err(CacheError::BuildSettingsMismatch { .. })
+    ));
+    assert!(load_hashmaps_with_settings(&file_path, &osm_data.build_settings).is_ok());
+}
+
+#[test]
+fn corruption_and_old_versions_are_detected() {
+    let osm_data = filtered_network();
+    let directory = test_directory("cache_test_corruption");
+    let file_path = directory.join("map.hashmap");
+    save_hashmaps(&file_path, &osm_data).expect("Failed to save cache");
+
+    let bytes = fs::read(&file_path).expect("Failed to read cache");
+    let nodes_offset = read_cache_header(&file_path)
+        .expect("Failed to read header")
+        .section(SectionKind::Nodes)
+        .expect("No node section")
+        .offset as usize;
+
+    let mut corrupted_bytes = bytes.clone();
+    corrupted_bytes[nodes_offset + 20] ^= 0xFF;
+    let corrupted_path = directory.join("corrupted.hashmap");
+    fs::write(&corrupted_path, corrupted_bytes).expect("Failed to write cache");
+    assert!(matches!(
+        load_hashmaps(&corrupted_path),
+        Err(CacheError::SectionChecksumMismatch(SectionKind::Nodes))
+    ));
+
+    let mut future_bytes = bytes;
+    future_bytes[8..12].copy_from_slice(&(CACHE_FORMAT_VERSION + 1).to_le_bytes());
+    let future_path = directory.join("future.hashmap");
+    fs::write(&future_path, future_bytes).expect("Failed to write cache");
+    assert!(matches!(
+        load_hashmaps(&future_path),
+        Err(CacheError::UnsupportedVersion { .. })
+    ));
+}
+
+#[test]
+fn legacy_caches_can_be_migrated() {
+    let osm_data = filtered_network();
+    let directory = test_directory("cache_test_legacy");
+    let legacy_path = directory.join("legacy.hashmap");
+
+    write_legacy_cache(&legacy_path, &osm_data);
+
+    let error = load_hashmaps(&legacy_path).expect_err("Legacy cache should not load");
+    assert!(matches!(error, CacheError::NotVersioned));
+    assert!(error.to_string().contains("migrate_cache"));
+
+    let migrated_path = directory.join("migrated.hashmap");
+    migrate_cache(&legacy_path, &migrated_path).expect("Failed to migrate");
+
+    let migrated_data = OSMData::new(&migrated_path);
+    assert_eq!(migrated_data.node_map.len(), osm_data.node_map.len());
+    assert_eq!(migrated_data.way_map.len(), osm_data.way_map.len());
+    assert!(migrated_data.area_map.is_empty());
+
+    let (way_id, way) = osm_data.way_map.iter().next().expect("No ways");
+    assert_eq!(
+        migrated_data.way_map[way_id].tags.get("highway"),
+        way.tags.get("highway")
+    );
+    assert!(migrated_data
+        .node_map
+        .values()
+        .all(|node| node.elevation.is_none()));
+
+    // A truncated cache is an error rather than an empty map.
+    let bytes = fs::read(&legacy_path).expect("Failed to read cache");
+    let truncated_path = directory.join("truncated.hashmap");
+    fs::write(&truncated_path, &bytes[..bytes.len() - 4]).expect("Failed to write cache");
+    assert!(migrate_cache(&truncated_path, &directory.join("failed.hashmap")).is_err());
+}
@@ -0,0 +1,166 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use osm_rust::{
+    data_handling::{
+        validation::WAY_PIECE_OFFSET, ChangeSummary, FilterSubset, OSMData, ReplicationState,
+    },
+    path_finding::{path_finding::path_finding, TransportMode},
+    utils::filtering_utilities::{filter_amenities, filter_highways},
+};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+mod common;
+use common::{filtered_map, test_directory};
+
+fn filtered_network() -> OSMData {
+    filtered_map(
+        "island_network.osm",
+        vec![filter_highways(), filter_amenities()],
+    )
+}
+
+fn subset_contains(osm_data: &OSMData, filter_subset: &FilterSubset, node_id: u64) -> bool {
+    osm_data
+        .node_subsets
+        .iter()
+        .filter(|subset| subset.filter_subset == *filter_subset)
+        .any(|subset| subset.node_subset.contains(&node_id))
+}
+
+#[test]
+fn changes_update_ways_adjacency_and_subsets() {
+    let mut osm_data = filtered_network();
+    let change_summary = osm_data
+        .apply_change_file(Path::new("tests/data/island_changes.osc"))
+        .expect("Failed to apply changes");
+
+    assert_eq!(
+        change_summary,
+        ChangeSummary {
+            created: 3,
+            modified: 2,
+            deleted: 3,
+            ignored: 1,
+        }
+    );
+
+    // The new way pulled in its untagged node; the unused one was left out.
+    assert!(osm_data.way_map.contains_key(&104));
+    assert!(osm_data.node_map[&5].nodes.contains(&6));
+    assert_eq!(osm_data.node_map[&6].ways, vec![104]);
+    assert!(!osm_data.node_map.contains_key(&31));
+    assert!(subset_contains(&osm_data, &FilterSubset::Roads, 6));
+
+    assert_eq!(osm_data.node_map[&2].coordinate.y(), 51.5601);
+    assert_eq!(osm_data.way_map[&102].tags.get("oneway"), None);
+
+    assert!(!osm_data.way_map.contains_key(&103));
+    assert!(!osm_data.node_map.contains_key(&20));
+    assert!(!subset_contains(&osm_data, &FilterSubset::Roads, 21));
+
+    let amenity_subset = FilterSubset::Landmark("amenity".to_string());
+    assert!(subset_contains(&osm_data, &amenity_subset, 30));
+    assert_eq!(
+        osm_data.node_map[&30].tags.get("name"),
+        Some("De Regenboog")
+    );
+
+    // Way 102 is no longer one-way, and the new way extends the network.
+    let path = path_finding(&osm_data, 6, 1, &TransportMode::Car).expect("No path");
+    assert_eq!(path.found_path, vec![6, 5, 4, 3, 2, 1]);
+}
+
+#[test]
+fn moved_and_deleted_nodes_update_elevation_and_ways() {
+    let mut osm_data = filtered_network();
+    for node_id in [2, 4] {
+        osm_data.node_map.get_mut(&node_id).unwrap().elevation = Some(12.0);
+    }
+
+    let change_summary = osm_data
+        .apply_change_file(Path::new("tests/data/island_node_changes.osc"))
+        .expect("Failed to apply changes");
+    assert_eq!(
+        change_summary,
+        ChangeSummary {
+            modified: 2,
+            deleted: 1,
+            ..Default::default()
+        }
+    );
+
+    // Node 2 moved; node 4 was modified in place.
+    assert_eq!(osm_data.node_map[&2].elevation, None);
+    assert_eq!(osm_data.node_map[&4].elevation, Some(12.0));
+
+    // Way 100 (1-2-3) loses its end, and way 101 (3-4) is left with a single node.
+    assert_eq!(osm_data.way_map[&100].node_ids, vec![1, 2]);
+    assert!(!osm_data.way_map.contains_key(&101));
+    assert_eq!(osm_data.node_map[&2].nodes, vec![1]);
+    assert!(!osm_data.node_map[&4].ways.contains(&101));
+    assert!(osm_data
+        .way_map
+        .values()
+        .all(|way| !way.node_ids.contains(&3)));
+}
+
+#[test]
+fn deleting_a_node_splits_the_ways_through_it() {
+    let mut osm_data = filtered_network();
+    osm_data
+        .apply_change_file(Path::new("tests/data/split_way_changes.osc"))
+        .expect("Failed to apply changes");
+
+    // Way 100 (1-2-3-4-5) keeps both sides of node 3.
+    assert_eq!(osm_data.way_map[&100].node_ids, vec![1, 2]);
+    let piece = &osm_data.way_map[&WAY_PIECE_OFFSET];
+    assert_eq!(piece.node_ids, vec![4, 5]);
+    assert_eq!(piece.tags.get("highway"), Some("residential"));
+
+    assert!(osm_data.node_map[&4].ways.contains(&WAY_PIECE_OFFSET));
+    assert!(osm_data.node_map[&5].nodes.contains(&4));
+    assert_eq!(osm_data.node_map[&2].nodes, vec![1]);
+}
+
+#[test]
+fn replication_state_is_recorded_and_saved() {
+    let directory = test_directory("changes_test_replication");
+
+    let change_path = directory.join("4212.osc.gz");
+    let mut encoder = GzEncoder::new(File::create(&change_path).unwrap(), Compression::default());
+    encoder
+        .write_all(&fs::read("tests/data/island_changes.osc").unwrap())
+        .unwrap();
+    encoder.finish().unwrap();
+
+    let state_path = directory.join("4212.state.txt");
+    fs::write(
+        &state_path,
+        "#Sat Oct 19 20:21:02 UTC 2024\nsequenceNumber=4212\ntimestamp=2024-10-19T20\\:21\\:02Z\n",
+    )
+    .unwrap();
+
+    let mut osm_data = filtered_network();
+    let change_summary = osm_data
+        .apply_replication_diff(&change_path, &state_path)
+        .expect("Failed to apply diff");
+    assert_eq!(change_summary.created, 3);
+
+    let replication_state = ReplicationState::from_state_file(&state_path).unwrap();
+    assert_eq!(replication_state.sequence_number, 4212);
+    assert_eq!(osm_data.replication_state, Some(replication_state));
+
+    // Applying the same diff again does nothing.
+    let change_summary = osm_data
+        .apply_replication_diff(&change_path, &state_path)
+        .expect("Failed to apply diff");
+    assert_eq!(change_summary, ChangeSummary::default());
+
+    let file_path = directory.join("map.hashmap");
+    osm_data.save_hashmaps(&file_path);
+    let loaded_data = OSMData::new(&file_path);
+    assert_eq!(loaded_data.replication_state, osm_data.replication_state);
+    assert!(loaded_data.way_map.contains_key(&104));
+}
@@ -0,0 +1,87 @@
+use geo::Point;
+use osm_rust::{
+    data_handling::{validation::WAY_PIECE_OFFSET, ClipArea, OSMData},
+    utils::geographic_areas::GeographicArea,
+};
+use std::path::Path;
+
+#[test]
+fn bounding_box_keeps_boundary_edges() {
+    let mut osm_data = OSMData::new(Path::new("tests/data/island_network.osm"));
+    osm_data.clip(&ClipArea::BoundingBox(GeographicArea::new(
+        51.559, 51.561, 5.079, 5.083,
+    )));
+
+    assert_eq!(osm_data.way_map.len(), 1);
+    assert_eq!(osm_data.way_map[&100].node_ids, vec![1, 2, 3]);
+
+    let mut node_ids: Vec<u64> = osm_data.node_map.keys().copied().collect();
+    node_ids.sort();
+    assert_eq!(node_ids, vec![1, 2, 3]);
+    assert_eq!(osm_data.node_map[&3].nodes, vec![2]);
+}
+
+#[test]
+fn way_leaving_the_area_is_cut_into_inside_runs() {
+    let mut osm_data = OSMData::new(Path::new("tests/data/clip_network.osm"));
+    osm_data.clip(&ClipArea::BoundingBox(GeographicArea::new(
+        51.559, 51.561, 5.079, 5.091,
+    )));
+
+    assert_eq!(osm_data.way_map.len(), 2);
+    assert_eq!(osm_data.way_map[&100].node_ids, vec![1, 2, 3]);
+    assert_eq!(osm_data.way_map[&WAY_PIECE_OFFSET].node_ids, vec![5, 6, 7]);
+    assert!(!osm_data.node_map.contains_key(&4));
+
+    assert_eq!(osm_data.relation_map.len(), 1);
+    let members: Vec<u64> = osm_data.relation_map[&300]
+        .members
+        .iter()
+        .map(|member| member.member_id)
+        .collect();
+    assert_eq!(members, vec![100, WAY_PIECE_OFFSET]);
+}
+
+#[test]
+fn polygon_with_hole() {
+    let clip_area = ClipArea::from_poly_file(Path::new("tests/data/clip_area.poly"))
+        .expect("Failed to load polygon file");
+
+    assert!(clip_area.contains(&Point::new(5.0800, 51.5600)));
+    assert!(!clip_area.contains(&Point::new(5.0820, 51.5600)));
+    assert!(!clip_area.contains(&Point::new(5.0900, 51.5700)));
+
+    let mut osm_data = OSMData::new(Path::new("tests/data/island_network.osm"));
+    osm_data.clip(&clip_area);
+
+    assert!(osm_data.way_map.contains_key(&102));
+    assert!(!osm_data.way_map.contains_key(&103));
+    assert!(osm_data.node_map.contains_key(&5));
+    assert!(!osm_data.node_map.contains_key(&20));
+}
+
+#[test]
+fn clip_by_administrative_boundary() {
+    let mut osm_data = OSMData::new(Path::new("tests/data/boundary_network.osm"));
+
+    assert_eq!(osm_data.relation_map[&400].members.len(), 3);
+    assert_eq!(osm_data.administrative_boundaries(None, Some(8)).len(), 1);
+    assert!(osm_data.administrative_area("Teststad", Some(2)).is_err());
+
+    let clip_area = osm_data
+        .administrative_area("Teststad", Some(8))
+        .expect("Failed to assemble boundary");
+
+    let ClipArea::Polygon(multi_polygon) = &clip_area else {
+        panic!("Expected a polygon");
+    };
+    assert_eq!(multi_polygon.0.len(), 1);
+    assert_eq!(multi_polygon.0[0].interiors().len(), 1);
+
+    assert!(clip_area.contains(&Point::new(5.0800, 51.5600)));
+    assert!(!clip_area.contains(&Point::new(5.0820, 51.5600)));
+
+    osm_data.clip(&clip_area);
+    assert!(osm_data.way_map.contains_key(&102));
+    assert!(!osm_data.node_map.contains_key(&20));
+}
@@ -0,0 +1,55 @@
+//! Maps and scratch directories shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+
+use osm_rust::data_handling::{FilterSet, OSMData};
+use osm_rust::utils::filtering_utilities::{
+    filter_amenities, filter_cycling_network, filter_highways, filter_stations,
+};
+
+/// Loads a map from `tests/data` and filters it.
+pub fn filtered_map(file_name: &str, filters: Vec<FilterSet>) -> OSMData {
+    let mut osm_data = OSMData::new(&Path::new("tests/data").join(file_name));
+    osm_data.filter(filters);
+    osm_data
+}
+
+/// Roads, a station and amenities drawn as closed ways and multipolygons.
+pub fn areas_network() -> OSMData {
+    filtered_map(
+        "areas_network.osm",
+        vec![filter_highways(), filter_stations(), filter_amenities()],
+    )
+}
+
+/// Cycleways and roads with surfaces and infrastructure.
+pub fn cycling_network() -> OSMData {
+    filtered_map("cycling_network.osm", vec![filter_cycling_network()])
+}
+
+/// Roads with turns and a roundabout.
+pub fn instructions_network() -> OSMData {
+    filtered_map("instructions_network.osm", vec![filter_highways()])
+}
+
+/// A road network with a separate island of roads.
+pub fn island_network() -> OSMData {
+    filtered_map("island_network.osm", vec![filter_highways()])
+}
+
+/// Two villages with a station each, a long walk apart.
+pub fn transit_network() -> OSMData {
+    filtered_map(
+        "transit_network.osm",
+        vec![filter_highways(), filter_stations()],
+    )
+}
+
+/// A directory for the test's output under the system temporary directory, created if needed.
+pub fn test_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("osm_rust_{}", name));
+    create_dir_all(&directory).expect("Failed to create test directory");
+    directory
+}
@@ -0,0 +1,77 @@
+use geo::Point;
+use osm_rust::{
+    data_handling::OSMData,
+    path_finding::{
+        nearest_road::{find_closest_road_coordinate, find_closest_road_coordinate_with},
+        TransportMode,
+    },
+    utils::filtering_utilities::{filter_amenities, filter_highways},
+};
+
+mod common;
+use common::{filtered_map, island_network};
+
+#[test]
+fn island_is_separate_component() {
+    let osm_data = island_network();
+    let road_components = osm_data.road_components();
+
+    assert_eq!(road_components.component_count(), 2);
+    assert_eq!(road_components.component(1), road_components.component(5));
+    assert_ne!(road_components.component(1), road_components.component(20));
+    assert!(road_components.is_in_largest_component(3));
+    assert!(!road_components.is_in_largest_component(21));
+}
+
+#[test]
+fn one_way_street_splits_strong_components() {
+    let osm_data = island_network();
+
+    let car_components = osm_data.strongly_connected_components(&TransportMode::Car);
+    assert_ne!(car_components.component(4), car_components.component(5));
+    assert_eq!(car_components.component(1), car_components.component(4));
+
+    let walking_components = osm_data.strongly_connected_components(&TransportMode::Walk(1.4));
+    assert_eq!(
+        walking_components.component(4),
+        walking_components.component(5)
+    );
+}
+
+#[test]
+fn snapping_avoids_islands() {
+    let osm_data = island_network();
+    let coordinate = Point::new(5.0905, 51.5700);
+
+    let (node_id, _) = find_closest_road_coordinate(&osm_data, coordinate);
+    assert_ne!(node_id, 21);
+
+    let (node_id, _) = find_closest_road_coordinate_with(&osm_data, coordinate, false);
+    assert_eq!(node_id, 21);
+}
+
+#[test]
+fn pruning_removes_islands() {
+    let mut osm_data = filtered_map(
+        "island_network.osm",
+        vec![filter_highways(), filter_amenities()],
+    );
+    let in_a_subset = |osm_data: &OSMData| {
+        osm_data
+            .node_subsets
+            .iter()
+            .any(|subset| subset.node_subset.contains(&21))
+    };
+    assert!(in_a_subset(&osm_data));
+
+    let removed_nodes = osm_data.prune_small_components(3);
+    assert_eq!(removed_nodes, 2);
+
+    assert!(!osm_data.node_map.contains_key(&20));
+    assert!(!osm_data.way_map.contains_key(&103));
+    assert_eq!(osm_data.road_components().component_count(), 1);
+    assert_eq!(osm_data.node_map[&2].nodes.len(), 2);
+
+    // The school on the island is gone from its landmark subset too.
+    assert!(!in_a_subset(&osm_data));
+}
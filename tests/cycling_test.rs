@@ -0,0 +1,58 @@
+use osm_rust::path_finding::{
+    path_finding::path_finding_with_options, CyclingPreferences, PathFindingError, PathOptions,
+    TransportMode,
+};
+
+mod common;
+use common::cycling_network;
+
+#[test]
+fn cycling_prefers_cycleway() {
+    let osm_data = cycling_network();
+    let transport_mode = TransportMode::Bike(20.0 / 3.6);
+
+    let options = PathOptions {
+        cycling_preferences: CyclingPreferences::comfortable(),
+        ..PathOptions::default()
+    };
+
+    let path_result = path_finding_with_options(&osm_data, 1, 2, &transport_mode, &options)
+        .expect("Failed to find path");
+
+    assert!(
+        path_result.found_path.contains(&4),
+        "Expected the cycleway, got {:?}",
+        path_result.found_path
+    );
+}
+
+#[test]
+fn cycling_without_preferences_takes_shortest_road() {
+    let osm_data = cycling_network();
+    let transport_mode = TransportMode::Bike(20.0 / 3.6);
+
+    let path_result =
+        path_finding_with_options(&osm_data, 1, 2, &transport_mode, &PathOptions::default())
+            .expect("Failed to find path");
+
+    assert!(
+        path_result.found_path.contains(&3),
+        "Expected the provincial road, got {:?}",
+        path_result.found_path
+    );
+}
+
+#[test]
+fn non_positive_preference_factors_are_rejected() {
+    let osm_data = cycling_network();
+    let options = PathOptions {
+        cycling_preferences: CyclingPreferences {
+            cycleway_factor: 0.0,
+            ..CyclingPreferences::default()
+        },
+        ..PathOptions::default()
+    };
+
+    let result = path_finding_with_options(&osm_data, 1, 2, &TransportMode::Bike(5.0), &options);
+    assert!(matches!(result, Err(PathFindingError::InvalidOptions(_))));
+}
@@ -0,0 +1,125 @@
+use osm_rust::{
+    elevation::{geotiff::load_geotiff_tile, ElevationModel, ElevationTile},
+    path_finding::{path_finding::path_finding_with_options, PathOptions, TransportMode},
+};
+use std::fs::{self, File};
+use std::path::Path;
+use tiff::encoder::{colortype, TiffEncoder};
+
+mod common;
+use common::{cycling_network, test_directory};
+
+/// Writes a coarse 3x3 SRTM tile that slopes from 100m in the north down to 0m in the south.
+fn write_sloped_tile(directory: &Path) {
+    let rows: [i16; 3] = [100, 50, 0];
+
+    let bytes: Vec<u8> = rows
+        .iter()
+        .flat_map(|elevation| [*elevation; 3])
+        .flat_map(|elevation| elevation.to_be_bytes())
+        .collect();
+
+    fs::write(directory.join("N51E005.hgt"), bytes).expect("Failed to write tile");
+}
+
+#[test]
+fn single_pixel_geotiff_is_rejected() {
+    let file_path = test_directory("elevation_test_single_pixel").join("single_pixel.tif");
+
+    let file = File::create(&file_path).expect("Failed to create GeoTIFF");
+    TiffEncoder::new(file)
+        .expect("Failed to start GeoTIFF")
+        .write_image::<colortype::GrayI16>(1, 1, &[42])
+        .expect("Failed to write GeoTIFF");
+
+    let error = load_geotiff_tile(&file_path).expect_err("Loaded a single pixel GeoTIFF");
+    assert!(error.to_string().contains("too small"), "{}", error);
+}
+
+#[test]
+fn elevation_ascent_and_descent() {
+    let directory = test_directory("elevation_test");
+    write_sloped_tile(&directory);
+
+    let elevation_model = ElevationModel::from_directory(&directory).expect("Failed to load tiles");
+
+    let mut osm_data = cycling_network();
+
+    let missing_nodes = osm_data.attach_elevations(&elevation_model);
+    assert_eq!(missing_nodes, 0);
+
+    let start_elevation = osm_data.node_map[&1].elevation.unwrap();
+    assert!((start_elevation - 55.0).abs() < 1e-6, "{}", start_elevation);
+
+    // The tile slopes north to south only, so the eastward path stays level.
+    let path_result = path_finding_with_options(
+        &osm_data,
+        1,
+        2,
+        &TransportMode::Walk(5.0 / 3.6),
+        &elevation_options(),
+    )
+    .expect("Failed to find path");
+    assert!(path_result.total_ascent < 1e-6);
+}
+
+fn elevation_options() -> PathOptions {
+    PathOptions {
+        use_elevation: true,
+        ..PathOptions::default()
+    }
+}
+
+/// A fine tile over the cycling network that rises 10m every 0.001 degrees to the east, a grade of
+/// about 14%.
+fn steep_model() -> ElevationModel {
+    let rows = 4;
+    let columns = 13;
+
+    ElevationModel {
+        tiles: vec![ElevationTile {
+            north_latitude: 51.552,
+            west_longitude: 5.079,
+            latitude_step: 0.001,
+            longitude_step: 0.001,
+            rows,
+            columns,
+            samples: (0..rows)
+                .flat_map(|_| (0..columns).map(|column| column as f32 * 10.0))
+                .collect(),
+        }],
+    }
+}
+
+#[test]
+fn slopes_change_walking_and_cycling_times() {
+    let mut osm_data = cycling_network();
+    assert_eq!(osm_data.attach_elevations(&steep_model()), 0);
+
+    for transport_mode in [
+        TransportMode::Walk(5.0 / 3.6),
+        TransportMode::Bike(20.0 / 3.6),
+    ] {
+        let flat_result =
+            path_finding_with_options(&osm_data, 1, 2, &transport_mode, &PathOptions::default())
+                .expect("Failed to find path");
+        let uphill_result =
+            path_finding_with_options(&osm_data, 1, 2, &transport_mode, &elevation_options())
+                .expect("Failed to find path");
+
+        // From 10m up to 110m.
+        assert!(
+            (uphill_result.total_ascent - 100.0).abs() < 1.0,
+            "{}",
+            uphill_result.total_ascent
+        );
+        assert!(uphill_result.total_descent < 1e-6);
+        assert!(
+            uphill_result.path_time > flat_result.path_time * 1.2,
+            "{:?}: {} uphill, {} flat",
+            transport_mode,
+            uphill_result.path_time,
+            flat_result.path_time
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use geo::Geometry;
+use osm_rust::data_handling::{FeatureSelection, FilterSubset};
+use serde_json::Value;
+use std::fs::{read, read_to_string};
+use std::path::PathBuf;
+
+mod common;
+use common::{areas_network, test_directory};
+
+fn export_path(file_name: &str) -> PathBuf {
+    test_directory("feature_export_test").join(file_name)
+}
+
+#[test]
+fn subset_nodes_become_points_with_tags() {
+    let osm_data = areas_network();
+    let features = osm_data.features(&FeatureSelection::Subset(FilterSubset::Landmark(
+        "amenity".to_string(),
+    )));
+
+    assert_eq!(features.len(), 1);
+    assert!(matches!(features[0].geometry, Geometry::Point(_)));
+    assert_eq!(features[0].tags.get("amenity"), Some("school"));
+}
+
+#[test]
+fn areas_are_exported_as_geojson_multipolygons() {
+    let osm_data = areas_network();
+    let file_path = export_path("areas.geojson");
+    osm_data
+        .export_features(&FeatureSelection::Areas, &file_path)
+        .expect("Failed to export features");
+
+    let feature_collection: Value =
+        serde_json::from_str(&read_to_string(&file_path).unwrap()).unwrap();
+    let features = feature_collection["features"].as_array().unwrap();
+    assert_eq!(features.len(), 2);
+
+    let school = features
+        .iter()
+        .find(|feature| feature["id"] == 410)
+        .expect("School missing");
+    assert_eq!(school["geometry"]["type"], "MultiPolygon");
+    // The courtyard is a hole in the school.
+    assert_eq!(
+        school["geometry"]["coordinates"][0]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    assert_eq!(school["properties"]["name"], "Testschool");
+}
+
+fn read_u32(buffer: &[u8], position: usize) -> usize {
+    u32::from_le_bytes(buffer[position..position + 4].try_into().unwrap()) as usize
+}
+
+/// Position of a table field in a FlatBuffers buffer, if the field is set.
+fn field(buffer: &[u8], table_position: usize, index: usize) -> Option<usize> {
+    let vtable_position = table_position
+        - i32::from_le_bytes(
+            buffer[table_position..table_position + 4]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+    let vtable_size = u16::from_le_bytes(
+        buffer[vtable_position..vtable_position + 2]
+            .try_into()
+            .unwrap(),
+    ) as usize;
+    let slot = vtable_position + 4 + 2 * index;
+    if slot >= vtable_position + vtable_size {
+        return None;
+    }
+
+    match u16::from_le_bytes(buffer[slot..slot + 2].try_into().unwrap()) {
+        0 => None,
+        offset => Some(table_position + offset as usize),
+    }
+}
+
+fn follow(buffer: &[u8], position: usize) -> usize {
+    position + read_u32(buffer, position)
+}
+
+#[test]
+fn ways_are_exported_as_flatgeobuf_line_strings() {
+    let osm_data = areas_network();
+    let file_path = export_path("ways.fgb");
+    osm_data
+        .export_features(&FeatureSelection::Ways, &file_path)
+        .expect("Failed to export features");
+
+    let file = read(&file_path).unwrap();
+    assert_eq!(&file[..8], b"fgb\x03fgb\x00");
+
+    let header_size = read_u32(&file, 8);
+    let header = &file[12..12 + header_size];
+    let header_table = follow(header, 0);
+
+    let features_count_position = field(header, header_table, 8).unwrap();
+    let features_count = u64::from_le_bytes(
+        header[features_count_position..features_count_position + 8]
+            .try_into()
+            .unwrap(),
+    );
+    assert_eq!(features_count, osm_data.way_map.len() as u64);
+    assert_eq!(header[field(header, header_table, 2).unwrap()], 2);
+
+    // Features are sorted by ID, so the first one is way 100.
+    let feature_start = 12 + header_size;
+    let feature_size = read_u32(&file, feature_start);
+    let feature = &file[feature_start + 4..feature_start + 4 + feature_size];
+    let feature_table = follow(feature, 0);
+    let geometry_table = follow(feature, field(feature, feature_table, 0).unwrap());
+    let xy_vector = follow(feature, field(feature, geometry_table, 1).unwrap());
+
+    let xy: Vec<f64> = (0..read_u32(feature, xy_vector))
+        .map(|index| {
+            let position = xy_vector + 4 + 8 * index;
+            f64::from_le_bytes(feature[position..position + 8].try_into().unwrap())
+        })
+        .collect();
+    let expected_xy: Vec<f64> = osm_data.way_map[&100]
+        .node_ids
+        .iter()
+        .flat_map(|node_id| {
+            let coordinate = osm_data.node_map[node_id].coordinate;
+            [coordinate.x(), coordinate.y()]
+        })
+        .collect();
+    assert_eq!(xy, expected_xy);
+
+    let properties = follow(feature, field(feature, feature_table, 1).unwrap());
+    let id_position = properties + 4 + 2;
+    assert_eq!(
+        u64::from_le_bytes(feature[id_position..id_position + 8].try_into().unwrap()),
+        100
+    );
+}
+
+#[test]
+fn unsupported_extensions_are_an_error() {
+    let osm_data = areas_network();
+    let result = osm_data.export_features(&FeatureSelection::Ways, &export_path("ways.txt"));
+    assert!(result.is_err());
+}
@@ -0,0 +1,41 @@
+use osm_rust::path_finding::{
+    instructions::render_instructions, path_finding::path_finding, Maneuver, TransportMode,
+};
+
+mod common;
+use common::instructions_network;
+
+#[test]
+fn turn_and_roundabout_instructions() {
+    let osm_data = instructions_network();
+
+    let path_result =
+        path_finding(&osm_data, 10, 18, &TransportMode::Car).expect("Failed to find path");
+    assert_eq!(path_result.found_path, vec![10, 11, 13, 14, 15, 18]);
+
+    let instructions = path_result.instructions(&osm_data);
+    let maneuvers: Vec<Maneuver> = instructions
+        .iter()
+        .map(|instruction| instruction.maneuver)
+        .collect();
+
+    assert_eq!(
+        maneuvers,
+        vec![
+            Maneuver::Depart,
+            Maneuver::Left,
+            Maneuver::Roundabout(2),
+            Maneuver::Arrive
+        ],
+        "{}",
+        render_instructions(&instructions)
+    );
+    assert_eq!(instructions[1].street_name.as_deref(), Some("Kerkstraat"));
+    assert_eq!(instructions[2].street_name.as_deref(), Some("Noordweg"));
+
+    let total_distance: f64 = instructions
+        .iter()
+        .map(|instruction| instruction.distance)
+        .sum();
+    assert!((total_distance - path_result.path_length).abs() < 1e-6);
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+use osm_rust::{
+    data_handling::OSMData,
+    path_finding::{path_finding::path_finding, TransportMode},
+    public_transport::{DepartureData, PublicTransportResult},
+    route_manager::{Itinerary, Route, RouteComponent, ITINERARY_SCHEMA_VERSION},
+};
+use serde_json::Value;
+
+mod common;
+use common::instructions_network;
+
+fn departure_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 10, 21, 8, 0, 0).unwrap()
+}
+
+/// A walk to the station followed by a train that leaves `train_departure` seconds after departing.
+fn network_and_route(train_departure: i64) -> (OSMData, Route) {
+    let osm_data = instructions_network();
+
+    let path_result =
+        path_finding(&osm_data, 10, 18, &TransportMode::Walk(1.5)).expect("Failed to find path");
+    let departure = DepartureData {
+        time: departure_time() + Duration::seconds(train_departure),
+        duration: 1800.0,
+        direction: "Noordstad".to_string(),
+        transfers: 1,
+        intermediate_stations: vec!["Tussenstad".to_string()],
+    };
+    let train = PublicTransportResult::new(18, 12, &departure, departure_time());
+
+    let mut route = Route::new(vec![
+        RouteComponent::Path(path_result),
+        RouteComponent::PublicTransport(train),
+    ]);
+    route.schedule(departure_time());
+    (osm_data, route)
+}
+
+#[test]
+fn route_round_trips_through_json() {
+    let (_, route) = network_and_route(1200);
+
+    let json = serde_json::to_string(&route).expect("Failed to serialize");
+    let value: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["components"][0]["kind"], "path");
+    assert_eq!(value["components"][1]["kind"], "public_transport");
+    assert_eq!(value["components"][1]["direction"], "Noordstad");
+
+    assert_eq!(serde_json::from_str::<Route>(&json).unwrap(), route);
+}
+
+#[test]
+fn itinerary_has_absolute_times_modes_and_geometry() {
+    let (osm_data, route) = network_and_route(1200);
+    let itinerary = route.itinerary(&osm_data, &TransportMode::Walk(1.5));
+
+    assert_eq!(itinerary.schema_version, ITINERARY_SCHEMA_VERSION);
+    assert_eq!(itinerary.legs.len(), 2);
+
+    let walk = &itinerary.legs[0];
+    assert_eq!(walk.mode, "walk");
+    assert_eq!(walk.component.departure(), departure_time());
+    assert_eq!(walk.geometry.len(), 6);
+    assert_eq!(walk.geometry[0], [5.08, 51.56]);
+
+    // The train leaves on time after the wait and arrives at the end of the itinerary.
+    let train = &itinerary.legs[1];
+    assert_eq!(train.mode, "train");
+    assert_eq!(
+        (train.component.departure() - departure_time()).num_seconds(),
+        1200
+    );
+    assert!(train.component.departure() > walk.component.arrival());
+    assert_eq!(
+        (train.component.arrival() - train.component.departure()).num_seconds(),
+        1800
+    );
+    assert_eq!(itinerary.arrival, train.component.arrival());
+    assert!((itinerary.duration - route.total_duration()).abs() < 0.01);
+
+    let json = serde_json::to_value(&itinerary).unwrap();
+    assert_eq!(json["legs"][1]["kind"], "public_transport");
+    assert_eq!(json["legs"][1]["transfers"], 1);
+    assert_eq!(json["departure"], "2024-10-21T08:00:00Z");
+    assert_eq!(json["legs"][1]["departure"], "2024-10-21T08:20:00Z");
+    assert_eq!(
+        serde_json::from_value::<Itinerary>(json).unwrap(),
+        itinerary
+    );
+}
+
+#[test]
+fn timeline_shows_local_clock_times() {
+    let (_, route) = network_and_route(1200);
+    assert!(route.is_feasible());
+
+    let amsterdam = FixedOffset::east_opt(2 * 3600).unwrap();
+    assert_eq!(
+        route.timeline(&amsterdam),
+        "depart 10:00, train 10:20–10:50, arrive 10:50"
+    );
+}
+
+#[test]
+fn missed_connection_is_not_feasible() {
+    // The train leaves before the station can be reached on foot.
+    let (_, route) = network_and_route(0);
+    assert!(!route.is_feasible());
+
+    let RouteComponent::PublicTransport(train) = &route.components[1] else {
+        panic!("Expected the train second");
+    };
+    assert!(train.waiting_time < 0.0);
+}
@@ -0,0 +1,119 @@
+use geo::Point;
+use osm_rust::{
+    data_handling::{areas::WAY_AREA_NODE_OFFSET, FilterSubset, MappedMap},
+    path_finding::{
+        nearest_road::find_closest_road_coordinate, path_finding::path_finding, RoutingGraph,
+        TransportMode, WayTags,
+    },
+    utils::filtering_utilities::{filter_highways, filter_stations},
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod common;
+use common::{areas_network, filtered_map, instructions_network, test_directory};
+
+fn mapped_file(name: &str) -> PathBuf {
+    test_directory("mapped_map_test").join(name)
+}
+
+#[test]
+fn mapped_map_routes_like_the_loaded_map() {
+    let osm_data = filtered_map(
+        "instructions_network.osm",
+        vec![filter_highways(), filter_stations()],
+    );
+
+    let file_path = mapped_file("map.graph");
+
+    osm_data.save_mapped(&file_path);
+    let mapped_map = MappedMap::open(&file_path).expect("Failed to open mapped map");
+
+    assert_eq!(mapped_map.node_count(), osm_data.node_map.len());
+    assert_eq!(mapped_map.way_count(), osm_data.way_map.len());
+
+    for transport_mode in [TransportMode::Car, TransportMode::Walk(1.4)] {
+        let loaded_path = path_finding(&osm_data, 10, 18, &transport_mode).expect("No path");
+        let mapped_path = path_finding(&mapped_map, 10, 18, &transport_mode).expect("No path");
+
+        assert_eq!(mapped_path.found_path, loaded_path.found_path);
+        assert!((mapped_path.path_length - loaded_path.path_length).abs() < 1e-6);
+        assert!((mapped_path.path_time - loaded_path.path_time).abs() < 1e-6);
+    }
+
+    let way = mapped_map
+        .connecting_way(10, 11)
+        .expect("No connecting way");
+    assert_eq!(way.way_id(), 200);
+    assert_eq!(way.tag("name"), Some("Hoofdstraat"));
+    assert_eq!(mapped_map.way(201).unwrap().tag("name"), Some("Kerkstraat"));
+    assert!(mapped_map.way(999).is_none());
+
+    let coordinate = Point::new(5.0801, 51.5601);
+    let (node_id, distance) = mapped_map.closest_road_node(coordinate).unwrap();
+    assert_eq!(
+        (node_id, distance),
+        find_closest_road_coordinate(&osm_data, coordinate)
+    );
+}
+
+#[test]
+fn landmark_subsets_are_kept() {
+    let osm_data = areas_network();
+
+    let file_path = mapped_file("stations.graph");
+    osm_data.save_mapped(&file_path);
+    let mapped_map = MappedMap::open(&file_path).expect("Failed to open mapped map");
+
+    let mut station_ids: Vec<u64> = mapped_map
+        .subset_nodes(&FilterSubset::Landmark("stations".to_string()))
+        .collect();
+    station_ids.sort();
+    let station_node_id = WAY_AREA_NODE_OFFSET + 310;
+    assert_eq!(station_ids, vec![station_node_id]);
+    assert_eq!(
+        mapped_map.node_tag(station_node_id, "name"),
+        Some("Station Teststad")
+    );
+    assert!(mapped_map.subset_nodes(&FilterSubset::Roads).count() > 0);
+}
+
+#[test]
+fn other_files_are_rejected() {
+    assert!(MappedMap::open(Path::new("tests/data/instructions_network.osm")).is_err());
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    let osm_data = instructions_network();
+    let file_path = mapped_file("malformed.graph");
+    osm_data.save_mapped(&file_path);
+    let bytes = fs::read(&file_path).expect("Failed to read mapped map");
+
+    // The offset of the first section, pointing past the end of the file.
+    let mut truncated_bytes = bytes.clone();
+    truncated_bytes[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+    let truncated_path = mapped_file("truncated.graph");
+    fs::write(&truncated_path, truncated_bytes).expect("Failed to write mapped map");
+    assert!(MappedMap::open(&truncated_path).is_err());
+
+    // An edge target out of range, with a matching checksum.
+    let entry = 16 + 6 * 24;
+    let offset = u64::from_le_bytes(bytes[entry..entry + 8].try_into().unwrap()) as usize;
+    let length = u64::from_le_bytes(bytes[entry + 8..entry + 16].try_into().unwrap()) as usize;
+    let mut out_of_range_bytes = bytes.clone();
+    out_of_range_bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let checksum = crc32fast::hash(&out_of_range_bytes[offset..offset + length]);
+    out_of_range_bytes[entry + 16..entry + 20].copy_from_slice(&checksum.to_le_bytes());
+    let out_of_range_path = mapped_file("out_of_range.graph");
+    fs::write(&out_of_range_path, out_of_range_bytes).expect("Failed to write mapped map");
+    let error = MappedMap::open(&out_of_range_path).expect_err("Index out of range was accepted");
+    assert!(error.to_string().contains("EdgeTargets"));
+
+    // The last byte of the string bytes.
+    let mut corrupted_bytes = bytes;
+    *corrupted_bytes.last_mut().unwrap() ^= 0xFF;
+    let corrupted_path = mapped_file("corrupted.graph");
+    fs::write(&corrupted_path, corrupted_bytes).expect("Failed to write mapped map");
+    assert!(MappedMap::open(&corrupted_path).is_err());
+}
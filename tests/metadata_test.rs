@@ -0,0 +1,64 @@
+use chrono::{TimeZone, Utc};
+use osm_rust::{
+    data_handling::{ClipArea, OSMData},
+    osm_parsing::ParseOptions,
+    utils::{filtering_utilities::filter_highways, geographic_areas::GeographicArea},
+};
+use std::path::Path;
+
+mod common;
+use common::test_directory;
+
+fn network_with_metadata() -> OSMData {
+    let options = ParseOptions {
+        keep_metadata: true,
+    };
+    let mut osm_data =
+        OSMData::new_with_options(Path::new("tests/data/metadata_network.osm"), &options);
+    osm_data.filter(vec![filter_highways()]);
+    osm_data
+}
+
+#[test]
+fn metadata_is_only_kept_when_requested() {
+    let osm_data = OSMData::new(Path::new("tests/data/metadata_network.osm"));
+    assert!(osm_data.metadata.is_none());
+    assert!(osm_data.way_metadata(100).is_none());
+
+    let osm_data = network_with_metadata();
+    let metadata = osm_data.way_metadata(100).expect("No metadata");
+    assert_eq!(metadata.version, Some(4));
+    assert_eq!(metadata.changeset, Some(1003));
+    assert_eq!(metadata.uid, Some(11));
+    assert_eq!(metadata.user.as_deref(), Some("anna"));
+    assert_eq!(
+        metadata.timestamp,
+        Some(Utc.with_ymd_and_hms(2024, 9, 20, 14, 15, 0).unwrap())
+    );
+
+    // Elements exported without metadata have none.
+    assert!(osm_data.way_metadata(103).is_none());
+}
+
+#[test]
+fn edits_since_a_date_in_an_area() {
+    let osm_data = network_with_metadata();
+    let since = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+    assert_eq!(osm_data.ways_edited_since(since, None), vec![100, 102]);
+    assert_eq!(osm_data.nodes_edited_since(since, None), vec![3]);
+
+    // Only way 100 has a node west of 5.083.
+    let area = ClipArea::BoundingBox(GeographicArea::new(51.55, 51.57, 5.07, 5.083));
+    assert_eq!(osm_data.ways_edited_since(since, Some(&area)), vec![100]);
+
+    let file_path = test_directory("metadata_test").join("map.hashmap");
+    osm_data.save_hashmaps(&file_path);
+
+    let options = ParseOptions {
+        keep_metadata: true,
+    };
+    let loaded_data = OSMData::new_with_options(&file_path, &options);
+    assert_eq!(loaded_data.ways_edited_since(since, None), vec![100, 102]);
+    assert!(OSMData::new(&file_path).metadata.is_none());
+}
@@ -0,0 +1,371 @@
+use flate2::read::ZlibDecoder;
+use osm_rust::{
+    data_handling::{validation::WAY_PIECE_OFFSET, ClipArea, OSMData},
+    osm_parsing::{
+        osm_parsing::parse_xml, MemberType, ParseOptions, Relation, RelationMember, Tags,
+    },
+    utils::geographic_areas::GeographicArea,
+};
+use std::collections::HashMap;
+use std::fs::read;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+mod common;
+use common::test_directory;
+
+fn export_path(file_name: &str) -> PathBuf {
+    test_directory("osm_export_test").join(file_name)
+}
+
+fn member(member_type: MemberType, member_id: u64) -> RelationMember {
+    RelationMember {
+        member_type,
+        member_id,
+        role: String::new(),
+    }
+}
+
+/// The boundary network with a member that is not in the map, a relation without any member in the map,
+/// and a relation whose only member is that relation.
+fn boundary_network() -> OSMData {
+    let mut osm_data = OSMData::new(Path::new("tests/data/boundary_network.osm"));
+    let node_id = *osm_data.node_map.keys().min().unwrap();
+    osm_data
+        .node_map
+        .get_mut(&node_id)
+        .unwrap()
+        .tags
+        .insert("name", r#"Café "De Zwaan" & <Co>"#);
+
+    osm_data
+        .relation_map
+        .get_mut(&400)
+        .unwrap()
+        .members
+        .push(member(MemberType::Node, 99999));
+    for (relation_id, member) in [
+        (401, member(MemberType::Way, 999)),
+        (402, member(MemberType::Relation, 401)),
+    ] {
+        osm_data.relation_map.insert(
+            relation_id,
+            Relation {
+                id: relation_id,
+                members: vec![member],
+                tags: Tags::new(),
+            },
+        );
+    }
+
+    osm_data
+}
+
+#[test]
+fn xml_export_round_trips_through_parse_xml() {
+    let osm_data = boundary_network();
+    let file_path = export_path("boundary_network.osm");
+    osm_data.export_osm(&file_path);
+
+    let (node_map, way_map, relation_map, _, _) = parse_xml(&file_path);
+
+    assert_eq!(node_map.len(), osm_data.node_map.len());
+    for (node_id, node) in osm_data.node_map.iter() {
+        let exported_node = &node_map[node_id];
+        assert_eq!(exported_node.tags, node.tags);
+        assert!((exported_node.coordinate.x() - node.coordinate.x()).abs() < 1e-7);
+        assert!((exported_node.coordinate.y() - node.coordinate.y()).abs() < 1e-7);
+    }
+
+    assert_eq!(way_map.len(), osm_data.way_map.len());
+    for (way_id, way) in osm_data.way_map.iter() {
+        assert_eq!(way_map[way_id].node_ids, way.node_ids);
+        assert_eq!(way_map[way_id].tags, way.tags);
+    }
+
+    assert_eq!(relation_map.len(), 1);
+    let relation = &relation_map[&400];
+    assert_eq!(relation.tags, osm_data.relation_map[&400].tags);
+    let roles: Vec<&str> = relation
+        .members
+        .iter()
+        .map(|member| member.role.as_str())
+        .collect();
+    assert_eq!(roles, ["outer", "outer", "inner"]);
+}
+
+#[test]
+fn xml_export_keeps_metadata() {
+    let options = ParseOptions {
+        keep_metadata: true,
+    };
+    let osm_data =
+        OSMData::new_with_options(Path::new("tests/data/metadata_network.osm"), &options);
+    let file_path = export_path("metadata_network.osm");
+    osm_data.export_osm(&file_path);
+
+    let exported_data = OSMData::new_with_options(&file_path, &options);
+    assert_eq!(exported_data.way_metadata(100), osm_data.way_metadata(100));
+    assert_eq!(exported_data.node_metadata(2), osm_data.node_metadata(2));
+    assert!(exported_data.node_metadata(20).is_none());
+}
+
+#[test]
+fn xml_export_leaves_out_way_pieces() {
+    let mut osm_data = OSMData::new(Path::new("tests/data/clip_network.osm"));
+    osm_data.clip(&ClipArea::BoundingBox(GeographicArea::new(
+        51.559, 51.561, 5.079, 5.091,
+    )));
+    assert!(osm_data.way_map.contains_key(&WAY_PIECE_OFFSET));
+
+    let file_path = export_path("clip_network.osm");
+    osm_data.export_osm(&file_path);
+
+    let (_, way_map, relation_map, _, _) = parse_xml(&file_path);
+    assert_eq!(way_map.keys().copied().collect::<Vec<u64>>(), vec![100]);
+    let members: Vec<u64> = relation_map[&300]
+        .members
+        .iter()
+        .map(|member| member.member_id)
+        .collect();
+    assert_eq!(members, vec![100]);
+}
+
+/// Reads the fields of a protobuf message as (field number, varint or bytes).
+fn fields(mut bytes: &[u8]) -> Vec<(u64, Field<'_>)> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let key = varint(&mut bytes);
+        match key & 7 {
+            0 => fields.push((key >> 3, Field::Varint(varint(&mut bytes)))),
+            2 => {
+                let length = varint(&mut bytes) as usize;
+                fields.push((key >> 3, Field::Bytes(&bytes[..length])));
+                bytes = &bytes[length..];
+            }
+            wire_type => panic!("Unexpected wire type {}", wire_type),
+        }
+    }
+    fields
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn varint(bytes: &mut &[u8]) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[0];
+        *bytes = &bytes[1..];
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+fn bytes_of(fields: &[(u64, Field<'_>)], number: u64) -> Vec<Vec<u8>> {
+    fields
+        .iter()
+        .filter_map(|(field_number, field)| match field {
+            Field::Bytes(bytes) if *field_number == number => Some(bytes.to_vec()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn deltas(mut bytes: &[u8]) -> Vec<i64> {
+    let mut value = 0;
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        let zigzag = varint(&mut bytes);
+        value += (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        values.push(value);
+    }
+    values
+}
+
+fn packed(mut bytes: &[u8]) -> Vec<u64> {
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        values.push(varint(&mut bytes));
+    }
+    values
+}
+
+/// The type and inflated contents of every blob in a PBF file.
+fn read_blocks(file_path: &Path) -> Vec<(String, Vec<u8>)> {
+    let file = read(file_path).expect("Failed to read export");
+    let mut remaining = file.as_slice();
+    let mut blocks: Vec<(String, Vec<u8>)> = Vec::new();
+
+    while !remaining.is_empty() {
+        let header_length = u32::from_be_bytes(remaining[..4].try_into().unwrap()) as usize;
+        let blob_header = fields(&remaining[4..4 + header_length]);
+        let blob_type = String::from_utf8(bytes_of(&blob_header, 1).remove(0)).unwrap();
+        let Some((_, Field::Varint(data_size))) =
+            blob_header.iter().find(|(number, _)| *number == 3)
+        else {
+            panic!("Blob header without a data size");
+        };
+
+        let blob_start = 4 + header_length;
+        let blob = fields(&remaining[blob_start..blob_start + *data_size as usize]);
+        let mut contents = Vec::new();
+        ZlibDecoder::new(bytes_of(&blob, 3)[0].as_slice())
+            .read_to_end(&mut contents)
+            .expect("Failed to inflate blob");
+
+        blocks.push((blob_type, contents));
+        remaining = &remaining[blob_start + *data_size as usize..];
+    }
+
+    blocks
+}
+
+fn block_strings(block: &[(u64, Field<'_>)]) -> Vec<String> {
+    let string_table = bytes_of(block, 1).remove(0);
+    bytes_of(&fields(&string_table), 1)
+        .into_iter()
+        .map(|string| String::from_utf8(string).unwrap())
+        .collect()
+}
+
+#[test]
+fn pbf_export_writes_blocks_with_nodes_ways_and_relations() {
+    let osm_data = boundary_network();
+    let file_path = export_path("boundary_network.osm.pbf");
+    osm_data.export_osm(&file_path);
+
+    let blocks = read_blocks(&file_path);
+
+    assert_eq!(blocks[0].0, "OSMHeader");
+    let header_block = fields(&blocks[0].1);
+    assert!(bytes_of(&header_block, 4).contains(&b"DenseNodes".to_vec()));
+
+    let mut node_ids = Vec::new();
+    let mut way_refs: HashMap<i64, Vec<i64>> = HashMap::new();
+    let mut relations = Vec::new();
+    let mut strings = Vec::new();
+
+    for (blob_type, contents) in blocks[1..].iter() {
+        assert_eq!(blob_type, "OSMData");
+        let block = fields(contents);
+        let block_strings = block_strings(&block);
+
+        for group in bytes_of(&block, 2) {
+            let group = fields(&group);
+            for dense in bytes_of(&group, 2) {
+                let dense = fields(&dense);
+                node_ids.extend(deltas(&bytes_of(&dense, 1)[0]));
+            }
+            for way in bytes_of(&group, 3) {
+                let way = fields(&way);
+                let Some((_, Field::Varint(way_id))) = way.first() else {
+                    panic!("Way without an ID");
+                };
+                way_refs.insert(*way_id as i64, deltas(&bytes_of(&way, 8)[0]));
+            }
+            for relation in bytes_of(&group, 4) {
+                let relation = fields(&relation);
+                let Some((_, Field::Varint(relation_id))) = relation.first() else {
+                    panic!("Relation without an ID");
+                };
+                let roles: Vec<String> = packed(&bytes_of(&relation, 8)[0])
+                    .into_iter()
+                    .map(|index| block_strings[index as usize].clone())
+                    .collect();
+                let member_ids = deltas(&bytes_of(&relation, 9)[0]);
+                let member_types = packed(&bytes_of(&relation, 10)[0]);
+                relations.push((*relation_id, roles, member_ids, member_types));
+            }
+        }
+
+        strings.extend(block_strings);
+    }
+
+    let mut expected_node_ids: Vec<i64> = osm_data.node_map.keys().map(|id| *id as i64).collect();
+    expected_node_ids.sort_unstable();
+    assert_eq!(node_ids, expected_node_ids);
+
+    assert_eq!(way_refs.len(), osm_data.way_map.len());
+    for (way_id, way) in osm_data.way_map.iter() {
+        let node_ids: Vec<i64> = way.node_ids.iter().map(|id| *id as i64).collect();
+        assert_eq!(way_refs[&(*way_id as i64)], node_ids);
+    }
+
+    // The missing node member is dropped, as are relations 401 and 402.
+    assert_eq!(
+        relations,
+        vec![(
+            400,
+            vec![
+                "outer".to_string(),
+                "outer".to_string(),
+                "inner".to_string()
+            ],
+            vec![300, 301, 302],
+            vec![1, 1, 1],
+        )]
+    );
+    assert!(strings
+        .iter()
+        .any(|string| string == r#"Café "De Zwaan" & <Co>"#));
+    assert!(strings.iter().any(|string| string == "administrative"));
+}
+
+#[test]
+fn pbf_export_writes_dense_info() {
+    let options = ParseOptions {
+        keep_metadata: true,
+    };
+    let osm_data =
+        OSMData::new_with_options(Path::new("tests/data/metadata_network.osm"), &options);
+    let file_path = export_path("metadata_network.osm.pbf");
+    osm_data.export_osm(&file_path);
+
+    let blocks = read_blocks(&file_path);
+    let block = fields(&blocks[1].1);
+    let strings = block_strings(&block);
+    let group_bytes = bytes_of(&block, 2).remove(0);
+    let group = fields(&group_bytes);
+    let dense_bytes = bytes_of(&group, 2).remove(0);
+    let dense = fields(&dense_bytes);
+    let node_ids = deltas(&bytes_of(&dense, 1)[0]);
+    let dense_info_bytes = bytes_of(&dense, 5).remove(0);
+    let dense_info = fields(&dense_info_bytes);
+
+    let versions = packed(&bytes_of(&dense_info, 1)[0]);
+    let timestamps = deltas(&bytes_of(&dense_info, 2)[0]);
+    let changesets = deltas(&bytes_of(&dense_info, 3)[0]);
+    let uids = deltas(&bytes_of(&dense_info, 4)[0]);
+    let users = deltas(&bytes_of(&dense_info, 5)[0]);
+    assert_eq!(versions.len(), node_ids.len());
+
+    for (index, node_id) in node_ids.iter().enumerate() {
+        match osm_data.node_metadata(*node_id as u64) {
+            Some(metadata) => {
+                assert_eq!(Some(versions[index] as u32), metadata.version);
+                assert_eq!(
+                    Some(timestamps[index]),
+                    metadata.timestamp.map(|timestamp| timestamp.timestamp())
+                );
+                assert_eq!(Some(changesets[index] as u64), metadata.changeset);
+                assert_eq!(Some(uids[index] as u64), metadata.uid);
+                assert_eq!(
+                    Some(&strings[users[index] as usize]),
+                    metadata.user.as_ref()
+                );
+            }
+            None => {
+                assert_eq!((versions[index], timestamps[index]), (0, 0));
+                assert_eq!(users[index], 0);
+            }
+        }
+    }
+    assert_eq!(timestamps[0], 1682935200);
+}
@@ -0,0 +1,99 @@
+use chrono::{Duration, TimeZone, Utc};
+use geo::{Coord, LineString};
+use osm_rust::{
+    data_handling::OSMData,
+    path_finding::{path_finding::path_finding, TransportMode},
+    public_transport::{DepartureData, PublicTransportResult},
+    route_manager::{Route, RouteComponent},
+    utils::polylines::{decode_polyline, encode_polyline},
+};
+
+mod common;
+use common::instructions_network;
+
+fn network_and_route() -> (OSMData, Route) {
+    let osm_data = instructions_network();
+
+    let path_result =
+        path_finding(&osm_data, 10, 18, &TransportMode::Bike(5.0)).expect("Failed to find path");
+    // The track of a public transport leg is not known; it runs straight from station to station.
+    let station_time = Utc.with_ymd_and_hms(2024, 10, 21, 8, 0, 0).unwrap();
+    let departure = DepartureData {
+        time: station_time + Duration::seconds(120),
+        duration: 900.0,
+        direction: "Noordstad".to_string(),
+        ..DepartureData::default()
+    };
+    let train = PublicTransportResult::new(18, 12, &departure, station_time);
+
+    let route = Route::new(vec![
+        RouteComponent::Path(path_result),
+        RouteComponent::PublicTransport(train),
+    ]);
+    (osm_data, route)
+}
+
+#[test]
+fn polyline_matches_reference_encoding() {
+    let line_string = LineString::from(vec![
+        Coord { x: -120.2, y: 38.5 },
+        Coord {
+            x: -120.95,
+            y: 40.7,
+        },
+        Coord {
+            x: -126.453,
+            y: 43.252,
+        },
+    ]);
+
+    let encoded = encode_polyline(&line_string, 5);
+    assert_eq!(encoded, "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+    assert_eq!(decode_polyline(&encoded, 5).unwrap(), line_string);
+    assert!(decode_polyline("_p~iF", 5).is_err());
+}
+
+#[test]
+fn route_geojson_has_a_feature_per_component() {
+    let (osm_data, route) = network_and_route();
+    let geojson = route.to_geojson(&osm_data, &TransportMode::Bike(5.0));
+
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(features.len(), 2);
+
+    assert_eq!(features[0]["properties"]["mode"], "bike");
+    assert_eq!(
+        features[0]["geometry"]["coordinates"]
+            .as_array()
+            .unwrap()
+            .len(),
+        6
+    );
+    assert!(features[0]["properties"]["length"].as_f64().unwrap() > 0.0);
+
+    assert_eq!(features[1]["properties"]["mode"], "train");
+    assert_eq!(features[1]["properties"]["duration"], 1020.0);
+    assert!(features[1]["properties"]["length"].is_null());
+}
+
+#[test]
+fn route_gpx_kml_and_polyline_follow_the_nodes() {
+    let (osm_data, route) = network_and_route();
+
+    let gpx = route.to_gpx(&osm_data);
+    assert_eq!(gpx.matches("<trkseg>").count(), 2);
+    assert_eq!(gpx.matches("<trkpt ").count(), 8);
+    assert!(gpx.contains(r#"<trkpt lat="51.5600000" lon="5.0800000">"#));
+
+    let kml = route.to_kml(&osm_data, &TransportMode::Bike(5.0));
+    assert_eq!(kml.matches("<Placemark>").count(), 2);
+    assert!(kml.contains("5.0800000,51.5600000 5.0820000,51.5600000"));
+
+    // The point shared by the bike path and the train is only included once.
+    let polyline = decode_polyline(&route.to_polyline(&osm_data), 5).unwrap();
+    assert_eq!(polyline.0.len(), 7);
+    for (coordinate, expected) in polyline.coords().zip(route.line_string(&osm_data).coords()) {
+        assert!((coordinate.x - expected.x).abs() < 1e-5);
+        assert!((coordinate.y - expected.y).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{Duration, TimeZone, Utc};
+use osm_rust::{
+    data_handling::OSMData,
+    path_finding::{path_finding::path_finding, TransportMode},
+    public_transport::{DepartureData, FixtureProvider, PublicTransportResult, TransitOptions},
+    route_manager::{
+        transport_options::search_routes, Route, RouteComponent, RouteCriterion, RouteSearchResult,
+        RouteTime, RouteWeights,
+    },
+};
+
+mod common;
+use common::instructions_network;
+
+/// A short bike ride followed by a train leaving after `waiting_time` seconds, with `transfers`.
+fn train_route(osm_data: &OSMData, waiting_time: i64, transfers: i64) -> Route {
+    let short_ride =
+        path_finding(osm_data, 10, 11, &TransportMode::Bike(5.0)).expect("Failed to find path");
+
+    let station_time = Utc.with_ymd_and_hms(2024, 10, 21, 8, 0, 0).unwrap();
+    let departure = DepartureData {
+        time: station_time + Duration::seconds(waiting_time),
+        duration: 60.0,
+        direction: "Noordstad".to_string(),
+        transfers,
+        ..DepartureData::default()
+    };
+    let train = PublicTransportResult::new(11, 18, &departure, station_time);
+
+    Route::new(vec![
+        RouteComponent::Path(short_ride),
+        RouteComponent::PublicTransport(train),
+    ])
+}
+
+/// A long bike ride, and a short one followed by a train with a transfer.
+fn candidate_routes(osm_data: &OSMData) -> (Route, Route) {
+    let long_ride =
+        path_finding(osm_data, 10, 18, &TransportMode::Bike(5.0)).expect("Failed to find path");
+    (
+        Route::new(vec![RouteComponent::Path(long_ride)]),
+        train_route(osm_data, 60, 1),
+    )
+}
+
+#[test]
+fn routes_are_sorted_by_the_criterion() {
+    let osm_data = instructions_network();
+    let (bike, train) = candidate_routes(&osm_data);
+    assert_eq!(train.transfers(), 1);
+    assert!(bike.road_distance() > train.road_distance());
+
+    let mut search_result = RouteSearchResult {
+        routes: vec![bike.clone(), train.clone()],
+        errors: Vec::new(),
+    };
+
+    search_result.rank(RouteCriterion::WalkingDistance);
+    assert_eq!(search_result.best(), Some(&train));
+
+    search_result.rank(RouteCriterion::Transfers);
+    assert_eq!(search_result.best(), Some(&bike));
+    assert_eq!(search_result.routes, vec![bike, train]);
+}
+
+#[test]
+fn pareto_selection_keeps_routes_that_are_better_somewhere() {
+    let osm_data = instructions_network();
+    let (bike, train) = candidate_routes(&osm_data);
+    // Waits longer for a train with more transfers, and is beaten by the other train everywhere.
+    let slow_train = train_route(&osm_data, 600, 2);
+    assert!(bike.total_duration() < train.total_duration());
+    assert!(train.costs().dominates(&slow_train.costs()));
+
+    let mut search_result = RouteSearchResult {
+        routes: vec![slow_train, train.clone(), bike.clone()],
+        errors: Vec::new(),
+    };
+    search_result.rank(RouteCriterion::Pareto(None));
+    assert_eq!(search_result.routes, vec![bike.clone(), train.clone()]);
+
+    // Someone who would rather not cycle picks the train.
+    let weights = RouteWeights {
+        active_distance: 1.0,
+        ..RouteWeights::default()
+    };
+    search_result.rank(RouteCriterion::Pareto(Some(weights)));
+    assert_eq!(search_result.routes, vec![train, bike]);
+}
+
+#[test]
+fn search_without_stations_returns_the_direct_route() {
+    let osm_data = instructions_network();
+    let time = Utc.with_ymd_and_hms(2024, 10, 21, 8, 0, 0).unwrap();
+
+    let search_result = search_routes(
+        &osm_data,
+        &TransportMode::Walk(1.5),
+        RouteTime::DepartAt(time),
+        10,
+        12,
+        &TransitOptions {
+            provider: &FixtureProvider::default(),
+            minimum_distance_to_station: 5000.0,
+        },
+        Some(RouteCriterion::Duration),
+    );
+
+    assert!(search_result.errors.is_empty());
+    assert_eq!(search_result.routes.len(), 1);
+    assert_eq!(search_result.best().unwrap().departure(), Some(time));
+}
+
+#[test]
+fn arrive_by_search_leaves_as_late_as_possible() {
+    let osm_data = instructions_network();
+    let time = Utc.with_ymd_and_hms(2024, 10, 21, 9, 0, 0).unwrap();
+
+    let search_result = search_routes(
+        &osm_data,
+        &TransportMode::Walk(1.5),
+        RouteTime::ArriveBy(time),
+        10,
+        12,
+        &TransitOptions {
+            provider: &FixtureProvider::default(),
+            minimum_distance_to_station: 5000.0,
+        },
+        None,
+    );
+
+    let route = search_result.best().expect("No route found");
+    assert_eq!(route.arrival(), Some(time));
+    assert!(route.departure().unwrap() < time);
+    assert!(
+        (route.total_duration() - (time - route.departure().unwrap()).num_seconds() as f64).abs()
+            < 1.0
+    );
+}
+
+#[test]
+fn arrival_schedule_catches_the_train_without_waiting() {
+    let osm_data = instructions_network();
+    let mut route = train_route(&osm_data, 600, 0);
+    let RouteComponent::PublicTransport(train) = route.components[1].clone() else {
+        panic!("Expected the train second");
+    };
+
+    route.schedule_arrival(train.arrival + Duration::minutes(10));
+    assert!(route.is_feasible());
+    assert_eq!(route.components[0].arrival(), train.departure);
+    assert_eq!(route.arrival(), Some(train.arrival));
+    assert_eq!(route.waiting_time(), 0.0);
+
+    // A later train that still makes it lets the traveller leave later.
+    let mut later_route = train_route(&osm_data, 900, 0);
+    later_route.schedule_arrival(train.arrival + Duration::minutes(10));
+
+    let mut search_result = RouteSearchResult {
+        routes: vec![route, later_route.clone()],
+        errors: Vec::new(),
+    };
+    search_result.rank(RouteCriterion::LatestDeparture);
+    assert_eq!(search_result.best(), Some(&later_route));
+}
@@ -0,0 +1,68 @@
+use osm_rust::{
+    data_handling::OSMData,
+    path_finding::{
+        path_finding::path_finding_with_options, CancellationToken, PathFindingError, PathOptions,
+        SearchLimits, TransportMode,
+    },
+    utils::filtering_utilities::{filter_amenities, filter_highways},
+};
+use std::time::Instant;
+
+mod common;
+use common::filtered_map;
+
+fn search(osm_data: &OSMData, target_node_id: u64, limits: SearchLimits) -> PathFindingError {
+    let options = PathOptions {
+        limits,
+        ..PathOptions::default()
+    };
+
+    path_finding_with_options(osm_data, 1, target_node_id, &TransportMode::Car, &options)
+        .expect_err("Search should have failed")
+}
+
+#[test]
+fn search_limits() {
+    let osm_data = filtered_map(
+        "cycling_network.osm",
+        vec![filter_highways(), filter_amenities()],
+    );
+
+    // The school is not on any road, so it can never be reached.
+    assert_eq!(
+        search(&osm_data, 6, SearchLimits::default()),
+        PathFindingError::NoPath
+    );
+
+    let max_settled_nodes = SearchLimits {
+        max_settled_nodes: Some(1),
+        ..SearchLimits::default()
+    };
+    assert_eq!(
+        search(&osm_data, 6, max_settled_nodes),
+        PathFindingError::SettledNodeLimit(1)
+    );
+
+    let max_cost = SearchLimits {
+        max_cost: Some(1.0),
+        ..SearchLimits::default()
+    };
+    assert_eq!(
+        search(&osm_data, 2, max_cost),
+        PathFindingError::CostLimit(1.0)
+    );
+
+    let deadline = SearchLimits {
+        deadline: Some(Instant::now()),
+        ..SearchLimits::default()
+    };
+    assert_eq!(search(&osm_data, 2, deadline), PathFindingError::Timeout);
+
+    let cancellation_token = CancellationToken::new();
+    cancellation_token.cancel();
+    let cancelled = SearchLimits {
+        cancellation_token: Some(cancellation_token),
+        ..SearchLimits::default()
+    };
+    assert_eq!(search(&osm_data, 2, cancelled), PathFindingError::Cancelled);
+}
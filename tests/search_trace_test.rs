@@ -0,0 +1,53 @@
+use osm_rust::path_finding::{
+    path_finding::path_finding_with_observer, PathOptions, SearchTrace, TransportMode,
+};
+use std::fs;
+
+mod common;
+use common::{instructions_network, test_directory};
+
+#[test]
+fn search_trace_export() {
+    let osm_data = instructions_network();
+
+    let mut search_trace = SearchTrace::new();
+    path_finding_with_observer(
+        &osm_data,
+        10,
+        18,
+        &TransportMode::Car,
+        &PathOptions::default(),
+        &mut search_trace,
+    )
+    .expect("Failed to find path");
+
+    let first_settled_node = search_trace.settled_nodes[0];
+    assert_eq!(first_settled_node.node_id, 10);
+    assert_eq!(first_settled_node.parent_node_id, 10);
+    assert_eq!(first_settled_node.cost, 0.0);
+
+    let directory = test_directory("search_trace_test");
+
+    let csv_path = directory.join("search_coordinates.csv");
+    search_trace
+        .write_csv(&osm_data, &csv_path)
+        .expect("Failed to write CSV");
+    let csv_contents = fs::read_to_string(&csv_path).expect("Failed to read CSV");
+    assert_eq!(
+        csv_contents.lines().count(),
+        search_trace.settled_nodes.len() + 1
+    );
+
+    let geojson_path = directory.join("search_trace.geojson");
+    search_trace
+        .write_geojson(&osm_data, &geojson_path)
+        .expect("Failed to write GeoJSON");
+    let geojson: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&geojson_path).expect("Failed to read GeoJSON"))
+            .expect("Invalid GeoJSON");
+    assert_eq!(geojson["type"], "FeatureCollection");
+    assert_eq!(
+        geojson["features"].as_array().unwrap().len(),
+        search_trace.settled_nodes.len()
+    );
+}
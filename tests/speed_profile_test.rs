@@ -0,0 +1,93 @@
+use chrono::{TimeZone, Utc};
+use osm_rust::{
+    data_handling::OSMData,
+    path_finding::{
+        path_finding::{direct_route, path_finding_with_options},
+        PathOptions, TransportMode,
+    },
+    route_manager::RouteTime,
+};
+use std::fs;
+
+mod common;
+use common::{cycling_network, test_directory};
+
+/// The cycling network with the primary road slowed down to 20 km/h on weekday mornings.
+fn rush_hour_network() -> OSMData {
+    let profile_path = test_directory("speed_profile_test").join("speed_profiles.csv");
+    fs::write(
+        &profile_path,
+        "highway,way_id,days,start,end,speed\nprimary,,mon-fri,07:00,09:00,20\n",
+    )
+    .expect("Failed to write speed profiles");
+
+    let mut osm_data = cycling_network();
+    osm_data
+        .load_speed_profiles(&profile_path, 0)
+        .expect("Failed to load speed profiles");
+    osm_data
+}
+
+#[test]
+fn rush_hour_slows_car_routes() {
+    let osm_data = rush_hour_network();
+
+    let path_time = |hour: u32| {
+        let options = PathOptions {
+            departure_time: Some(Utc.with_ymd_and_hms(2024, 9, 2, hour, 0, 0).unwrap()),
+            ..PathOptions::default()
+        };
+        path_finding_with_options(&osm_data, 1, 3, &TransportMode::Car, &options)
+            .expect("Failed to find path")
+            .path_time
+    };
+
+    let rush_hour_time = path_time(8);
+    let midday_time = path_time(12);
+
+    // 347m at 20 km/h during rush hour, at the 80 km/h speed limit otherwise.
+    assert!((rush_hour_time - 62.4).abs() < 1.0, "{}", rush_hour_time);
+    assert!((midday_time - 15.6).abs() < 1.0, "{}", midday_time);
+}
+
+#[test]
+fn arrive_by_car_routes_use_the_speeds_before_the_arrival() {
+    let osm_data = rush_hour_network();
+
+    let road_time = |hour: u32, minute: u32| {
+        let arrival = Utc.with_ymd_and_hms(2024, 9, 2, hour, minute, 0).unwrap();
+        let route = direct_route(
+            &osm_data,
+            &TransportMode::Car,
+            RouteTime::ArriveBy(arrival),
+            1,
+            3,
+        )
+        .expect("Failed to find route");
+
+        assert_eq!(route.components.len(), 3);
+        assert_eq!(route.arrival(), Some(arrival));
+        assert!(route.is_feasible());
+        assert!(
+            (route.total_duration()
+                - (arrival - route.departure().unwrap()).num_milliseconds() as f64 / 1000.0)
+                .abs()
+                < 0.01
+        );
+
+        route.components[1].duration()
+    };
+
+    let rush_hour_time = road_time(8, 30);
+    let midday_time = road_time(12, 0);
+    assert!((rush_hour_time - 62.4).abs() < 1.0, "{}", rush_hour_time);
+    assert!((midday_time - 15.6).abs() < 1.0, "{}", midday_time);
+
+    // Arriving just after 9 means driving in the rush hour.
+    let after_rush_hour_time = road_time(9, 0);
+    assert!(
+        (after_rush_hour_time - 62.4).abs() < 1.0,
+        "{}",
+        after_rush_hour_time
+    );
+}
@@ -0,0 +1,107 @@
+use osm_rust::{
+    data_handling::OSMData,
+    osm_parsing::Tags,
+    utils::{file_handling::read_cache_header, string_table::Symbol},
+};
+use std::path::Path;
+
+mod common;
+use common::{instructions_network, island_network, test_directory};
+
+#[test]
+fn tags_are_interned_and_looked_up_by_key() {
+    let mut tags: Tags = [("highway", "residential"), ("maxspeed", "30")]
+        .into_iter()
+        .collect();
+
+    assert_eq!(tags.get("maxspeed"), Some("30"));
+    assert_eq!(tags.get("name"), None);
+    assert!(tags.contains_key("highway"));
+
+    tags.insert("maxspeed", "50");
+    assert_eq!(tags.get("maxspeed"), Some("50"));
+    assert_eq!(tags.len(), 2);
+
+    let other_tags: Tags = [("highway", "residential")].into_iter().collect();
+    assert_eq!(
+        other_tags.get_symbol(Symbol::intern("highway")),
+        Some(Symbol::intern("residential"))
+    );
+
+    assert!(tags.remove("maxspeed"));
+    assert!(!tags.remove("maxspeed"));
+    assert_eq!(tags, other_tags);
+
+    let json = serde_json::to_string(&tags).expect("Failed to serialize");
+    assert_eq!(json, r#"{"highway":"residential"}"#);
+    assert_eq!(serde_json::from_str::<Tags>(&json).unwrap(), tags);
+}
+
+#[test]
+fn strings_interned_on_other_threads_are_found() {
+    let symbols: Vec<Symbol> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|thread| {
+                scope.spawn(move || {
+                    (0..100)
+                        .map(|index| Symbol::intern(&format!("thread {} string {}", thread, index)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    assert_eq!(
+        Symbol::lookup("thread 3 string 99"),
+        symbols.last().copied()
+    );
+    assert_eq!(symbols[150].as_str(), "thread 1 string 50");
+}
+
+#[test]
+fn version_1_cache_with_string_tags_loads() {
+    // Written by the first versioned cache format, before tags were stored as string IDs.
+    let file_path = Path::new("tests/data/cache_version_1.hashmap");
+    assert_eq!(
+        read_cache_header(file_path)
+            .expect("Failed to read header")
+            .format_version,
+        1
+    );
+
+    let loaded_data = OSMData::new(file_path);
+    let osm_data = island_network();
+
+    assert_eq!(loaded_data.way_map.len(), osm_data.way_map.len());
+    for (way_id, way) in osm_data.way_map.iter() {
+        let mut loaded_tags: Vec<(&str, &str)> = loaded_data.way_map[way_id].tags.iter().collect();
+        let mut tags: Vec<(&str, &str)> = way.tags.iter().collect();
+        loaded_tags.sort();
+        tags.sort();
+        assert_eq!(loaded_tags, tags);
+    }
+    assert_eq!(
+        loaded_data.way_map[&103].tags.get("highway"),
+        Some("living_street")
+    );
+}
+
+#[test]
+fn cache_round_trip_keeps_tags() {
+    let osm_data = instructions_network();
+    let file_path = test_directory("tags_test").join("map.hashmap");
+    osm_data.save_hashmaps(&file_path);
+
+    let loaded_data = OSMData::new(&file_path);
+    for (way_id, way) in osm_data.way_map.iter() {
+        assert_eq!(loaded_data.way_map[way_id].tags, way.tags);
+    }
+    assert_eq!(
+        loaded_data.way_map[&200].tags.get("name"),
+        Some("Hoofdstraat")
+    );
+}
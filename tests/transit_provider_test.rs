@@ -0,0 +1,133 @@
+use chrono::{DateTime, TimeZone, Utc};
+use osm_rust::{
+    data_handling::OSMData,
+    path_finding::TransportMode,
+    public_transport::{FixtureProvider, NsApi, StandInServer, TransitOptions, TransitProvider},
+    route_manager::{
+        transport_options::search_routes, RouteCandidate, RouteComponent, RouteSearchResult,
+        RouteTime,
+    },
+};
+use std::path::Path;
+
+mod common;
+use common::transit_network;
+
+fn fixture_provider() -> FixtureProvider {
+    FixtureProvider::from_file(Path::new("tests/data/transit_trips.json"))
+        .expect("Failed to read trips")
+}
+
+fn search(
+    osm_data: &OSMData,
+    provider: &dyn TransitProvider,
+    time: RouteTime,
+) -> RouteSearchResult {
+    let transit_options = TransitOptions {
+        provider,
+        minimum_distance_to_station: 2000.0,
+    };
+    search_routes(
+        osm_data,
+        &TransportMode::Walk(1.5),
+        time,
+        1,
+        6,
+        &transit_options,
+        None,
+    )
+}
+
+fn train_departure(search_result: &RouteSearchResult) -> DateTime<Utc> {
+    let route = search_result.best().expect("No route found");
+    assert!(route.is_feasible());
+    match &route.components[3] {
+        RouteComponent::PublicTransport(public_transport) => public_transport.departure,
+        RouteComponent::Path(_) => panic!("Expected the train fourth"),
+    }
+}
+
+#[test]
+fn planner_takes_the_first_fixture_train_it_can_catch() {
+    let osm_data = transit_network();
+    let departure = Utc.with_ymd_and_hms(2024, 10, 21, 8, 0, 0).unwrap();
+    let search_result = search(
+        &osm_data,
+        &fixture_provider(),
+        RouteTime::DepartAt(departure),
+    );
+
+    // The train beats walking all the way.
+    assert_eq!(search_result.routes.len(), 2);
+    assert!(search_result.errors.is_empty());
+    assert_eq!(
+        train_departure(&search_result),
+        Utc.with_ymd_and_hms(2024, 10, 21, 8, 10, 0).unwrap()
+    );
+}
+
+#[test]
+fn planner_takes_the_last_fixture_train_that_arrives_on_time() {
+    let osm_data = transit_network();
+    let arrival = Utc.with_ymd_and_hms(2024, 10, 21, 9, 20, 0).unwrap();
+    let search_result = search(&osm_data, &fixture_provider(), RouteTime::ArriveBy(arrival));
+
+    assert_eq!(
+        train_departure(&search_result),
+        Utc.with_ymd_and_hms(2024, 10, 21, 8, 40, 0).unwrap()
+    );
+    assert!(search_result.best().unwrap().arrival().unwrap() <= arrival);
+}
+
+#[test]
+fn stand_in_server_answers_like_the_ns_api() {
+    let server = StandInServer::start(fixture_provider()).expect("Failed to start the stand-in");
+    let ns_api = NsApi {
+        base_url: server.url(),
+        subscription_key: "stand-in".to_string(),
+    };
+    let time = Utc.with_ymd_and_hms(2024, 10, 21, 8, 30, 0).unwrap();
+
+    let departures = ns_api
+        .departures("Stationsdorp", "Eindhalte", &time, false)
+        .expect("Failed to query the stand-in");
+    assert_eq!(
+        departures,
+        fixture_provider()
+            .departures("Stationsdorp", "Eindhalte", &time, false)
+            .unwrap()
+    );
+    assert_eq!(departures.len(), 1);
+    assert_eq!(departures[0].intermediate_stations, vec!["Overstapstad"]);
+
+    let osm_data = transit_network();
+    let departure = Utc.with_ymd_and_hms(2024, 10, 21, 8, 0, 0).unwrap();
+    assert_eq!(
+        search(&osm_data, &ns_api, RouteTime::DepartAt(departure)),
+        search(
+            &osm_data,
+            &fixture_provider(),
+            RouteTime::DepartAt(departure)
+        )
+    );
+}
+
+#[test]
+fn missing_trips_are_reported_per_candidate() {
+    let osm_data = transit_network();
+    let departure = Utc.with_ymd_and_hms(2024, 10, 21, 9, 0, 0).unwrap();
+    let search_result = search(
+        &osm_data,
+        &fixture_provider(),
+        RouteTime::DepartAt(departure),
+    );
+
+    assert_eq!(search_result.routes.len(), 1);
+    assert_eq!(
+        search_result.errors[0].candidate,
+        RouteCandidate::PublicTransport {
+            start_station_id: 50,
+            target_station_id: 60,
+        }
+    );
+}
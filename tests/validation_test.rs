@@ -0,0 +1,101 @@
+use osm_rust::{
+    data_handling::{validation::WAY_PIECE_OFFSET, OSMData, ValidationIssue},
+    utils::filtering_utilities::filter_highways,
+};
+use std::path::Path;
+
+mod common;
+use common::filtered_map;
+
+#[test]
+fn reports_all_issue_kinds() {
+    let osm_data = OSMData::new(Path::new("tests/data/invalid_network.osm"));
+    let report = osm_data.validate();
+
+    assert!(report
+        .issues
+        .contains(&ValidationIssue::DanglingNodeReference {
+            way_id: 100,
+            node_id: 99
+        }));
+    assert!(report
+        .issues
+        .contains(&ValidationIssue::DuplicateConsecutiveNode {
+            way_id: 100,
+            node_id: 2
+        }));
+    assert!(report.issues.contains(&ValidationIssue::ZeroLengthSegment {
+        way_id: 100,
+        from_node_id: 2,
+        to_node_id: 3
+    }));
+    assert!(report
+        .issues
+        .contains(&ValidationIssue::UnparseableMaxSpeed {
+            way_id: 100,
+            max_speed: "fast".to_string()
+        }));
+    assert!(report.issues.contains(&ValidationIssue::DoublesBack {
+        way_id: 101,
+        node_id: 4
+    }));
+    assert!(report
+        .issues
+        .contains(&ValidationIssue::UntaggedWay { way_id: 102 }));
+
+    assert_eq!(report.summary()["dangling_node_reference"], 2);
+    assert_eq!(report.issues.len(), 7);
+}
+
+#[test]
+fn repair_fixes_repairable_issues() {
+    let mut osm_data = OSMData::new(Path::new("tests/data/invalid_network.osm"));
+    osm_data.repair();
+
+    assert_eq!(osm_data.way_map[&100].node_ids, vec![1, 2, 3]);
+    assert_eq!(osm_data.way_map[&101].node_ids, vec![3, 4]);
+    assert!(!osm_data.way_map.contains_key(&103));
+
+    let remaining_issues = osm_data.validate().issues;
+    assert!(remaining_issues.iter().all(|issue| !issue.is_repairable()));
+}
+
+#[test]
+fn repair_never_joins_nodes_that_were_not_adjacent() {
+    let mut osm_data = OSMData::new(Path::new("tests/data/broken_ways.osm"));
+    let report = osm_data.repair();
+
+    assert!(report.issues.contains(&ValidationIssue::DoublesBack {
+        way_id: 201,
+        node_id: 3
+    }));
+
+    // 5-1-97-2-3 with 97 missing is cut into 5-1 and 2-3 instead of becoming 5-1-2-3.
+    assert_eq!(osm_data.way_map[&200].node_ids, vec![5, 1]);
+    let piece_id = WAY_PIECE_OFFSET;
+    assert_eq!(osm_data.way_map[&piece_id].node_ids, vec![2, 3]);
+    assert_eq!(
+        osm_data.way_map[&piece_id].tags,
+        osm_data.way_map[&200].tags
+    );
+    assert_eq!(osm_data.node_map[&1].nodes, vec![5]);
+
+    // 2-3-2-4 turns at 3, so it is cut into 2-3 and 3-2-4.
+    assert_eq!(osm_data.way_map[&201].node_ids, vec![2, 3]);
+    assert_eq!(osm_data.way_map[&(piece_id + 1)].node_ids, vec![3, 2, 4]);
+
+    let members: Vec<(u64, &str)> = osm_data.relation_map[&300]
+        .members
+        .iter()
+        .map(|member| (member.member_id, member.role.as_str()))
+        .collect();
+    assert_eq!(members, vec![(200, "forward"), (piece_id, "forward")]);
+}
+
+#[test]
+fn filtering_tolerates_dangling_references() {
+    let osm_data = filtered_map("invalid_network.osm", vec![filter_highways()]);
+
+    assert!(osm_data.node_map[&5].nodes.is_empty());
+    assert_eq!(osm_data.node_map[&1].nodes, vec![2]);
+}